@@ -0,0 +1,159 @@
+use std::sync::Arc;
+
+use cgmath::{point3, prelude::*, vec3};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::prelude::*;
+use raytracing_1w::{
+    bvh::{BVHNode, FlatBVH, SahOptions, SplitMethod},
+    hittable::Hittable,
+    material::Material,
+    ray::Ray,
+    sphere::Sphere,
+    triangle::Triangle,
+    Float, MyRng,
+};
+
+const RAYS: usize = 1024;
+
+type SceneFn = fn(&mut MyRng) -> Vec<Box<dyn Hittable>>;
+
+/// Small spheres clustered unevenly, like the boxes and spheres of the final scene.
+fn spheres(rng: &mut MyRng) -> Vec<Box<dyn Hittable>> {
+    let material: Arc<Box<dyn Material>> = Arc::new(Box::new(()));
+    (0..10_000)
+        .map(|i| -> Box<dyn Hittable> {
+            let spread = if i % 10 == 0 { 100.0 } else { 10.0 };
+            Box::new(Sphere {
+                center: point3(
+                    rng.gen_range(-spread..spread),
+                    rng.gen_range(-spread..spread),
+                    rng.gen_range(-spread..spread),
+                ),
+                radius: 0.1,
+                material: material.clone(),
+            })
+        })
+        .collect()
+}
+
+/// A bumpy height field of 20k triangles.
+fn triangles(rng: &mut MyRng) -> Vec<Box<dyn Hittable>> {
+    let material: Arc<Box<dyn Material>> = Arc::new(Box::new(()));
+    let n = 100;
+    let heights: Vec<Float> = (0..(n + 1) * (n + 1)).map(|_| rng.gen()).collect();
+    let vertex = |x: usize, z: usize| {
+        point3(
+            x as Float - n as Float / 2.0,
+            heights[z * (n + 1) + x],
+            z as Float - n as Float / 2.0,
+        )
+    };
+
+    let mut triangles: Vec<Box<dyn Hittable>> = Vec::new();
+    for z in 0..n {
+        for x in 0..n {
+            for (v0, v1, v2) in [
+                (vertex(x, z), vertex(x + 1, z), vertex(x, z + 1)),
+                (vertex(x + 1, z), vertex(x + 1, z + 1), vertex(x, z + 1)),
+            ] {
+                triangles.push(Box::new(Triangle {
+                    v0,
+                    v1,
+                    v2,
+                    material: material.clone(),
+                }));
+            }
+        }
+    }
+    triangles
+}
+
+fn rays(rng: &mut MyRng) -> Vec<Ray> {
+    (0..RAYS)
+        .map(|_| Ray {
+            origin: point3(
+                rng.gen_range(-50.0..50.0),
+                rng.gen_range(5.0..50.0),
+                rng.gen_range(-50.0..50.0),
+            ),
+            direction: vec3(
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..0.0),
+                rng.gen_range(-1.0..1.0),
+            )
+            .normalize(),
+            time: 0.0,
+        })
+        .collect()
+}
+
+fn trace(world: &dyn Hittable, rays: &[Ray], rng: &mut MyRng) -> usize {
+    rays.iter()
+        .filter(|ray| world.hit(ray, 0.001, Float::INFINITY, rng).is_some())
+        .count()
+}
+
+fn bench_traversal(c: &mut Criterion) {
+    let scenes: [(&str, SceneFn); 2] = [("spheres", spheres), ("triangles", triangles)];
+    let split_methods = [
+        ("median", SplitMethod::Median),
+        ("sah", SplitMethod::Sah(SahOptions::default())),
+    ];
+
+    let mut rng = MyRng::seed_from_u64(0);
+    let rays = rays(&mut rng);
+
+    for (scene, objects) in scenes.iter() {
+        let mut group = c.benchmark_group(format!("traversal/{}", scene));
+        for (name, split_method) in split_methods.iter() {
+            let tree = BVHNode::with_split_method(
+                objects(&mut MyRng::seed_from_u64(1)),
+                0.0,
+                1.0,
+                *split_method,
+                &mut MyRng::seed_from_u64(2),
+            );
+            group.bench_with_input(BenchmarkId::new("tree", name), &tree, |b, tree| {
+                b.iter(|| trace(black_box(tree), &rays, &mut rng))
+            });
+
+            let flat = FlatBVH::from(BVHNode::with_split_method(
+                objects(&mut MyRng::seed_from_u64(1)),
+                0.0,
+                1.0,
+                *split_method,
+                &mut MyRng::seed_from_u64(2),
+            ));
+            group.bench_with_input(BenchmarkId::new("flat", name), &flat, |b, flat| {
+                b.iter(|| trace(black_box(flat), &rays, &mut rng))
+            });
+        }
+        group.finish();
+    }
+}
+
+fn bench_build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build/triangles");
+    group.sample_size(10);
+    for (name, split_method) in [
+        ("median", SplitMethod::Median),
+        ("sah", SplitMethod::Sah(SahOptions::default())),
+    ] {
+        group.bench_function(name, |b| {
+            let mut rng = MyRng::seed_from_u64(0);
+            b.iter(|| {
+                FlatBVH::from(BVHNode::with_split_method(
+                    triangles(&mut rng),
+                    0.0,
+                    1.0,
+                    split_method,
+                    &mut rng,
+                ))
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_traversal, bench_build);
+criterion_main!(benches);
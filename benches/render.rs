@@ -0,0 +1,32 @@
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::prelude::*;
+use raytracing_1w::{bvh::SplitMethod, scenes::builtin_scene, MyRng, Renderer};
+
+/// Small renders of built-in scenes, dominated by per-bounce shading work rather than setup.
+fn render(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render");
+    group.sample_size(10);
+    // A render takes about 100 ms, so the default 5 s fit too few iterations to average out
+    // run-to-run noise of 10 to 15% on a single core.
+    group.measurement_time(Duration::from_secs(15));
+
+    for name in ["random", "cornell-box", "cornell-smoke"] {
+        let mut rng = MyRng::seed_from_u64(1);
+        let scene = builtin_scene(name, SplitMethod::default(), &mut rng).unwrap();
+        let mut renderer = Renderer::new(&scene.settings);
+        renderer.image_width = 64;
+        renderer.image_height = 64;
+        renderer.samples_per_pixel = 8;
+
+        group.bench_with_input(BenchmarkId::from_parameter(name), &scene, |b, scene| {
+            b.iter(|| renderer.render(scene))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, render);
+criterion_main!(benches);
@@ -1,7 +1,7 @@
 use std::mem::swap;
 
 use crate::{ray::Ray, Float};
-use cgmath::{point3, Point3};
+use cgmath::{point3, EuclideanSpace, Point3};
 
 #[derive(Clone, Copy, Debug)]
 pub struct AABB {
@@ -10,6 +10,15 @@ pub struct AABB {
 }
 
 impl AABB {
+    pub fn centroid(&self) -> Point3<Float> {
+        self.minimum.midpoint(self.maximum)
+    }
+
+    pub fn surface_area(&self) -> Float {
+        let d = self.maximum - self.minimum;
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
     pub fn hit(&self, ray: &Ray, mut t_min: Float, mut t_max: Float) -> bool {
         for a in 0..3 {
             let inv_d = 1.0 / ray.direction[a];
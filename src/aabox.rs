@@ -1,14 +1,15 @@
 use std::sync::Arc;
 
-use cgmath::Point3;
+use cgmath::{point3, Point3, Vector3};
 use rand::Rng;
 
 use crate::{
     aabb::AABB,
     aarect::{XYRect, XZRect, YZRect},
     bvh::BVHNode,
-    hittable::Hittable,
+    hittable::{area_to_solid_angle_pdf, Hittable},
     material::Material,
+    ray::Ray,
     Float, MyRng,
 };
 
@@ -16,6 +17,7 @@ pub struct AABox {
     box_min: Point3<Float>,
     box_max: Point3<Float>,
     sides: BVHNode,
+    material: Arc<Box<dyn Material>>,
 }
 
 impl AABox {
@@ -72,7 +74,7 @@ impl AABox {
                 z0: p0.z,
                 z1: p1.z,
                 k: p0.x,
-                material,
+                material: material.clone(),
             }),
         ];
 
@@ -80,10 +82,19 @@ impl AABox {
             box_min: p0,
             box_max: p1,
             sides: BVHNode::new(sides, 0.0, 1.0, rng),
+            material,
         }
     }
 }
 
+impl AABox {
+    /// Areas of the faces perpendicular to x, y and z.
+    fn face_areas(&self) -> [Float; 3] {
+        let size = self.box_max - self.box_min;
+        [size.y * size.z, size.x * size.z, size.x * size.y]
+    }
+}
+
 impl Hittable for AABox {
     fn hit(
         &self,
@@ -91,7 +102,7 @@ impl Hittable for AABox {
         t_min: Float,
         t_max: Float,
         rng: &mut MyRng,
-    ) -> Option<crate::hittable::HitRecord> {
+    ) -> Option<crate::hittable::HitRecord<'_>> {
         self.sides.hit(ray, t_min, t_max, rng)
     }
 
@@ -101,4 +112,64 @@ impl Hittable for AABox {
             maximum: self.box_max,
         })
     }
+
+    /// Sums over both faces a ray crosses, since either could have been sampled.
+    fn pdf_value(
+        &self,
+        origin: Point3<Float>,
+        v: Vector3<Float>,
+        time: Float,
+        rng: &mut MyRng,
+    ) -> Float {
+        let total_area = 2.0 * self.face_areas().iter().sum::<Float>();
+        let ray = Ray {
+            origin,
+            direction: v,
+            time,
+        };
+
+        let mut pdf = 0.0;
+        let mut t_min = 0.001;
+        while let Some(hit_record) = self.sides.hit(&ray, t_min, Float::INFINITY, rng) {
+            pdf += area_to_solid_angle_pdf(&hit_record, v, total_area);
+            t_min = hit_record.t + 0.001;
+        }
+        pdf
+    }
+
+    fn random(&self, origin: Point3<Float>, _time: Float, rng: &mut MyRng) -> Vector3<Float> {
+        let [area_x, area_y, _] = self.face_areas();
+        let mut point = point3(
+            rng.gen_range(self.box_min.x..=self.box_max.x),
+            rng.gen_range(self.box_min.y..=self.box_max.y),
+            rng.gen_range(self.box_min.z..=self.box_max.z),
+        );
+        let side = if rng.gen() {
+            self.box_max
+        } else {
+            self.box_min
+        };
+
+        let target = rng.gen_range(0.0..=self.face_areas().iter().sum::<Float>());
+        if target < area_x {
+            point.x = side.x;
+        } else if target < area_x + area_y {
+            point.y = side.y;
+        } else {
+            point.z = side.z;
+        }
+        point - origin
+    }
+
+    fn supports_sampling(&self) -> bool {
+        self.face_areas().iter().sum::<Float>() > 0.0
+    }
+
+    fn area(&self) -> Option<Float> {
+        Some(2.0 * self.face_areas().iter().sum::<Float>())
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        Some(&**self.material)
+    }
 }
@@ -1,11 +1,11 @@
 use std::sync::Arc;
 
-use cgmath::{dot, point3, vec3, EuclideanSpace, InnerSpace, Point3, Vector3};
+use cgmath::{point3, vec3, EuclideanSpace, Point3, Vector3};
 use rand::Rng;
 
 use crate::{
     aabb::AABB,
-    hittable::{HitRecord, Hittable},
+    hittable::{area_to_solid_angle_pdf, HitRecord, Hittable},
     material::Material,
     ray::Ray,
     Float, MyRng,
@@ -43,7 +43,13 @@ pub struct YZRect {
 
 impl Hittable for XYRect {
     #[allow(clippy::many_single_char_names)]
-    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, _rng: &mut MyRng) -> Option<HitRecord> {
+    fn hit(
+        &self,
+        ray: &Ray,
+        t_min: Float,
+        t_max: Float,
+        _rng: &mut MyRng,
+    ) -> Option<HitRecord<'_>> {
         let t = (self.k - ray.origin.z) / ray.direction.z;
         if t < t_min || t > t_max {
             return None;
@@ -60,15 +66,10 @@ impl Hittable for XYRect {
         let v = (y - self.y0) / (self.y1 - self.y0);
         let outward_normal = vec3(0.0, 0.0, 1.0);
 
-        Some(HitRecord::new(
-            ray.at(t),
-            outward_normal,
-            t,
-            u,
-            v,
-            ray,
-            self.material.clone(),
-        ))
+        Some(
+            HitRecord::new(ray.at(t), outward_normal, t, u, v, ray, &**self.material)
+                .with_tangent(vec3(1.0, 0.0, 0.0)),
+        )
     }
 
     fn bounding_box(&self, _time0: Float, _time1: Float) -> Option<AABB> {
@@ -77,11 +78,56 @@ impl Hittable for XYRect {
             maximum: point3(self.x1, self.y1, self.k + 0.0001),
         })
     }
+
+    fn pdf_value(
+        &self,
+        origin: Point3<Float>,
+        v: Vector3<Float>,
+        time: Float,
+        rng: &mut MyRng,
+    ) -> Float {
+        let ray = Ray {
+            origin,
+            direction: v,
+            time,
+        };
+        self.hit(&ray, 0.001, Float::INFINITY, rng)
+            .map_or(0.0, |hit_record| {
+                area_to_solid_angle_pdf(&hit_record, v, (self.x1 - self.x0) * (self.y1 - self.y0))
+            })
+    }
+
+    fn random(&self, origin: Point3<Float>, _time: Float, rng: &mut MyRng) -> Vector3<Float> {
+        let random_point = vec3(
+            rng.gen_range(self.x0..self.x1),
+            rng.gen_range(self.y0..self.y1),
+            self.k,
+        );
+        random_point - origin.to_vec()
+    }
+
+    fn supports_sampling(&self) -> bool {
+        true
+    }
+
+    fn area(&self) -> Option<Float> {
+        Some((self.x1 - self.x0) * (self.y1 - self.y0))
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        Some(&**self.material)
+    }
 }
 
 impl Hittable for XZRect {
     #[allow(clippy::many_single_char_names)]
-    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, _rng: &mut MyRng) -> Option<HitRecord> {
+    fn hit(
+        &self,
+        ray: &Ray,
+        t_min: Float,
+        t_max: Float,
+        _rng: &mut MyRng,
+    ) -> Option<HitRecord<'_>> {
         let t = (self.k - ray.origin.y) / ray.direction.y;
         if t < t_min || t > t_max {
             return None;
@@ -98,15 +144,10 @@ impl Hittable for XZRect {
         let v = (z - self.z0) / (self.z1 - self.z0);
         let outward_normal = vec3(0.0, 1.0, 0.0);
 
-        Some(HitRecord::new(
-            ray.at(t),
-            outward_normal,
-            t,
-            u,
-            v,
-            ray,
-            self.material.clone(),
-        ))
+        Some(
+            HitRecord::new(ray.at(t), outward_normal, t, u, v, ray, &**self.material)
+                .with_tangent(vec3(1.0, 0.0, 0.0)),
+        )
     }
 
     fn bounding_box(&self, _time0: Float, _time1: Float) -> Option<AABB> {
@@ -116,28 +157,25 @@ impl Hittable for XZRect {
         })
     }
 
-    fn pdf_value(&self, origin: Point3<Float>, v: Vector3<Float>, rng: &mut MyRng) -> Float {
-        if let Some(hit_record) = self.hit(
-            &Ray {
-                origin,
-                direction: v,
-                time: 0.0,
-            },
-            0.001,
-            Float::INFINITY,
-            rng,
-        ) {
-            let area = (self.x1 - self.x0) * (self.z1 - self.z0);
-            let distance_squared = hit_record.t * hit_record.t * v.magnitude2();
-            let cosine = (dot(v, hit_record.normal) / v.magnitude()).abs();
-
-            distance_squared / (cosine * area)
-        } else {
-            0.0
-        }
+    fn pdf_value(
+        &self,
+        origin: Point3<Float>,
+        v: Vector3<Float>,
+        time: Float,
+        rng: &mut MyRng,
+    ) -> Float {
+        let ray = Ray {
+            origin,
+            direction: v,
+            time,
+        };
+        self.hit(&ray, 0.001, Float::INFINITY, rng)
+            .map_or(0.0, |hit_record| {
+                area_to_solid_angle_pdf(&hit_record, v, (self.x1 - self.x0) * (self.z1 - self.z0))
+            })
     }
 
-    fn random(&self, origin: Point3<Float>, rng: &mut MyRng) -> Vector3<Float> {
+    fn random(&self, origin: Point3<Float>, _time: Float, rng: &mut MyRng) -> Vector3<Float> {
         let random_point = vec3(
             rng.gen_range(self.x0..self.x1),
             self.k,
@@ -145,11 +183,29 @@ impl Hittable for XZRect {
         );
         random_point - origin.to_vec()
     }
+
+    fn supports_sampling(&self) -> bool {
+        true
+    }
+
+    fn area(&self) -> Option<Float> {
+        Some((self.x1 - self.x0) * (self.z1 - self.z0))
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        Some(&**self.material)
+    }
 }
 
 impl Hittable for YZRect {
     #[allow(clippy::many_single_char_names)]
-    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, _rng: &mut MyRng) -> Option<HitRecord> {
+    fn hit(
+        &self,
+        ray: &Ray,
+        t_min: Float,
+        t_max: Float,
+        _rng: &mut MyRng,
+    ) -> Option<HitRecord<'_>> {
         let t = (self.k - ray.origin.x) / ray.direction.x;
         if t < t_min || t > t_max {
             return None;
@@ -166,15 +222,10 @@ impl Hittable for YZRect {
         let v = (z - self.z0) / (self.z1 - self.z0);
         let outward_normal = vec3(1.0, 0.0, 0.0);
 
-        Some(HitRecord::new(
-            ray.at(t),
-            outward_normal,
-            t,
-            u,
-            v,
-            ray,
-            self.material.clone(),
-        ))
+        Some(
+            HitRecord::new(ray.at(t), outward_normal, t, u, v, ray, &**self.material)
+                .with_tangent(vec3(0.0, 1.0, 0.0)),
+        )
     }
 
     fn bounding_box(&self, _time0: Float, _time1: Float) -> Option<AABB> {
@@ -183,4 +234,43 @@ impl Hittable for YZRect {
             maximum: point3(self.k + 0.0001, self.y1, self.z1),
         })
     }
+
+    fn pdf_value(
+        &self,
+        origin: Point3<Float>,
+        v: Vector3<Float>,
+        time: Float,
+        rng: &mut MyRng,
+    ) -> Float {
+        let ray = Ray {
+            origin,
+            direction: v,
+            time,
+        };
+        self.hit(&ray, 0.001, Float::INFINITY, rng)
+            .map_or(0.0, |hit_record| {
+                area_to_solid_angle_pdf(&hit_record, v, (self.y1 - self.y0) * (self.z1 - self.z0))
+            })
+    }
+
+    fn random(&self, origin: Point3<Float>, _time: Float, rng: &mut MyRng) -> Vector3<Float> {
+        let random_point = vec3(
+            self.k,
+            rng.gen_range(self.y0..self.y1),
+            rng.gen_range(self.z0..self.z1),
+        );
+        random_point - origin.to_vec()
+    }
+
+    fn supports_sampling(&self) -> bool {
+        true
+    }
+
+    fn area(&self) -> Option<Float> {
+        Some((self.y1 - self.y0) * (self.z1 - self.z0))
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        Some(&**self.material)
+    }
 }
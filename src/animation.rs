@@ -0,0 +1,273 @@
+use std::sync::Arc;
+
+use cgmath::{
+    vec3, ElementWise, EuclideanSpace, InnerSpace, Matrix4, Point3, Quaternion, Vector3,
+    VectorSpace,
+};
+
+use crate::{
+    aabb::{surrounding_box, AABB},
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    transform::Affine,
+    Float, MyRng,
+};
+
+/// A pose at one point in time: scale, then rotate, then translate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe {
+    pub time: Float,
+    pub translation: Vector3<Float>,
+    pub rotation: Quaternion<Float>,
+    pub scale: Vector3<Float>,
+}
+
+/// A transform interpolated between keyframes: translation and scale linearly, rotation with
+/// slerp. Before the first and after the last keyframe the pose is held.
+#[derive(Clone, Debug)]
+pub struct AnimatedTransform {
+    keyframes: Vec<Keyframe>,
+}
+
+/// Applies an [`AnimatedTransform`] to a hittable at the time of each ray.
+pub struct Animated<T> {
+    hittable: T,
+    animation: AnimatedTransform,
+    aabb: Option<AABB>,
+}
+
+impl Keyframe {
+    pub fn new(time: Float) -> Self {
+        Self {
+            time,
+            translation: vec3(0.0, 0.0, 0.0),
+            rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            scale: vec3(1.0, 1.0, 1.0),
+        }
+    }
+
+    pub fn translate(mut self, translation: Vector3<Float>) -> Self {
+        self.translation = translation;
+        self
+    }
+
+    pub fn rotate(mut self, rotation: Quaternion<Float>) -> Self {
+        self.rotation = rotation.normalize();
+        self
+    }
+
+    pub fn scale(mut self, scale: Vector3<Float>) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn to_affine(&self) -> Affine {
+        let rotation = Matrix4::from(self.rotation);
+        Affine::identity()
+            .scale(self.scale)
+            .then(Affine::from_matrix(rotation).expect("rotations are invertible"))
+            .translate(self.translation)
+    }
+}
+
+impl AnimatedTransform {
+    /// Keyframes are sorted by time; at least one is required and every time must be finite.
+    pub fn new(mut keyframes: Vec<Keyframe>) -> Self {
+        assert!(!keyframes.is_empty(), "keyframes must not be empty");
+        assert!(
+            keyframes.iter().all(|k| k.time.is_finite()),
+            "keyframe times must be finite"
+        );
+        assert!(
+            keyframes
+                .iter()
+                .all(|k| k.scale.x != 0.0 && k.scale.y != 0.0 && k.scale.z != 0.0),
+            "scale factors must not be zero"
+        );
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self { keyframes }
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    pub fn interpolate(&self, time: Float) -> Keyframe {
+        let first = self.keyframes[0];
+        let last = self.keyframes[self.keyframes.len() - 1];
+        if time <= first.time {
+            return first;
+        }
+        if time >= last.time {
+            return last;
+        }
+
+        let i = self.keyframes.partition_point(|k| k.time <= time);
+        let (k0, k1) = (self.keyframes[i - 1], self.keyframes[i]);
+        let s = (time - k0.time) / (k1.time - k0.time);
+
+        Keyframe {
+            time,
+            translation: k0.translation.lerp(k1.translation, s),
+            rotation: slerp(k0.rotation, k1.rotation, s),
+            scale: k0.scale.lerp(k1.scale, s),
+        }
+    }
+
+    pub fn at(&self, time: Float) -> Affine {
+        self.interpolate(time).to_affine()
+    }
+
+    /// Bounds `aabb` under every pose in `[time0, time1]`.
+    ///
+    /// Between two keyframes with the same rotation every corner moves linearly, so the poses at
+    /// the ends are enough. Otherwise the corners stay within the largest scaled corner distance
+    /// of the linearly moving translation, which bounds any rotation.
+    pub fn motion_bounds(&self, aabb: AABB, time0: Float, time1: Float) -> AABB {
+        let mut times = vec![time0];
+        times.extend(
+            self.keyframes
+                .iter()
+                .map(|k| k.time)
+                .filter(|&t| time0 < t && t < time1),
+        );
+        times.push(time1);
+
+        times
+            .windows(2)
+            .map(|segment| {
+                let (k0, k1) = (self.interpolate(segment[0]), self.interpolate(segment[1]));
+                let (start, end) = if same_rotation(k0.rotation, k1.rotation) {
+                    (
+                        k0.to_affine().transform_aabb(aabb),
+                        k1.to_affine().transform_aabb(aabb),
+                    )
+                } else {
+                    (rotation_bounds(&k0, aabb), rotation_bounds(&k1, aabb))
+                };
+                surrounding_box(start, end)
+            })
+            .reduce(surrounding_box)
+            .unwrap()
+    }
+}
+
+fn slerp(q0: Quaternion<Float>, q1: Quaternion<Float>, s: Float) -> Quaternion<Float> {
+    // Take the shorter way around.
+    let q1 = if q0.dot(q1) < 0.0 { -q1 } else { q1 };
+    if q0.dot(q1) > 0.9995 {
+        q0.nlerp(q1, s)
+    } else {
+        q0.slerp(q1, s)
+    }
+}
+
+fn same_rotation(q0: Quaternion<Float>, q1: Quaternion<Float>) -> bool {
+    q0.dot(q1).abs() >= 1.0 - 1e-12
+}
+
+/// Bounds `aabb` scaled by `keyframe` and rotated arbitrarily, then translated.
+fn rotation_bounds(keyframe: &Keyframe, aabb: AABB) -> AABB {
+    let radius = (0..8)
+        .map(|i| {
+            let corner = vec3(
+                if i & 1 == 0 {
+                    aabb.minimum.x
+                } else {
+                    aabb.maximum.x
+                },
+                if i & 2 == 0 {
+                    aabb.minimum.y
+                } else {
+                    aabb.maximum.y
+                },
+                if i & 4 == 0 {
+                    aabb.minimum.z
+                } else {
+                    aabb.maximum.z
+                },
+            );
+            corner.mul_element_wise(keyframe.scale).magnitude()
+        })
+        .fold(0.0, Float::max);
+    let center = Point3::from_vec(keyframe.translation);
+    AABB {
+        minimum: center - vec3(radius, radius, radius),
+        maximum: center + vec3(radius, radius, radius),
+    }
+}
+
+impl<T: Hittable> Animated<T> {
+    pub fn new(hittable: T, animation: AnimatedTransform, time0: Float, time1: Float) -> Self {
+        let aabb = hittable
+            .bounding_box(time0, time1)
+            .map(|aabb| animation.motion_bounds(aabb, time0, time1));
+        Self {
+            hittable,
+            animation,
+            aabb,
+        }
+    }
+}
+
+impl<T: Hittable> Hittable for Animated<T> {
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord<'_>> {
+        let transform = self.animation.at(ray.time);
+        let local = transform.inverse().transform_ray(ray);
+        self.hittable
+            .hit(&local, t_min, t_max, rng)
+            .map(|hit_record| transform.transform_hit(hit_record))
+    }
+
+    fn bounding_box(&self, _time0: Float, _time1: Float) -> Option<AABB> {
+        self.aabb
+    }
+
+    fn pdf_value(
+        &self,
+        origin: Point3<Float>,
+        v: Vector3<Float>,
+        time: Float,
+        rng: &mut MyRng,
+    ) -> Float {
+        self.animation
+            .at(time)
+            .transform_pdf_value(&self.hittable, origin, v, time, rng)
+    }
+
+    fn random(&self, origin: Point3<Float>, time: Float, rng: &mut MyRng) -> Vector3<Float> {
+        self.animation
+            .at(time)
+            .transform_random(&self.hittable, origin, time, rng)
+    }
+
+    fn supports_sampling(&self) -> bool {
+        self.hittable.supports_sampling()
+    }
+
+    /// Uses the first pose, since the area is only needed as an estimate.
+    fn area(&self) -> Option<Float> {
+        let area_scale = self.animation.keyframes()[0].to_affine().area_scale();
+        self.hittable.area().map(|area| area * area_scale)
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        self.hittable.material()
+    }
+
+    /// Parts keep the bounding box of the whole object.
+    fn parts(&self) -> Vec<Arc<dyn Hittable>> {
+        self.hittable
+            .parts()
+            .into_iter()
+            .map(|part| -> Arc<dyn Hittable> {
+                Arc::new(Animated {
+                    hittable: part,
+                    animation: self.animation.clone(),
+                    aabb: self.aabb,
+                })
+            })
+            .collect()
+    }
+}
@@ -1,15 +1,56 @@
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+    sync::Arc,
+};
+
+use cgmath::{EuclideanSpace, Point3};
 use float_ord::FloatOrd;
 use rand::Rng;
 
 use crate::{
     aabb::{surrounding_box, AABB},
-    hittable::Hittable,
+    hittable::{HitRecord, Hittable},
+    ray::Ray,
     Float, MyRng,
 };
 
+/// How [`BVHNode::with_split_method`] partitions primitives.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SplitMethod {
+    /// Sort along a random axis and split at the median. Leaves hold at most two primitives.
+    #[default]
+    Median,
+    /// Binned surface area heuristic.
+    Sah(SahOptions),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SahOptions {
+    /// Number of buckets centroids are binned into along each axis.
+    pub bins: usize,
+    /// Nodes with more primitives than this are always split.
+    pub max_leaf_size: usize,
+    /// Cost of visiting an interior node, relative to `intersection_cost`.
+    pub traversal_cost: Float,
+    /// Cost of intersecting one primitive.
+    pub intersection_cost: Float,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BVHStats {
+    /// Number of nodes on the longest root-to-leaf path.
+    pub depth: usize,
+    pub node_count: usize,
+    pub leaf_count: usize,
+    pub primitive_count: usize,
+    /// Expected cost of tracing a ray that hits the root, under the surface area heuristic.
+    pub sah_cost: Float,
+}
+
 enum BVHChild {
-    One(Box<dyn Hittable>),
-    Two(Box<dyn Hittable>, Box<dyn Hittable>),
+    Leaf(Vec<Arc<dyn Hittable>>),
+    Interior(Box<BVHNode>, Box<BVHNode>),
 }
 
 pub struct BVHNode {
@@ -17,25 +58,105 @@ pub struct BVHNode {
     aabb: AABB,
 }
 
+/// A [`BVHNode`] tree flattened into an array in depth-first order.
+///
+/// Each interior node is followed by its first child; the second child is found by index.
+/// Traversal visits the child nearer to the ray origin first and uses an explicit stack.
+pub struct FlatBVH {
+    nodes: Vec<LinearNode>,
+    primitives: Vec<Arc<dyn Hittable>>,
+}
+
+const TRAVERSAL_STACK_SIZE: usize = 64;
+
+#[derive(Clone, Copy, Debug)]
+struct LinearNode {
+    aabb: AABB,
+    kind: LinearNodeKind,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum LinearNodeKind {
+    Leaf { first: u32, count: u32 },
+    Interior { second_child: u32, axis: u8 },
+}
+
+struct BuildPrimitive {
+    object: Arc<dyn Hittable>,
+    aabb: AABB,
+    centroid: Point3<Float>,
+}
+
+impl Default for SahOptions {
+    fn default() -> Self {
+        Self {
+            bins: 12,
+            max_leaf_size: 4,
+            traversal_cost: 0.125,
+            intersection_cost: 1.0,
+        }
+    }
+}
+
+impl FromStr for SplitMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "median" => Ok(SplitMethod::Median),
+            "sah" => Ok(SplitMethod::Sah(SahOptions::default())),
+            _ => Err(format!(
+                "unknown BVH split method `{}` (expected median or sah)",
+                s
+            )),
+        }
+    }
+}
+
+impl Display for BVHStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} nodes ({} leaves), {} primitives, depth {}, SAH cost {:.2}",
+            self.node_count, self.leaf_count, self.primitive_count, self.depth, self.sah_cost
+        )
+    }
+}
+
 impl Hittable for BVHNode {
-    fn bounding_box(&self, _time0: crate::Float, _time1: crate::Float) -> Option<AABB> {
+    fn bounding_box(&self, _time0: Float, _time1: Float) -> Option<AABB> {
         Some(self.aabb)
     }
 
-    fn hit(
-        &self,
-        ray: &crate::ray::Ray,
-        t_min: crate::Float,
-        t_max: crate::Float,
-        rng: &mut MyRng,
-    ) -> Option<crate::hittable::HitRecord> {
+    fn parts(&self) -> Vec<Arc<dyn Hittable>> {
+        match &self.child {
+            BVHChild::Leaf(objects) => objects.clone(),
+            BVHChild::Interior(left, right) => {
+                let mut parts = left.parts();
+                parts.extend(right.parts());
+                parts
+            }
+        }
+    }
+
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord<'_>> {
         if !self.aabb.hit(ray, t_min, t_max) {
             return None;
         }
 
         match &self.child {
-            BVHChild::One(obj) => obj.hit(ray, t_min, t_max, rng),
-            BVHChild::Two(left, right) => {
+            BVHChild::Leaf(objects) => {
+                let mut closest = None;
+                let mut t_max = t_max;
+                for obj in objects {
+                    if let Some(hit) = obj.hit(ray, t_min, t_max, rng) {
+                        t_max = hit.t;
+                        closest = Some(hit);
+                    }
+                }
+                closest
+            }
+            BVHChild::Interior(left, right) => {
                 if let Some(hit_left) = left.hit(ray, t_min, t_max, rng) {
                     if let Some(hit_right) = right.hit(ray, t_min, hit_left.t, rng) {
                         Some(hit_right)
@@ -51,54 +172,458 @@ impl Hittable for BVHNode {
 }
 
 impl BVHNode {
+    /// Builds a BVH with [`SplitMethod::Median`].
     pub fn new(
-        mut objects: Vec<Box<dyn Hittable>>,
+        objects: Vec<Box<dyn Hittable>>,
+        time0: Float,
+        time1: Float,
+        rng: &mut impl Rng,
+    ) -> Self {
+        Self::with_split_method(objects, time0, time1, SplitMethod::Median, rng)
+    }
+
+    pub fn with_split_method(
+        objects: Vec<Box<dyn Hittable>>,
         time0: Float,
         time1: Float,
+        split_method: SplitMethod,
         rng: &mut impl Rng,
     ) -> Self {
-        match objects.len() {
-            0 => panic!("objects mut not be empty"),
-            1 => {
-                let obj = objects.pop().unwrap();
-                Self {
-                    aabb: obj
-                        .bounding_box(time0, time1)
-                        .expect("Bounding Box is required"),
-                    child: BVHChild::One(obj),
+        assert!(!objects.is_empty(), "objects mut not be empty");
+
+        let primitives = objects
+            .into_iter()
+            .map(|object| {
+                let aabb = object
+                    .bounding_box(time0, time1)
+                    .expect("Bounding Box is required");
+                BuildPrimitive {
+                    object: Arc::from(object),
+                    aabb,
+                    centroid: aabb.centroid(),
                 }
+            })
+            .collect();
+
+        match split_method {
+            SplitMethod::Median => Self::build_median(primitives, rng),
+            SplitMethod::Sah(options) => {
+                assert!(options.bins >= 2, "SAH needs at least two bins");
+                assert!(options.max_leaf_size >= 1, "max_leaf_size must be positive");
+                Self::build_sah(primitives, &options, 1)
             }
-            2 => {
-                let left = objects.pop().unwrap();
-                let right = objects.pop().unwrap();
-                let left_box = left.bounding_box(time0, time1).unwrap();
-                let right_box = right.bounding_box(time0, time1).unwrap();
-                let aabb = surrounding_box(left_box, right_box);
+        }
+    }
 
-                Self {
-                    child: BVHChild::Two(left, right),
-                    aabb,
+    pub fn stats(&self, traversal_cost: Float, intersection_cost: Float) -> BVHStats {
+        let mut stats = BVHStats {
+            depth: 0,
+            node_count: 0,
+            leaf_count: 0,
+            primitive_count: 0,
+            sah_cost: 0.0,
+        };
+        let root_area = self.aabb.surface_area();
+        self.collect_stats(1, root_area, traversal_cost, intersection_cost, &mut stats);
+        stats
+    }
+
+    fn collect_stats(
+        &self,
+        depth: usize,
+        root_area: Float,
+        traversal_cost: Float,
+        intersection_cost: Float,
+        stats: &mut BVHStats,
+    ) {
+        let area_ratio = if root_area > 0.0 {
+            self.aabb.surface_area() / root_area
+        } else {
+            1.0
+        };
+
+        stats.depth = stats.depth.max(depth);
+        stats.node_count += 1;
+
+        match &self.child {
+            BVHChild::Leaf(objects) => {
+                stats.leaf_count += 1;
+                stats.primitive_count += objects.len();
+                stats.sah_cost += area_ratio * objects.len() as Float * intersection_cost;
+            }
+            BVHChild::Interior(left, right) => {
+                stats.sah_cost += area_ratio * traversal_cost;
+                for child in [left, right] {
+                    child.collect_stats(
+                        depth + 1,
+                        root_area,
+                        traversal_cost,
+                        intersection_cost,
+                        stats,
+                    );
                 }
             }
-            len => {
-                let axis = rng.gen_range(0..=2);
-                objects
-                    .sort_by_key(|o| FloatOrd(o.bounding_box(time0, time1).unwrap().minimum[axis]));
-                let right = objects.split_off(len / 2);
+        }
+    }
+
+    fn leaf(primitives: Vec<BuildPrimitive>) -> Self {
+        let aabb = bounds(&primitives);
+        Self {
+            child: BVHChild::Leaf(primitives.into_iter().map(|p| p.object).collect()),
+            aabb,
+        }
+    }
 
-                let left = objects;
-                let right = right;
+    fn interior(left: Self, right: Self) -> Self {
+        Self {
+            aabb: surrounding_box(left.aabb, right.aabb),
+            child: BVHChild::Interior(Box::new(left), Box::new(right)),
+        }
+    }
 
-                let left = Self::new(left, time0, time1, rng);
-                let right = Self::new(right, time0, time1, rng);
+    fn build_median(mut primitives: Vec<BuildPrimitive>, rng: &mut impl Rng) -> Self {
+        let len = primitives.len();
+        if len <= 2 {
+            return Self::leaf(primitives);
+        }
 
-                let aabb = surrounding_box(left.aabb, right.aabb);
+        let axis = rng.gen_range(0..=2);
+        primitives.sort_by_key(|p| FloatOrd(p.aabb.minimum[axis]));
+        let right = primitives.split_off(len / 2);
 
-                Self {
-                    child: BVHChild::Two(Box::new(left), Box::new(right)),
-                    aabb,
+        let left = Self::build_median(primitives, rng);
+        let right = Self::build_median(right, rng);
+        Self::interior(left, right)
+    }
+
+    /// Builds the subtree of `primitives` at `depth`, counting the root as one. Past the depth
+    /// that still leaves room to split what remains in half, primitives are split in half by
+    /// count instead, so the tree never outgrows the traversal stack of [`FlatBVH`].
+    fn build_sah(mut primitives: Vec<BuildPrimitive>, options: &SahOptions, depth: usize) -> Self {
+        let len = primitives.len();
+        if len == 1 {
+            return Self::leaf(primitives);
+        }
+        // Halving reaches single primitives after `ceil(log2(len))` more levels.
+        let halvings = (usize::BITS - (len - 1).leading_zeros()) as usize;
+        if depth + halvings >= TRAVERSAL_STACK_SIZE {
+            let right = split_in_half(&mut primitives);
+            let left = Self::build_sah(primitives, options, depth + 1);
+            let right = Self::build_sah(right, options, depth + 1);
+            return Self::interior(left, right);
+        }
+
+        let aabb = bounds(&primitives);
+        let leaf_cost = len as Float * options.intersection_cost;
+
+        let centroid_min = primitives.iter().fold(primitives[0].centroid, |acc, p| {
+            Point3::from_vec(acc.to_vec().zip(p.centroid.to_vec(), Float::min))
+        });
+        let centroid_max = primitives.iter().fold(primitives[0].centroid, |acc, p| {
+            Point3::from_vec(acc.to_vec().zip(p.centroid.to_vec(), Float::max))
+        });
+        let bin_index = |centroid: Point3<Float>, axis: usize| {
+            let extent = centroid_max[axis] - centroid_min[axis];
+            let offset = (centroid[axis] - centroid_min[axis]) / extent;
+            ((offset * options.bins as Float) as usize).min(options.bins - 1)
+        };
+
+        // (cost, axis, number of bins on the left)
+        let mut best: Option<(Float, usize, usize)> = None;
+        for axis in 0..3 {
+            if centroid_max[axis] - centroid_min[axis] <= 0.0 {
+                continue;
+            }
+
+            let mut counts = vec![0usize; options.bins];
+            let mut boxes: Vec<Option<AABB>> = vec![None; options.bins];
+            for p in &primitives {
+                let b = bin_index(p.centroid, axis);
+                counts[b] += 1;
+                boxes[b] = Some(union(boxes[b], p.aabb));
+            }
+
+            // Sweep from the right to get the area and count of every right-hand side.
+            let mut right_area = vec![0.0; options.bins];
+            let mut right_count = vec![0; options.bins];
+            let mut acc_box = None;
+            let mut acc_count = 0;
+            for b in (1..options.bins).rev() {
+                if let Some(aabb) = boxes[b] {
+                    acc_box = Some(union(acc_box, aabb));
+                }
+                acc_count += counts[b];
+                right_area[b] = acc_box.map_or(0.0, |aabb| aabb.surface_area());
+                right_count[b] = acc_count;
+            }
+
+            let mut acc_box = None;
+            let mut acc_count = 0;
+            for split in 1..options.bins {
+                if let Some(aabb) = boxes[split - 1] {
+                    acc_box = Some(union(acc_box, aabb));
+                }
+                acc_count += counts[split - 1];
+                if acc_count == 0 || right_count[split] == 0 {
+                    continue;
+                }
+
+                let left_area = acc_box.map_or(0.0, |aabb| aabb.surface_area());
+                let cost = options.traversal_cost
+                    + options.intersection_cost
+                        * (acc_count as Float * left_area
+                            + right_count[split] as Float * right_area[split])
+                        / aabb.surface_area();
+
+                if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                    best = Some((cost, axis, split));
+                }
+            }
+        }
+
+        let right = match best {
+            Some((cost, axis, split)) if cost < leaf_cost || len > options.max_leaf_size => {
+                let (left, right): (Vec<_>, Vec<_>) = primitives
+                    .into_iter()
+                    .partition(|p| bin_index(p.centroid, axis) < split);
+                primitives = left;
+                right
+            }
+            // All centroids coincide, so no bin split exists; fall back to splitting in half.
+            None if len > options.max_leaf_size => primitives.split_off(len / 2),
+            _ => return Self::leaf(primitives),
+        };
+
+        let left = Self::build_sah(primitives, options, depth + 1);
+        let right = Self::build_sah(right, options, depth + 1);
+        Self::interior(left, right)
+    }
+}
+
+impl From<BVHNode> for FlatBVH {
+    fn from(root: BVHNode) -> Self {
+        let mut flat = FlatBVH {
+            nodes: Vec::new(),
+            primitives: Vec::new(),
+        };
+        flat.push(root);
+        assert!(
+            flat.stats(0.0, 0.0).depth <= TRAVERSAL_STACK_SIZE,
+            "BVH is too deep to traverse"
+        );
+        flat
+    }
+}
+
+impl FlatBVH {
+    fn push(&mut self, node: BVHNode) -> usize {
+        let index = self.nodes.len();
+        match node.child {
+            BVHChild::Leaf(objects) => {
+                self.nodes.push(LinearNode {
+                    aabb: node.aabb,
+                    kind: LinearNodeKind::Leaf {
+                        first: self.primitives.len() as u32,
+                        count: objects.len() as u32,
+                    },
+                });
+                self.primitives.extend(objects);
+            }
+            BVHChild::Interior(left, right) => {
+                // Order the children along the axis that separates them best, so the sign of the
+                // ray direction on that axis tells which one is nearer.
+                let offset = right.aabb.centroid() - left.aabb.centroid();
+                let axis = (0..3)
+                    .max_by_key(|&axis| FloatOrd(offset[axis].abs()))
+                    .unwrap();
+                let (left, right) = if offset[axis] >= 0.0 {
+                    (left, right)
+                } else {
+                    (right, left)
+                };
+
+                self.nodes.push(LinearNode {
+                    aabb: node.aabb,
+                    kind: LinearNodeKind::Interior {
+                        second_child: 0,
+                        axis: axis as u8,
+                    },
+                });
+                self.push(*left);
+                let second = self.push(*right) as u32;
+                if let LinearNodeKind::Interior { second_child, .. } = &mut self.nodes[index].kind {
+                    *second_child = second;
+                }
+            }
+        }
+        index
+    }
+
+    pub fn stats(&self, traversal_cost: Float, intersection_cost: Float) -> BVHStats {
+        let mut stats = BVHStats {
+            depth: 0,
+            node_count: self.nodes.len(),
+            leaf_count: 0,
+            primitive_count: self.primitives.len(),
+            sah_cost: 0.0,
+        };
+        let root_area = self.nodes[0].aabb.surface_area();
+
+        let mut stack = vec![(0, 1)];
+        while let Some((index, depth)) = stack.pop() {
+            let node = &self.nodes[index];
+            let area_ratio = if root_area > 0.0 {
+                node.aabb.surface_area() / root_area
+            } else {
+                1.0
+            };
+            stats.depth = stats.depth.max(depth);
+
+            match node.kind {
+                LinearNodeKind::Leaf { count, .. } => {
+                    stats.leaf_count += 1;
+                    stats.sah_cost += area_ratio * count as Float * intersection_cost;
+                }
+                LinearNodeKind::Interior { second_child, .. } => {
+                    stats.sah_cost += area_ratio * traversal_cost;
+                    stack.push((index + 1, depth + 1));
+                    stack.push((second_child as usize, depth + 1));
                 }
             }
         }
+
+        stats
+    }
+
+    /// Calls `f` with every primitive in a leaf whose box the ray crosses between `t_min` and
+    /// `t_max`, without intersecting the primitives themselves.
+    pub(crate) fn for_each_candidate(
+        &self,
+        ray: &Ray,
+        t_min: Float,
+        t_max: Float,
+        mut f: impl FnMut(&dyn Hittable),
+    ) {
+        let mut stack = [0u32; TRAVERSAL_STACK_SIZE];
+        let mut stack_len = 0;
+        let mut index = 0;
+
+        loop {
+            let node = &self.nodes[index];
+            if node.aabb.hit(ray, t_min, t_max) {
+                match node.kind {
+                    LinearNodeKind::Leaf { first, count } => {
+                        let first = first as usize;
+                        for obj in &self.primitives[first..first + count as usize] {
+                            f(&**obj);
+                        }
+                    }
+                    LinearNodeKind::Interior { second_child, .. } => {
+                        stack[stack_len] = second_child;
+                        stack_len += 1;
+                        index += 1;
+                        continue;
+                    }
+                }
+            }
+
+            if stack_len == 0 {
+                break;
+            }
+            stack_len -= 1;
+            index = stack[stack_len] as usize;
+        }
+    }
+}
+
+impl Hittable for FlatBVH {
+    fn bounding_box(&self, _time0: Float, _time1: Float) -> Option<AABB> {
+        Some(self.nodes[0].aabb)
+    }
+
+    fn parts(&self) -> Vec<Arc<dyn Hittable>> {
+        self.primitives.clone()
+    }
+
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord<'_>> {
+        let dir_is_neg = [
+            ray.direction.x < 0.0,
+            ray.direction.y < 0.0,
+            ray.direction.z < 0.0,
+        ];
+
+        let mut closest = None;
+        let mut t_max = t_max;
+        let mut stack = [0u32; TRAVERSAL_STACK_SIZE];
+        let mut stack_len = 0;
+        let mut index = 0;
+
+        loop {
+            let node = &self.nodes[index];
+            if node.aabb.hit(ray, t_min, t_max) {
+                match node.kind {
+                    LinearNodeKind::Leaf { first, count } => {
+                        let first = first as usize;
+                        for obj in &self.primitives[first..first + count as usize] {
+                            if let Some(hit) = obj.hit(ray, t_min, t_max, rng) {
+                                t_max = hit.t;
+                                closest = Some(hit);
+                            }
+                        }
+                    }
+                    LinearNodeKind::Interior { second_child, axis } => {
+                        if dir_is_neg[axis as usize] {
+                            stack[stack_len] = index as u32 + 1;
+                            index = second_child as usize;
+                        } else {
+                            stack[stack_len] = second_child;
+                            index += 1;
+                        }
+                        stack_len += 1;
+                        continue;
+                    }
+                }
+            }
+
+            if stack_len == 0 {
+                break;
+            }
+            stack_len -= 1;
+            index = stack[stack_len] as usize;
+        }
+
+        closest
+    }
+}
+
+fn union(aabb: Option<AABB>, other: AABB) -> AABB {
+    match aabb {
+        Some(aabb) => surrounding_box(aabb, other),
+        None => other,
     }
 }
+
+fn bounds(primitives: &[BuildPrimitive]) -> AABB {
+    primitives
+        .iter()
+        .map(|p| p.aabb)
+        .reduce(surrounding_box)
+        .unwrap()
+}
+
+/// Sorts `primitives` along the axis their centroids spread furthest on and splits off the upper
+/// half.
+fn split_in_half(primitives: &mut Vec<BuildPrimitive>) -> Vec<BuildPrimitive> {
+    let centroids = primitives
+        .iter()
+        .map(|p| AABB {
+            minimum: p.centroid,
+            maximum: p.centroid,
+        })
+        .reduce(surrounding_box)
+        .unwrap();
+    let extent = centroids.maximum - centroids.minimum;
+    let axis = (0..3).max_by_key(|&axis| FloatOrd(extent[axis])).unwrap();
+    primitives.sort_by_key(|p| FloatOrd(p.centroid[axis]));
+    primitives.split_off(primitives.len() / 2)
+}
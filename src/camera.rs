@@ -11,7 +11,6 @@ pub struct Camera {
     vertical: Vector3<Float>,
     u: Vector3<Float>,
     v: Vector3<Float>,
-    w: Vector3<Float>,
     lens_radius: Float,
     time0: Float,
     time1: Float,
@@ -51,7 +50,6 @@ impl Camera {
             vertical,
             u,
             v,
-            w,
             lens_radius: aperture / 2.0,
             time0,
             time1,
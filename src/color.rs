@@ -11,6 +11,11 @@ pub struct Color(pub Vector3<Float>);
 pub struct SampledColor(Vector3<Float>);
 
 impl Color {
+    /// Rec. 709 relative luminance.
+    pub fn luminance(&self) -> Float {
+        0.2126 * self.0.x + 0.7152 * self.0.y + 0.0722 * self.0.z
+    }
+
     pub fn into_sampled(self, sample_per_pixel: usize) -> SampledColor {
         let scale = 1.0 / sample_per_pixel as Float;
         let r = if self.0.x.is_nan() { 0.0 } else { self.0.x };
@@ -53,14 +58,25 @@ impl Display for Color {
     }
 }
 
+impl SampledColor {
+    /// Gamma 2 encoding clamped to 8 bits, as used by PPM and PNG output.
+    pub fn to_rgb8(self) -> [u8; 3] {
+        [
+            (256.0 * self[0].sqrt().clamp(0.0, 0.999)) as u8,
+            (256.0 * self[1].sqrt().clamp(0.0, 0.999)) as u8,
+            (256.0 * self[2].sqrt().clamp(0.0, 0.999)) as u8,
+        ]
+    }
+
+    /// Unclamped linear radiance, as used by floating-point output.
+    pub fn to_rgb32f(self) -> [f32; 3] {
+        [self[0] as f32, self[1] as f32, self[2] as f32]
+    }
+}
+
 impl Display for SampledColor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} {} {}",
-            (256.0 * self[0].sqrt().clamp(0.0, 0.999)) as usize,
-            (256.0 * self[1].sqrt().clamp(0.0, 0.999)) as usize,
-            (256.0 * self[2].sqrt().clamp(0.0, 0.999)) as usize
-        )
+        let [r, g, b] = self.to_rgb8();
+        write!(f, "{} {} {}", r, g, b)
     }
 }
@@ -1,11 +1,13 @@
 use std::sync::Arc;
 
-use cgmath::{vec3, InnerSpace};
+use cgmath::{vec3, InnerSpace, Vector3};
+use num_traits::FloatConst;
 use rand::Rng;
 
 use crate::{
+    color::Color,
     hittable::{HitRecord, Hittable},
-    material::{Material, Scatter, ScatterKind},
+    material::{BsdfSample, Material},
     math::random_in_unit_sphere,
     ray::Ray,
     texture::Texture,
@@ -34,20 +36,36 @@ pub struct Isotropic {
 }
 
 impl Material for Isotropic {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, rng: &mut MyRng) -> Option<Scatter> {
-        let attenuation = self
+    fn eval(&self, hit_record: &HitRecord, _wo: Vector3<Float>, _wi: Vector3<Float>) -> Color {
+        let albedo = self
             .albedo
             .value(hit_record.u, hit_record.v, hit_record.position);
+        Color(albedo.0 / (4.0 * Float::PI()))
+    }
 
-        Some(Scatter {
-            kind: ScatterKind::Spacular(Ray {
-                origin: hit_record.position,
-                direction: random_in_unit_sphere(rng),
-                time: ray.time,
-            }),
-            attenuation,
+    fn sample(
+        &self,
+        hit_record: &HitRecord,
+        _wo: Vector3<Float>,
+        rng: &mut MyRng,
+    ) -> Option<BsdfSample> {
+        Some(BsdfSample {
+            wi: random_in_unit_sphere(rng).normalize(),
+            weight: self
+                .albedo
+                .value(hit_record.u, hit_record.v, hit_record.position),
+            pdf: 1.0 / (4.0 * Float::PI()),
+            is_delta: false,
         })
     }
+
+    fn pdf(&self, _hit_record: &HitRecord, _wo: Vector3<Float>, _wi: Vector3<Float>) -> Float {
+        1.0 / (4.0 * Float::PI())
+    }
+
+    fn bounds_medium(&self) -> bool {
+        false
+    }
 }
 
 impl<T: Hittable> Hittable for ConstantMedium<T> {
@@ -55,7 +73,7 @@ impl<T: Hittable> Hittable for ConstantMedium<T> {
         self.boundary.bounding_box(time0, time1)
     }
 
-    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord<'_>> {
         const ENABLE_DEBUG: bool = false;
         let debugging = ENABLE_DEBUG && rng.gen::<Float>() < 0.00001;
 
@@ -102,7 +120,9 @@ impl<T: Hittable> Hittable for ConstantMedium<T> {
                     u: 0.0,
                     v: 0.0,
                     front_face: true,
-                    material: self.phase_function.clone(),
+                    tangent: vec3(0.0, 0.0, 0.0),
+                    material: &**self.phase_function,
+                    wavelength: None,
                 })
             } else {
                 None
@@ -0,0 +1,161 @@
+//! Lights without a surface: points, spots and distant lights such as the sun.
+//!
+//! Rays never hit these lights, so they are reached by light sampling alone and do not show up
+//! in mirrors or through glass.
+
+use std::fmt::Debug;
+
+use cgmath::{InnerSpace, Point3, Vector3};
+use num_traits::FloatConst;
+
+use crate::{color::Color, math::random_to_sphere, onb::Onb, Float, MyRng};
+
+/// A light that can only be sampled.
+pub trait DeltaLight: Debug + Send + Sync {
+    /// Samples light arriving at `origin`, or `None` if none does.
+    fn sample(&self, origin: Point3<Float>, rng: &mut MyRng) -> Option<DeltaLightSample>;
+}
+
+/// Light from a [`DeltaLight`] arriving at a point.
+#[derive(Clone, Copy, Debug)]
+pub struct DeltaLightSample {
+    /// Normalized direction towards the light.
+    pub wi: Vector3<Float>,
+    /// Distance to the light, infinite for distant lights.
+    pub distance: Float,
+    /// Irradiance on a surface facing the light, divided by the density of `wi` if the light
+    /// is not a point.
+    pub irradiance: Color,
+}
+
+/// Emits `intensity` in watts per steradian equally in every direction.
+#[derive(Debug)]
+pub struct PointLight {
+    pub position: Point3<Float>,
+    pub intensity: Color,
+}
+
+/// A point light shining into a cone around `direction`, fading out smoothly towards its edge.
+#[derive(Debug)]
+pub struct SpotLight {
+    pub position: Point3<Float>,
+    /// Normalized axis of the cone.
+    pub direction: Vector3<Float>,
+    /// Intensity along the axis in watts per steradian.
+    pub intensity: Color,
+    /// Cosine of the angle between the axis and the edge of the cone.
+    pub cos_total_width: Float,
+    /// Cosine of the angle from the axis where the light starts to fade.
+    pub cos_falloff_start: Float,
+    /// Relative intensities at evenly spaced angles from the axis (0°) to the opposite
+    /// direction (180°), as in a rotationally symmetric IES profile. Empty for none.
+    pub profile: Vec<Float>,
+}
+
+/// A light infinitely far away, covering a disk of `angular_diameter` in the sky like the sun.
+#[derive(Debug)]
+pub struct DistantLight {
+    /// Normalized direction the light travels in.
+    pub direction: Vector3<Float>,
+    /// Irradiance in watts per square metre on a surface facing the light.
+    pub irradiance: Color,
+    /// In radians; zero for perfectly sharp shadows.
+    pub angular_diameter: Float,
+}
+
+impl PointLight {
+    /// Luminance of the total power emitted.
+    pub fn power(&self) -> Float {
+        4.0 * Float::PI() * self.intensity.luminance()
+    }
+}
+
+impl DeltaLight for PointLight {
+    fn sample(&self, origin: Point3<Float>, _rng: &mut MyRng) -> Option<DeltaLightSample> {
+        let to_light = self.position - origin;
+        let distance_squared = to_light.magnitude2();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        Some(DeltaLightSample {
+            wi: to_light / distance_squared.sqrt(),
+            distance: distance_squared.sqrt(),
+            irradiance: Color(self.intensity.0 / distance_squared),
+        })
+    }
+}
+
+impl SpotLight {
+    /// Fraction of the axial intensity sent at `cos_theta` from the axis.
+    pub fn falloff(&self, cos_theta: Float) -> Float {
+        let cone = if cos_theta >= self.cos_falloff_start {
+            1.0
+        } else if cos_theta <= self.cos_total_width {
+            0.0
+        } else {
+            let t = (cos_theta - self.cos_total_width)
+                / (self.cos_falloff_start - self.cos_total_width);
+            t * t * (3.0 - 2.0 * t)
+        };
+
+        match self.profile.len() {
+            0 => cone,
+            1 => cone * self.profile[0],
+            n => {
+                let x = cos_theta.clamp(-1.0, 1.0).acos() / Float::PI() * (n - 1) as Float;
+                let i = (x as usize).min(n - 2);
+                let f = x - i as Float;
+                cone * (self.profile[i] * (1.0 - f) + self.profile[i + 1] * f)
+            }
+        }
+    }
+
+    /// Luminance of the total power emitted, integrating the falloff over the sphere.
+    pub fn power(&self) -> Float {
+        const STEPS: usize = 1024;
+
+        let integral: Float = (0..STEPS)
+            .map(|i| self.falloff(-1.0 + 2.0 * (i as Float + 0.5) / STEPS as Float))
+            .sum::<Float>()
+            * 2.0
+            / STEPS as Float;
+        2.0 * Float::PI() * integral * self.intensity.luminance()
+    }
+}
+
+impl DeltaLight for SpotLight {
+    fn sample(&self, origin: Point3<Float>, _rng: &mut MyRng) -> Option<DeltaLightSample> {
+        let to_light = self.position - origin;
+        let distance_squared = to_light.magnitude2();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let wi = to_light / distance_squared.sqrt();
+        let falloff = self.falloff(-wi.dot(self.direction));
+        if falloff <= 0.0 {
+            return None;
+        }
+        Some(DeltaLightSample {
+            wi,
+            distance: distance_squared.sqrt(),
+            irradiance: Color(self.intensity.0 * (falloff / distance_squared)),
+        })
+    }
+}
+
+impl DeltaLight for DistantLight {
+    fn sample(&self, _origin: Point3<Float>, rng: &mut MyRng) -> Option<DeltaLightSample> {
+        let wi = if self.angular_diameter > 0.0 {
+            // Uniform over the disk's cone, whose radiance times solid angle is the irradiance.
+            let sin_max = (self.angular_diameter / 2.0).min(Float::FRAC_PI_2()).sin();
+            Onb::from_w(-self.direction).local(random_to_sphere(sin_max, 1.0, rng))
+        } else {
+            -self.direction
+        };
+        Some(DeltaLightSample {
+            wi: wi.normalize(),
+            distance: Float::INFINITY,
+            irradiance: self.irradiance,
+        })
+    }
+}
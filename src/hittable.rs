@@ -3,21 +3,31 @@ use std::sync::Arc;
 use crate::aabb::{surrounding_box, AABB};
 use crate::{material::Material, ray::Ray};
 use crate::{Float, MyRng};
-use cgmath::{dot, point3, vec3, Angle, Deg, Point3, Rad, Vector3};
+use cgmath::{
+    dot, point3, vec3, Angle, Deg, EuclideanSpace, InnerSpace, Point3, Rad, Vector3, Zero,
+};
 use rand::prelude::SliceRandom;
 
+/// Borrows the material from the hittable that was hit, so recording a hit costs no allocation
+/// or reference counting.
 #[derive(Clone, Debug)]
-pub struct HitRecord {
+pub struct HitRecord<'a> {
     pub position: Point3<Float>,
     pub normal: Vector3<Float>,
     pub t: Float,
     pub u: Float,
     pub v: Float,
     pub front_face: bool,
-    pub material: Arc<Box<dyn Material>>,
+    /// Direction of increasing `u` on the surface (dp/du), which orients anisotropic
+    /// materials. Not necessarily normalized or orthogonal to `normal`; zero when the surface
+    /// has no parameterization.
+    pub tangent: Vector3<Float>,
+    pub material: &'a dyn Material,
+    /// Hero wavelength in nanometres of a spectral path, set by the integrator after the hit.
+    pub wavelength: Option<Float>,
 }
 
-impl HitRecord {
+impl<'a> HitRecord<'a> {
     pub fn new(
         position: Point3<Float>,
         outward_normal: Vector3<Float>,
@@ -25,7 +35,7 @@ impl HitRecord {
         u: Float,
         v: Float,
         ray: &Ray,
-        material: Arc<Box<dyn Material>>,
+        material: &'a dyn Material,
     ) -> Self {
         let front_face = dot(ray.direction, outward_normal) < 0.0;
         let normal = if front_face {
@@ -41,9 +51,42 @@ impl HitRecord {
             u,
             v,
             front_face,
+            tangent: Vector3::zero(),
             material,
+            wavelength: None,
         }
     }
+
+    /// Sets the surface tangent dp/du.
+    pub fn with_tangent(mut self, tangent: Vector3<Float>) -> Self {
+        self.tangent = tangent;
+        self
+    }
+
+    /// Replaces the shading normal while keeping `front_face`, which was decided by the
+    /// geometric normal.
+    pub fn with_shading_normal(mut self, shading_normal: Vector3<Float>) -> Self {
+        let shading_normal = shading_normal.normalize();
+        self.normal = if self.front_face {
+            shading_normal
+        } else {
+            -shading_normal
+        };
+        self
+    }
+}
+
+/// Converts the density `1 / area` of uniformly sampling a surface point to a density over
+/// directions, for the hit found along `direction`.
+pub fn area_to_solid_angle_pdf(
+    hit_record: &HitRecord,
+    direction: Vector3<Float>,
+    area: Float,
+) -> Float {
+    let distance_squared = hit_record.t * hit_record.t * direction.magnitude2();
+    let cosine = (dot(direction, hit_record.normal) / direction.magnitude()).abs();
+
+    distance_squared / (cosine * area)
 }
 
 pub struct Translate<T> {
@@ -61,18 +104,42 @@ pub struct RotateY<T> {
 pub struct FlipFace<T>(pub T);
 
 pub trait Hittable: Send + Sync {
-    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord>;
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord<'_>>;
     fn bounding_box(&self, time0: Float, time1: Float) -> Option<AABB>;
-    fn pdf_value(&self, _origin: Point3<Float>, _v: Vector3<Float>, _rng: &mut MyRng) -> Float {
+    /// Solid angle density of sampling direction `v` from `origin` at `time` with `random`.
+    fn pdf_value(
+        &self,
+        _origin: Point3<Float>,
+        _v: Vector3<Float>,
+        _time: Float,
+        _rng: &mut MyRng,
+    ) -> Float {
         0.0
     }
-    fn random(&self, _origin: Point3<Float>, _rng: &mut MyRng) -> Vector3<Float> {
+    fn random(&self, _origin: Point3<Float>, _time: Float, _rng: &mut MyRng) -> Vector3<Float> {
         vec3(1.0, 0.0, 0.0)
     }
+    /// Whether `pdf_value` and `random` are implemented, which lights need.
+    fn supports_sampling(&self) -> bool {
+        false
+    }
+    /// Surface area, used to estimate the power of lights.
+    fn area(&self) -> Option<Float> {
+        None
+    }
+    /// The material of the whole surface, if it has a single one.
+    fn material(&self) -> Option<&dyn Material> {
+        None
+    }
+    /// The objects an aggregate is made of, placed as they are in it, so that lights can be
+    /// found among them. Empty for objects that are not aggregates.
+    fn parts(&self) -> Vec<Arc<dyn Hittable>> {
+        Vec::new()
+    }
 }
 
 impl<T: Hittable + ?Sized> Hittable for &T {
-    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord<'_>> {
         (*self).hit(ray, t_min, t_max, rng)
     }
 
@@ -80,17 +147,39 @@ impl<T: Hittable + ?Sized> Hittable for &T {
         (*self).bounding_box(time0, time1)
     }
 
-    fn pdf_value(&self, o: Point3<Float>, v: Vector3<Float>, rng: &mut MyRng) -> Float {
-        (*self).pdf_value(o, v, rng)
+    fn pdf_value(
+        &self,
+        o: Point3<Float>,
+        v: Vector3<Float>,
+        time: Float,
+        rng: &mut MyRng,
+    ) -> Float {
+        (*self).pdf_value(o, v, time, rng)
     }
 
-    fn random(&self, o: Point3<Float>, rng: &mut MyRng) -> Vector3<Float> {
-        (*self).random(o, rng)
+    fn random(&self, o: Point3<Float>, time: Float, rng: &mut MyRng) -> Vector3<Float> {
+        (*self).random(o, time, rng)
+    }
+
+    fn supports_sampling(&self) -> bool {
+        (*self).supports_sampling()
+    }
+
+    fn area(&self) -> Option<Float> {
+        (*self).area()
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        (*self).material()
+    }
+
+    fn parts(&self) -> Vec<Arc<dyn Hittable>> {
+        (*self).parts()
     }
 }
 
 impl<T: Hittable + ?Sized> Hittable for Box<T> {
-    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord<'_>> {
         self.as_ref().hit(ray, t_min, t_max, rng)
     }
 
@@ -98,17 +187,79 @@ impl<T: Hittable + ?Sized> Hittable for Box<T> {
         self.as_ref().bounding_box(time0, time1)
     }
 
-    fn pdf_value(&self, o: Point3<Float>, v: Vector3<Float>, rng: &mut MyRng) -> Float {
-        self.as_ref().pdf_value(o, v, rng)
+    fn pdf_value(
+        &self,
+        o: Point3<Float>,
+        v: Vector3<Float>,
+        time: Float,
+        rng: &mut MyRng,
+    ) -> Float {
+        self.as_ref().pdf_value(o, v, time, rng)
+    }
+
+    fn random(&self, o: Point3<Float>, time: Float, rng: &mut MyRng) -> Vector3<Float> {
+        self.as_ref().random(o, time, rng)
     }
 
-    fn random(&self, o: Point3<Float>, rng: &mut MyRng) -> Vector3<Float> {
-        self.as_ref().random(o, rng)
+    fn supports_sampling(&self) -> bool {
+        self.as_ref().supports_sampling()
+    }
+
+    fn area(&self) -> Option<Float> {
+        self.as_ref().area()
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        self.as_ref().material()
+    }
+
+    fn parts(&self) -> Vec<Arc<dyn Hittable>> {
+        self.as_ref().parts()
+    }
+}
+
+impl<T: Hittable + ?Sized> Hittable for Arc<T> {
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord<'_>> {
+        self.as_ref().hit(ray, t_min, t_max, rng)
+    }
+
+    fn bounding_box(&self, time0: Float, time1: Float) -> Option<AABB> {
+        self.as_ref().bounding_box(time0, time1)
+    }
+
+    fn pdf_value(
+        &self,
+        o: Point3<Float>,
+        v: Vector3<Float>,
+        time: Float,
+        rng: &mut MyRng,
+    ) -> Float {
+        self.as_ref().pdf_value(o, v, time, rng)
+    }
+
+    fn random(&self, o: Point3<Float>, time: Float, rng: &mut MyRng) -> Vector3<Float> {
+        self.as_ref().random(o, time, rng)
+    }
+
+    fn supports_sampling(&self) -> bool {
+        self.as_ref().supports_sampling()
+    }
+
+    fn area(&self) -> Option<Float> {
+        self.as_ref().area()
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        self.as_ref().material()
+    }
+
+    fn parts(&self) -> Vec<Arc<dyn Hittable>> {
+        self.as_ref().parts()
     }
 }
 
 impl<T: Hittable> Hittable for [T] {
-    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord<'_>> {
         let mut hit_record = None;
         let mut closest_so_far = t_max;
 
@@ -141,16 +292,26 @@ impl<T: Hittable> Hittable for [T] {
         b
     }
 
-    fn pdf_value(&self, o: Point3<Float>, v: Vector3<Float>, rng: &mut MyRng) -> Float {
+    fn pdf_value(
+        &self,
+        o: Point3<Float>,
+        v: Vector3<Float>,
+        time: Float,
+        rng: &mut MyRng,
+    ) -> Float {
         let weight = 1.0 / self.len() as Float;
 
         self.iter()
-            .map(|hittable| weight * hittable.pdf_value(o, v, rng))
+            .map(|hittable| weight * hittable.pdf_value(o, v, time, rng))
             .sum()
     }
 
-    fn random(&self, o: Point3<Float>, rng: &mut MyRng) -> Vector3<Float> {
-        self.choose(rng).unwrap().random(o, rng)
+    fn random(&self, o: Point3<Float>, time: Float, rng: &mut MyRng) -> Vector3<Float> {
+        self.choose(rng).unwrap().random(o, time, rng)
+    }
+
+    fn supports_sampling(&self) -> bool {
+        !self.is_empty() && self.iter().all(|hittable| hittable.supports_sampling())
     }
 }
 
@@ -202,26 +363,35 @@ impl<T: Hittable> RotateY<T> {
     }
 }
 
+impl<T> RotateY<T> {
+    /// World to object space.
+    fn to_object(&self, v: Vector3<Float>) -> Vector3<Float> {
+        vec3(
+            self.cos_theta * v.x - self.sin_theta * v.z,
+            v.y,
+            self.sin_theta * v.x + self.cos_theta * v.z,
+        )
+    }
+
+    /// Object to world space.
+    fn to_world(&self, v: Vector3<Float>) -> Vector3<Float> {
+        vec3(
+            self.cos_theta * v.x + self.sin_theta * v.z,
+            v.y,
+            -self.sin_theta * v.x + self.cos_theta * v.z,
+        )
+    }
+}
+
 impl<T: Hittable> Hittable for Translate<T> {
-    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord> {
-        let moved = Ray {
-            origin: ray.origin - self.offset,
-            direction: ray.direction,
-            time: ray.time,
-        };
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord<'_>> {
+        let moved = ray.spawn(ray.origin - self.offset, ray.direction);
 
         self.hittable
             .hit(&moved, t_min, t_max, rng)
-            .map(|hit_record| {
-                HitRecord::new(
-                    hit_record.position + self.offset,
-                    hit_record.normal,
-                    hit_record.t,
-                    hit_record.u,
-                    hit_record.v,
-                    &moved,
-                    hit_record.material,
-                )
+            .map(|mut hit_record| {
+                hit_record.position += self.offset;
+                hit_record
             })
     }
 
@@ -231,10 +401,49 @@ impl<T: Hittable> Hittable for Translate<T> {
             maximum: aabb.maximum + self.offset,
         })
     }
+
+    fn pdf_value(
+        &self,
+        o: Point3<Float>,
+        v: Vector3<Float>,
+        time: Float,
+        rng: &mut MyRng,
+    ) -> Float {
+        self.hittable.pdf_value(o - self.offset, v, time, rng)
+    }
+
+    fn random(&self, o: Point3<Float>, time: Float, rng: &mut MyRng) -> Vector3<Float> {
+        self.hittable.random(o - self.offset, time, rng)
+    }
+
+    fn supports_sampling(&self) -> bool {
+        self.hittable.supports_sampling()
+    }
+
+    fn area(&self) -> Option<Float> {
+        self.hittable.area()
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        self.hittable.material()
+    }
+
+    fn parts(&self) -> Vec<Arc<dyn Hittable>> {
+        self.hittable
+            .parts()
+            .into_iter()
+            .map(|part| -> Arc<dyn Hittable> {
+                Arc::new(Translate {
+                    hittable: part,
+                    offset: self.offset,
+                })
+            })
+            .collect()
+    }
 }
 
 impl<T: Hittable> Hittable for RotateY<T> {
-    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord<'_>> {
         let mut origin = ray.origin;
         let mut direction = ray.direction;
 
@@ -244,47 +453,71 @@ impl<T: Hittable> Hittable for RotateY<T> {
         direction[0] = self.cos_theta * ray.direction[0] - self.sin_theta * ray.direction[2];
         direction[2] = self.sin_theta * ray.direction[0] + self.cos_theta * ray.direction[2];
 
-        let rotated_r = Ray {
-            origin,
-            direction,
-            time: ray.time,
-        };
+        let rotated_r = ray.spawn(origin, direction);
 
         self.hittable
             .hit(&rotated_r, t_min, t_max, rng)
-            .map(|hit_record| {
-                let mut p = hit_record.position;
-                let mut normal = hit_record.normal;
-
-                p[0] = self.cos_theta * hit_record.position[0]
-                    + self.sin_theta * hit_record.position[2];
-                p[2] = -self.sin_theta * hit_record.position[0]
-                    + self.cos_theta * hit_record.position[2];
-
-                normal[0] =
-                    self.cos_theta * hit_record.normal[0] + self.sin_theta * hit_record.normal[2];
-                normal[2] =
-                    -self.sin_theta * hit_record.normal[0] + self.cos_theta * hit_record.normal[2];
-
-                HitRecord::new(
-                    p,
-                    normal,
-                    hit_record.t,
-                    hit_record.u,
-                    hit_record.v,
-                    &rotated_r,
-                    hit_record.material,
-                )
+            .map(|mut hit_record| {
+                // The normal already faces the ray, so `front_face` is kept rather than recomputed.
+                hit_record.position = Point3::from_vec(self.to_world(hit_record.position.to_vec()));
+                hit_record.normal = self.to_world(hit_record.normal);
+                hit_record.tangent = self.to_world(hit_record.tangent);
+                hit_record
             })
     }
 
     fn bounding_box(&self, _time0: Float, _time1: Float) -> Option<AABB> {
         self.aabb
     }
+
+    // Rotations preserve solid angle, so only the query needs to be rotated.
+    fn pdf_value(
+        &self,
+        o: Point3<Float>,
+        v: Vector3<Float>,
+        time: Float,
+        rng: &mut MyRng,
+    ) -> Float {
+        let o = Point3::from_vec(self.to_object(o.to_vec()));
+        self.hittable.pdf_value(o, self.to_object(v), time, rng)
+    }
+
+    fn random(&self, o: Point3<Float>, time: Float, rng: &mut MyRng) -> Vector3<Float> {
+        let o = Point3::from_vec(self.to_object(o.to_vec()));
+        self.to_world(self.hittable.random(o, time, rng))
+    }
+
+    fn supports_sampling(&self) -> bool {
+        self.hittable.supports_sampling()
+    }
+
+    fn area(&self) -> Option<Float> {
+        self.hittable.area()
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        self.hittable.material()
+    }
+
+    /// Parts keep the bounding box of the whole object.
+    fn parts(&self) -> Vec<Arc<dyn Hittable>> {
+        self.hittable
+            .parts()
+            .into_iter()
+            .map(|part| -> Arc<dyn Hittable> {
+                Arc::new(RotateY {
+                    hittable: part,
+                    sin_theta: self.sin_theta,
+                    cos_theta: self.cos_theta,
+                    aabb: self.aabb,
+                })
+            })
+            .collect()
+    }
 }
 
 impl<T: Hittable> Hittable for FlipFace<T> {
-    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord<'_>> {
         self.0.hit(ray, t_min, t_max, rng).map(|mut hit_record| {
             hit_record.front_face = !hit_record.front_face;
             hit_record
@@ -294,4 +527,38 @@ impl<T: Hittable> Hittable for FlipFace<T> {
     fn bounding_box(&self, time0: Float, time1: Float) -> Option<AABB> {
         self.0.bounding_box(time0, time1)
     }
+
+    fn pdf_value(
+        &self,
+        o: Point3<Float>,
+        v: Vector3<Float>,
+        time: Float,
+        rng: &mut MyRng,
+    ) -> Float {
+        self.0.pdf_value(o, v, time, rng)
+    }
+
+    fn random(&self, o: Point3<Float>, time: Float, rng: &mut MyRng) -> Vector3<Float> {
+        self.0.random(o, time, rng)
+    }
+
+    fn supports_sampling(&self) -> bool {
+        self.0.supports_sampling()
+    }
+
+    fn area(&self) -> Option<Float> {
+        self.0.area()
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        self.0.material()
+    }
+
+    fn parts(&self) -> Vec<Arc<dyn Hittable>> {
+        self.0
+            .parts()
+            .into_iter()
+            .map(|part| -> Arc<dyn Hittable> { Arc::new(FlipFace(part)) })
+            .collect()
+    }
 }
@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use cgmath::{Point3, Vector3};
+
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    transform::{Affine, Transform},
+    Float, MyRng,
+};
+
+/// A placement of shared geometry with its own object-to-world transform and, optionally, its
+/// own material.
+///
+/// The geometry, typically a [`FlatBVH`](crate::bvh::FlatBVH), is built once and referenced by
+/// every instance, so a top-level BVH over instances only stores one transform per copy.
+pub struct Instance {
+    object: Transform<Arc<dyn Hittable>>,
+    material: Option<Arc<Box<dyn Material>>>,
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Hittable>, to_world: Affine, time0: Float, time1: Float) -> Self {
+        Self {
+            object: Transform::new(object, to_world, time0, time1),
+            material: None,
+        }
+    }
+
+    /// Replaces the material of every hit on this instance.
+    pub fn with_material(mut self, material: Arc<Box<dyn Material>>) -> Self {
+        self.material = Some(material);
+        self
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord<'_>> {
+        self.object
+            .hit(ray, t_min, t_max, rng)
+            .map(|mut hit_record| {
+                if let Some(material) = &self.material {
+                    hit_record.material = &***material;
+                }
+                hit_record
+            })
+    }
+
+    fn bounding_box(&self, time0: Float, time1: Float) -> Option<AABB> {
+        self.object.bounding_box(time0, time1)
+    }
+
+    fn pdf_value(
+        &self,
+        origin: Point3<Float>,
+        v: Vector3<Float>,
+        time: Float,
+        rng: &mut MyRng,
+    ) -> Float {
+        self.object.pdf_value(origin, v, time, rng)
+    }
+
+    fn random(&self, origin: Point3<Float>, time: Float, rng: &mut MyRng) -> Vector3<Float> {
+        self.object.random(origin, time, rng)
+    }
+
+    fn supports_sampling(&self) -> bool {
+        self.object.supports_sampling()
+    }
+
+    fn area(&self) -> Option<Float> {
+        self.object.area()
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        match &self.material {
+            Some(material) => Some(&***material),
+            None => self.object.material(),
+        }
+    }
+
+    fn parts(&self) -> Vec<Arc<dyn Hittable>> {
+        self.object
+            .transformed_parts()
+            .into_iter()
+            .map(|object| -> Arc<dyn Hittable> {
+                Arc::new(Instance {
+                    object,
+                    material: self.material.clone(),
+                })
+            })
+            .collect()
+    }
+}
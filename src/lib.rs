@@ -0,0 +1,56 @@
+//! Path tracer from the Ray Tracing in One Weekend book series.
+
+use rand::rngs::StdRng;
+
+pub type Float = f64;
+pub type MyRng = StdRng;
+
+pub mod aabb;
+pub mod aabox;
+pub mod aarect;
+pub mod animation;
+pub mod bvh;
+pub mod camera;
+pub mod color;
+pub mod constant_medium;
+pub mod delta_light;
+pub mod hittable;
+pub mod instance;
+pub mod light;
+pub mod material;
+pub mod math;
+pub mod mesh;
+pub mod microfacet;
+pub mod moving_sphere;
+pub mod obj;
+pub mod onb;
+pub mod output;
+pub mod pdf;
+pub mod perlin;
+pub mod principled;
+pub mod ray;
+pub mod render;
+pub mod scene;
+pub mod scenes;
+pub mod spectrum;
+pub mod sphere;
+pub mod texture;
+pub mod transform;
+pub mod triangle;
+
+/// Compiles the examples in the README, so they keep up with the API.
+#[doc = include_str!("../README.md")]
+#[cfg(doctest)]
+pub struct ReadmeDoctests;
+
+pub use crate::{
+    bvh::{BVHNode, FlatBVH},
+    camera::Camera,
+    color::Color,
+    hittable::Hittable,
+    material::Material,
+    pdf::Pdf,
+    render::{render, Image, Renderer},
+    scene::Scene,
+    texture::Texture,
+};
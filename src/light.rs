@@ -0,0 +1,218 @@
+use std::sync::Arc;
+
+use cgmath::{InnerSpace, Point3, Vector3};
+use num_traits::FloatConst;
+use rand::{Rng, SeedableRng};
+
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    math::random_in_unit_sphere,
+    ray::Ray,
+    Float, MyRng,
+};
+
+/// Lights for direct sampling, each picked with probability proportional to its weight.
+pub struct LightList {
+    lights: Vec<Arc<dyn Hittable>>,
+    /// Running sum of the weights, normalized to end at 1.
+    cdf: Vec<Float>,
+}
+
+/// Collects the lights of a scene while its objects are assembled.
+///
+/// Objects passed to [`LightsBuilder::discover`] become lights when their material emits, weighed
+/// by their estimated power.
+#[derive(Default)]
+pub struct LightsBuilder {
+    discovered: Vec<(Arc<dyn Hittable>, Float)>,
+    extra: Vec<Arc<dyn Hittable>>,
+}
+
+/// Estimated power emitted by `object`, or zero if it has no single emissive material, no
+/// known area or cannot be sampled.
+pub fn emitted_power(object: &dyn Hittable) -> Float {
+    match (object.material(), object.area()) {
+        (Some(material), Some(area)) => {
+            let surface = surface_points(object);
+            Float::PI() * area * material.average_emission(&surface).luminance().max(0.0)
+        }
+        _ => 0.0,
+    }
+}
+
+/// Points spread over the surface of `object`, found by sampling it from directions all around
+/// at time 0. Seen from far enough away, the first hits are spread evenly over a convex
+/// surface. Empty if `object` cannot be sampled or is unbounded.
+fn surface_points(object: &dyn Hittable) -> Vec<HitRecord<'_>> {
+    const SAMPLES: usize = 256;
+
+    let aabb = match object.bounding_box(0.0, 0.0) {
+        Some(aabb) if object.supports_sampling() => aabb,
+        _ => return Vec::new(),
+    };
+    let center = aabb.minimum + (aabb.maximum - aabb.minimum) / 2.0;
+    let distance = 4.0 * (aabb.maximum - aabb.minimum).magnitude();
+    if !(distance > 0.0 && distance.is_finite()) {
+        return Vec::new();
+    }
+
+    let mut rng = MyRng::seed_from_u64(0);
+    (0..SAMPLES)
+        .filter_map(|_| {
+            let origin = center + distance * random_in_unit_sphere(&mut rng).normalize();
+            let ray = Ray {
+                origin,
+                direction: object.random(origin, 0.0, &mut rng),
+                time: 0.0,
+            };
+            object.hit(&ray, 0.0, Float::INFINITY, &mut rng)
+        })
+        .collect()
+}
+
+/// The power of `object` if it is a light, see [`LightsBuilder::discover`].
+fn light_power(object: &dyn Hittable) -> Option<Float> {
+    let power = emitted_power(object);
+    (power > 0.0 && power.is_finite()).then_some(power)
+}
+
+impl LightList {
+    /// Panics unless there is at least one light and every weight is positive and finite.
+    pub fn new(lights: Vec<(Arc<dyn Hittable>, Float)>) -> Self {
+        assert!(!lights.is_empty(), "a light list needs at least one light");
+        assert!(
+            lights
+                .iter()
+                .all(|(_, weight)| weight.is_finite() && *weight > 0.0),
+            "light weights must be positive and finite"
+        );
+
+        let total: Float = lights.iter().map(|(_, weight)| weight).sum();
+        let mut sum = 0.0;
+        let cdf = lights
+            .iter()
+            .map(|(_, weight)| {
+                sum += weight;
+                sum / total
+            })
+            .collect();
+
+        Self {
+            lights: lights.into_iter().map(|(light, _)| light).collect(),
+            cdf,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    /// Probability of picking light `index`.
+    pub fn probability(&self, index: usize) -> Float {
+        if index == 0 {
+            self.cdf[0]
+        } else {
+            self.cdf[index] - self.cdf[index - 1]
+        }
+    }
+
+    fn pick(&self, rng: &mut MyRng) -> usize {
+        let target: Float = rng.gen();
+        self.cdf
+            .partition_point(|&p| p <= target)
+            .min(self.lights.len() - 1)
+    }
+}
+
+impl Hittable for LightList {
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord<'_>> {
+        self.lights.as_slice().hit(ray, t_min, t_max, rng)
+    }
+
+    fn bounding_box(&self, time0: Float, time1: Float) -> Option<AABB> {
+        self.lights.as_slice().bounding_box(time0, time1)
+    }
+
+    fn pdf_value(
+        &self,
+        origin: Point3<Float>,
+        v: Vector3<Float>,
+        time: Float,
+        rng: &mut MyRng,
+    ) -> Float {
+        (0..self.lights.len())
+            .map(|i| self.probability(i) * self.lights[i].pdf_value(origin, v, time, rng))
+            .sum()
+    }
+
+    fn random(&self, origin: Point3<Float>, time: Float, rng: &mut MyRng) -> Vector3<Float> {
+        let i = self.pick(rng);
+        self.lights[i].random(origin, time, rng)
+    }
+
+    fn supports_sampling(&self) -> bool {
+        self.lights.iter().all(|light| light.supports_sampling())
+    }
+}
+
+impl LightsBuilder {
+    /// Returns `object`, sharing it with the light list if it emits and can be sampled. Otherwise
+    /// lights are looked for among its [`parts`](Hittable::parts), such as the meshes of an OBJ
+    /// file or the objects of an instance.
+    pub fn discover(&mut self, object: Box<dyn Hittable>) -> Box<dyn Hittable> {
+        if let Some(power) = light_power(object.as_ref()) {
+            let object: Arc<dyn Hittable> = Arc::from(object);
+            self.discovered.push((object.clone(), power));
+            Box::new(object)
+        } else {
+            self.discover_parts(object.parts());
+            object
+        }
+    }
+
+    fn discover_parts(&mut self, parts: Vec<Arc<dyn Hittable>>) {
+        for part in parts {
+            if let Some(power) = light_power(part.as_ref()) {
+                self.discovered.push((part, power));
+            } else {
+                self.discover_parts(part.parts());
+            }
+        }
+    }
+
+    /// Adds a light that is sampled without being discovered, such as a glass sphere worth
+    /// aiming at. It is picked as often as an average discovered light.
+    pub fn add(&mut self, light: Box<dyn Hittable>) {
+        self.extra.push(Arc::from(light));
+    }
+
+    /// `None` if no lights were found or added.
+    pub fn build(self) -> Option<LightList> {
+        let extra_weight = if self.discovered.is_empty() {
+            1.0
+        } else {
+            self.discovered
+                .iter()
+                .map(|(_, power)| power)
+                .sum::<Float>()
+                / self.discovered.len() as Float
+        };
+
+        let lights: Vec<_> = self
+            .discovered
+            .into_iter()
+            .chain(self.extra.into_iter().map(|light| (light, extra_weight)))
+            .collect();
+
+        if lights.is_empty() {
+            None
+        } else {
+            Some(LightList::new(lights))
+        }
+    }
+}
@@ -1,8 +1,10 @@
 use std::fmt::Debug;
 
 use crate::{
+    microfacet::{self, fresnel_conductor, fresnel_dielectric, Ggx, ShadingFrame},
     onb::Onb,
     pdf::{CosinePdf, Pdf},
+    spectrum::Dispersion,
     texture::Texture,
     Float,
 };
@@ -12,31 +14,61 @@ use rand::Rng;
 
 use crate::{color::Color, hittable::HitRecord, math::random_in_unit_sphere, ray::Ray, MyRng};
 
-pub enum ScatterKind {
-    Spacular(Ray),
-    Pdf(Box<dyn Pdf>),
-}
-
-pub struct Scatter {
-    pub kind: ScatterKind,
-    pub attenuation: Color,
+/// A direction drawn by [`Material::sample`].
+#[derive(Clone, Copy, Debug)]
+pub struct BsdfSample {
+    /// Normalized direction the light arrives from.
+    pub wi: Vector3<Float>,
+    /// `eval(wo, wi) / pdf`, or the throughput of a delta lobe.
+    pub weight: Color,
+    /// Solid angle density of `wi`, zero for delta samples.
+    pub pdf: Float,
+    /// Whether `wi` came from a delta lobe, which `eval` and `pdf` leave out and light sampling
+    /// can never hit.
+    pub is_delta: bool,
 }
 
+/// Scattering at a surface or in a medium. `wo` points towards where the light leaves to and
+/// `wi` towards where it arrives from; both are normalized and point away from the hit.
 pub trait Material: Debug + Send + Sync {
-    fn scatter(&self, _ray: &Ray, _hit_record: &HitRecord, _rng: &mut MyRng) -> Option<Scatter> {
-        None
+    /// The BSDF times the cosine between `wi` and the normal, so phase functions fit as well.
+    fn eval(&self, _hit_record: &HitRecord, _wo: Vector3<Float>, _wi: Vector3<Float>) -> Color {
+        Color(vec3(0.0, 0.0, 0.0))
     }
 
-    fn scattering_pdf(
+    /// Samples `wi`, or returns `None` if the light is absorbed.
+    fn sample(
         &self,
-        _ray_in: &Ray,
         _hit_record: &HitRecord,
-        _ray_scatterd: &Ray,
+        _wo: Vector3<Float>,
         _rng: &mut MyRng,
-    ) -> Float {
+    ) -> Option<BsdfSample> {
+        None
+    }
+
+    /// Solid angle density of `sample` returning `wi`.
+    fn pdf(&self, _hit_record: &HitRecord, _wo: Vector3<Float>, _wi: Vector3<Float>) -> Float {
         0.0
     }
 
+    /// Whether scattered directions depend on [`HitRecord::wavelength`], so a spectral path has
+    /// to drop all but its hero wavelength.
+    fn is_dispersive(&self) -> bool {
+        false
+    }
+
+    /// Fraction of light left after travelling `distance` inside an object made of this
+    /// material.
+    fn transmittance(&self, _distance: Float) -> Color {
+        Color(vec3(1.0, 1.0, 1.0))
+    }
+
+    /// Whether hitting this material means crossing the boundary of an object, which a path
+    /// enters or leaves. Phase functions scatter inside a volume and answer `false`.
+    fn bounds_medium(&self) -> bool {
+        true
+    }
+
     fn emitted(
         &self,
         _ray_in: &Ray,
@@ -47,6 +79,12 @@ pub trait Material: Debug + Send + Sync {
     ) -> Color {
         Color(vec3(0.0, 0.0, 0.0))
     }
+
+    /// Rough average radiance leaving the surface, summed over the faces that emit, estimated at
+    /// the `surface` points of an object. Used to find and weigh lights.
+    fn average_emission(&self, _surface: &[HitRecord]) -> Color {
+        Color(vec3(0.0, 0.0, 0.0))
+    }
 }
 
 #[derive(Debug)]
@@ -60,34 +98,70 @@ pub struct Metal {
     pub fuzz: Float,
 }
 
+/// Complex index of refraction `eta + i k` of a metal, sampled at red, green and blue.
+#[derive(Clone, Copy, Debug)]
+pub struct ComplexIor {
+    pub eta: Color,
+    pub k: Color,
+}
+
+/// A metal with GGX microfacets, reflecting according to its complex index of refraction.
+#[derive(Debug)]
+pub struct Conductor {
+    pub ior: ComplexIor,
+    pub distribution: Ggx,
+}
+
+/// Frosted glass: a dielectric with GGX microfacets whose roughness is read from the luminance
+/// of a texture, reflecting and refracting after Walter et al. 2007.
+#[derive(Debug)]
+pub struct RoughDielectric<T> {
+    pub ir: Float,
+    pub roughness: T,
+}
+
+/// Emits the radiance of a texture times `scale`, from the front face unless `two_sided`.
 #[derive(Debug)]
 pub struct DiffuseLight<T> {
     pub emit: T,
+    pub scale: Float,
+    pub two_sided: bool,
 }
 
 impl Material for () {}
 
 impl<T: Texture> Material for Lambertian<T> {
-    fn scatter(&self, _ray: &Ray, hit_record: &HitRecord, _rng: &mut MyRng) -> Option<Scatter> {
-        Some(Scatter {
-            attenuation: self
+    fn eval(&self, hit_record: &HitRecord, _wo: Vector3<Float>, wi: Vector3<Float>) -> Color {
+        let cosine = dot(hit_record.normal, wi).max(0.0);
+        let albedo = self
+            .albedo
+            .value(hit_record.u, hit_record.v, hit_record.position);
+        Color(albedo.0 * (cosine / Float::PI()))
+    }
+
+    fn sample(
+        &self,
+        hit_record: &HitRecord,
+        _wo: Vector3<Float>,
+        rng: &mut MyRng,
+    ) -> Option<BsdfSample> {
+        let cosine_pdf = CosinePdf {
+            uvw: Onb::from_w(hit_record.normal),
+        };
+        let wi = cosine_pdf.generate(rng).normalize();
+
+        Some(BsdfSample {
+            wi,
+            weight: self
                 .albedo
                 .value(hit_record.u, hit_record.v, hit_record.position),
-            kind: ScatterKind::Pdf(Box::new(CosinePdf {
-                uvw: Onb::from_w(hit_record.normal),
-            })),
+            pdf: cosine_pdf.value(wi, rng),
+            is_delta: false,
         })
     }
 
-    fn scattering_pdf(
-        &self,
-        _ray_in: &Ray,
-        hit_record: &HitRecord,
-        ray_scatterd: &Ray,
-        _rng: &mut MyRng,
-    ) -> Float {
-        let cosine = dot(hit_record.normal, ray_scatterd.direction.normalize());
-        (cosine / Float::PI()).max(0.0)
+    fn pdf(&self, hit_record: &HitRecord, _wo: Vector3<Float>, wi: Vector3<Float>) -> Float {
+        (dot(hit_record.normal, wi) / Float::PI()).max(0.0)
     }
 }
 
@@ -96,19 +170,107 @@ fn reflect(v: Vector3<Float>, n: Vector3<Float>) -> Vector3<Float> {
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, rng: &mut MyRng) -> Option<Scatter> {
-        let reflected = reflect(ray.direction.normalize(), hit_record.normal);
-        let spacular_ray = Ray {
-            origin: hit_record.position,
-            direction: reflected + self.fuzz * random_in_unit_sphere(rng),
-            time: ray.time,
-        };
+    /// Fuzzy reflection has no closed-form density, so it is sampled as a delta lobe.
+    fn sample(
+        &self,
+        hit_record: &HitRecord,
+        wo: Vector3<Float>,
+        rng: &mut MyRng,
+    ) -> Option<BsdfSample> {
+        let reflected = reflect(-wo, hit_record.normal);
+
+        Some(BsdfSample {
+            wi: (reflected + self.fuzz * random_in_unit_sphere(rng)).normalize(),
+            weight: self.albedo,
+            pdf: 0.0,
+            is_delta: true,
+        })
+    }
+}
+
+impl ComplexIor {
+    pub const GOLD: Self = Self::new([0.143, 0.374, 1.442], [3.983, 2.385, 1.603]);
+    pub const COPPER: Self = Self::new([0.200, 0.924, 1.102], [3.912, 2.452, 2.142]);
+    pub const ALUMINIUM: Self = Self::new([1.657, 0.880, 0.521], [9.224, 6.270, 4.837]);
+    pub const SILVER: Self = Self::new([0.155, 0.117, 0.138], [4.828, 3.122, 2.147]);
+
+    const fn new(eta: [Float; 3], k: [Float; 3]) -> Self {
+        Self {
+            eta: Color(Vector3::new(eta[0], eta[1], eta[2])),
+            k: Color(Vector3::new(k[0], k[1], k[2])),
+        }
+    }
+
+    /// Looks up a preset by name: `gold`, `copper`, `aluminium` (or `aluminum`) or `silver`.
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "gold" => Some(Self::GOLD),
+            "copper" => Some(Self::COPPER),
+            "aluminium" | "aluminum" => Some(Self::ALUMINIUM),
+            "silver" => Some(Self::SILVER),
+            _ => None,
+        }
+    }
+}
+
+impl Material for Conductor {
+    fn eval(&self, hit_record: &HitRecord, wo: Vector3<Float>, wi: Vector3<Float>) -> Color {
+        if self.distribution.is_smooth() {
+            return Color(vec3(0.0, 0.0, 0.0));
+        }
+        let frame = ShadingFrame::from_hit(hit_record);
+        let (wo, wi) = (frame.to_local(wo), frame.to_local(wi));
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Color(vec3(0.0, 0.0, 0.0));
+        }
+
+        let wm = (wo + wi).normalize();
+        let fresnel = fresnel_conductor(dot(wo, wm), self.ior.eta, self.ior.k);
+        let ggx = &self.distribution;
+        Color(fresnel.0 * (ggx.d(wm) * ggx.g(wo, wi) / (4.0 * wo.z)))
+    }
+
+    fn sample(
+        &self,
+        hit_record: &HitRecord,
+        wo: Vector3<Float>,
+        rng: &mut MyRng,
+    ) -> Option<BsdfSample> {
+        let frame = ShadingFrame::from_hit(hit_record);
+        let wo = frame.to_local(wo);
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        if self.distribution.is_smooth() {
+            return Some(BsdfSample {
+                wi: frame.to_world(vec3(-wo.x, -wo.y, wo.z)),
+                weight: fresnel_conductor(wo.z, self.ior.eta, self.ior.k),
+                pdf: 0.0,
+                is_delta: true,
+            });
+        }
 
-        Some(Scatter {
-            kind: ScatterKind::Spacular(spacular_ray),
-            attenuation: self.albedo,
+        let ggx = &self.distribution;
+        let wi = microfacet::sample_reflection(ggx, wo, rng)?;
+        let wm = (wo + wi).normalize();
+
+        let fresnel = fresnel_conductor(dot(wo, wm), self.ior.eta, self.ior.k);
+        Some(BsdfSample {
+            wi: frame.to_world(wi).normalize(),
+            weight: Color(fresnel.0 * (ggx.g(wo, wi) / ggx.g1(wo))),
+            pdf: microfacet::reflection_pdf(ggx, wo, wi),
+            is_delta: false,
         })
     }
+
+    fn pdf(&self, hit_record: &HitRecord, wo: Vector3<Float>, wi: Vector3<Float>) -> Float {
+        if self.distribution.is_smooth() {
+            return 0.0;
+        }
+        let frame = ShadingFrame::from_hit(hit_record);
+        microfacet::reflection_pdf(&self.distribution, frame.to_local(wo), frame.to_local(wi))
+    }
 }
 
 fn refract(uv: Vector3<Float>, n: Vector3<Float>, etai_over_etat: Float) -> Vector3<Float> {
@@ -124,20 +286,73 @@ fn reflectance(cosine: Float, ref_idx: Float) -> Float {
     r0 + (1.0 - r0) * (1.0 - cosine).powf(5.0)
 }
 
+/// Smooth glass, tinted by Beer-Lambert absorption inside.
 #[derive(Debug)]
 pub struct Dielectric {
     pub ir: Float,
+    /// Absorption coefficient per unit length in each channel.
+    pub absorption: Color,
+    /// Replaces `ir` when rendering spectrally.
+    pub dispersion: Option<Dispersion>,
+}
+
+impl Dielectric {
+    /// Clear glass.
+    pub fn new(ir: Float) -> Self {
+        Self {
+            ir,
+            absorption: Color(vec3(0.0, 0.0, 0.0)),
+            dispersion: None,
+        }
+    }
+
+    /// Glass that leaves `color` of the light after it travels `distance` inside.
+    pub fn tinted(ir: Float, color: Color, distance: Float) -> Self {
+        let absorption = |c: Float| -c.clamp(Float::MIN_POSITIVE, 1.0).ln() / distance;
+        Self {
+            ir,
+            absorption: Color(vec3(
+                absorption(color.x),
+                absorption(color.y),
+                absorption(color.z),
+            )),
+            dispersion: None,
+        }
+    }
+
+    /// Index of refraction at the wavelength of the hit, if there is one.
+    fn ir_at(&self, hit_record: &HitRecord) -> Float {
+        match (self.dispersion, hit_record.wavelength) {
+            (Some(dispersion), Some(wavelength)) => dispersion.ior(wavelength),
+            _ => self.ir,
+        }
+    }
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, rng: &mut MyRng) -> Option<Scatter> {
-        let refraction_ratio = if hit_record.front_face {
-            1.0 / self.ir
-        } else {
-            self.ir
-        };
+    fn is_dispersive(&self) -> bool {
+        self.dispersion.is_some()
+    }
+
+    fn transmittance(&self, distance: Float) -> Color {
+        let absorption = self.absorption.0 * distance;
+        Color(vec3(
+            (-absorption.x).exp(),
+            (-absorption.y).exp(),
+            (-absorption.z).exp(),
+        ))
+    }
+
+    fn sample(
+        &self,
+        hit_record: &HitRecord,
+        wo: Vector3<Float>,
+        rng: &mut MyRng,
+    ) -> Option<BsdfSample> {
+        let ir = self.ir_at(hit_record);
+        let refraction_ratio = if hit_record.front_face { 1.0 / ir } else { ir };
 
-        let unit_direction = ray.direction.normalize();
+        let unit_direction = -wo;
         let cos_theta = dot(-unit_direction, hit_record.normal).min(1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
@@ -149,22 +364,121 @@ impl Material for Dielectric {
                 refract(unit_direction, hit_record.normal, refraction_ratio)
             };
 
-        Some(Scatter {
-            attenuation: Color(vec3(1.0, 1.0, 1.0)),
-            kind: ScatterKind::Spacular(Ray {
-                origin: hit_record.position,
-                direction,
-                time: ray.time,
-            }),
+        Some(BsdfSample {
+            wi: direction.normalize(),
+            weight: Color(vec3(1.0, 1.0, 1.0)),
+            pdf: 0.0,
+            is_delta: true,
         })
     }
 }
 
-impl<T: Texture> Material for DiffuseLight<T> {
-    fn scatter(&self, _ray: &Ray, _hit_record: &HitRecord, _rng: &mut MyRng) -> Option<Scatter> {
-        None
+impl<T: Texture> RoughDielectric<T> {
+    fn distribution(&self, hit_record: &HitRecord) -> Ggx {
+        let roughness = self
+            .roughness
+            .value(hit_record.u, hit_record.v, hit_record.position)
+            .luminance();
+        Ggx::isotropic(roughness.clamp(0.0, 1.0))
+    }
+
+    /// Index of refraction on the far side of the hit over the one on the side of the normal.
+    fn eta(&self, hit_record: &HitRecord) -> Float {
+        if hit_record.front_face {
+            self.ir
+        } else {
+            1.0 / self.ir
+        }
+    }
+}
+
+impl<T: Texture> Material for RoughDielectric<T> {
+    fn eval(&self, hit_record: &HitRecord, wo: Vector3<Float>, wi: Vector3<Float>) -> Color {
+        let ggx = self.distribution(hit_record);
+        if ggx.is_smooth() {
+            return Color(vec3(0.0, 0.0, 0.0));
+        }
+        let frame = ShadingFrame::from_hit(hit_record);
+        let (value, _) = microfacet::dielectric_eval_pdf(
+            &ggx,
+            self.eta(hit_record),
+            frame.to_local(wo),
+            frame.to_local(wi),
+        );
+        Color(vec3(value, value, value))
     }
 
+    fn sample(
+        &self,
+        hit_record: &HitRecord,
+        wo: Vector3<Float>,
+        rng: &mut MyRng,
+    ) -> Option<BsdfSample> {
+        let frame = ShadingFrame::from_hit(hit_record);
+        let wo = frame.to_local(wo);
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        let ggx = self.distribution(hit_record);
+        let eta = self.eta(hit_record);
+        if ggx.is_smooth() {
+            let n = vec3(0.0, 0.0, 1.0);
+            let (wi, weight) = if rng.gen::<Float>() < fresnel_dielectric(wo.z, eta) {
+                (microfacet::reflect(wo, n), 1.0)
+            } else {
+                (microfacet::refract(wo, n, eta)?, 1.0)
+            };
+            return Some(BsdfSample {
+                wi: frame.to_world(wi).normalize(),
+                weight: Color(vec3(weight, weight, weight)),
+                pdf: 0.0,
+                is_delta: true,
+            });
+        }
+
+        let wi = microfacet::sample_dielectric(&ggx, eta, wo, rng)?;
+        let (value, pdf) = microfacet::dielectric_eval_pdf(&ggx, eta, wo, wi);
+        if pdf == 0.0 {
+            return None;
+        }
+        let weight = value / pdf;
+        Some(BsdfSample {
+            wi: frame.to_world(wi).normalize(),
+            weight: Color(vec3(weight, weight, weight)),
+            pdf,
+            is_delta: false,
+        })
+    }
+
+    fn pdf(&self, hit_record: &HitRecord, wo: Vector3<Float>, wi: Vector3<Float>) -> Float {
+        let ggx = self.distribution(hit_record);
+        if ggx.is_smooth() {
+            return 0.0;
+        }
+        let frame = ShadingFrame::from_hit(hit_record);
+        microfacet::dielectric_eval_pdf(
+            &ggx,
+            self.eta(hit_record),
+            frame.to_local(wo),
+            frame.to_local(wi),
+        )
+        .1
+    }
+}
+
+impl<T> DiffuseLight<T> {
+    /// A one-sided light emitting the radiance of `emit` as is.
+    pub fn new(emit: T) -> Self {
+        Self {
+            emit,
+            scale: 1.0,
+            two_sided: false,
+        }
+    }
+}
+
+impl<T: Texture> Material for DiffuseLight<T> {
     fn emitted(
         &self,
         _ray_in: &Ray,
@@ -173,10 +487,28 @@ impl<T: Texture> Material for DiffuseLight<T> {
         v: Float,
         p: Point3<Float>,
     ) -> Color {
-        if hit_record.front_face {
-            self.emit.value(u, v, p)
+        if hit_record.front_face || self.two_sided {
+            Color(self.emit.value(u, v, p).0 * self.scale)
         } else {
             Color(vec3(0.0, 0.0, 0.0))
         }
     }
+
+    /// Averages the texture at the points, whichever face they are on, doubled when both faces
+    /// emit.
+    fn average_emission(&self, surface: &[HitRecord]) -> Color {
+        if surface.is_empty() {
+            return Color(vec3(0.0, 0.0, 0.0));
+        }
+        let sum = surface
+            .iter()
+            .map(|hit_record| {
+                self.emit
+                    .value(hit_record.u, hit_record.v, hit_record.position)
+                    .0
+            })
+            .fold(vec3(0.0, 0.0, 0.0), |sum, value| sum + value);
+        let sides = if self.two_sided { 2.0 } else { 1.0 };
+        Color(sum * (self.scale * sides / surface.len() as Float))
+    }
 }
@@ -17,7 +17,6 @@ pub fn random_in_unit_sphere(rng: &mut impl Rng) -> Vector3<Float> {
     }
 }
 
-#[allow(dead_code)]
 pub fn random_in_hemisphere(normal: Vector3<Float>, rng: &mut impl Rng) -> Vector3<Float> {
     let v = random_in_unit_sphere(rng).normalize();
     if dot(normal, v) > 0.0 {
@@ -70,6 +69,12 @@ pub fn sphere_uv(point: Point3<Float>) -> (Float, Float) {
     (phi / (2.0 * Float::PI()), theta / Float::PI())
 }
 
+/// The tangent dp/du of the parameterization in [`sphere_uv`] at the point `radius * normal`
+/// from the center. It vanishes at the poles.
+pub fn sphere_tangent(normal: Vector3<Float>, radius: Float) -> Vector3<Float> {
+    2.0 * Float::PI() * radius * vec3(normal.z, 0.0, -normal.x)
+}
+
 pub trait IsNearZero {
     fn is_near_zero(&self) -> bool;
 }
@@ -0,0 +1,293 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    sync::Arc,
+};
+
+use cgmath::{dot, EuclideanSpace, InnerSpace, Point2, Point3, Vector3};
+use rand::Rng;
+
+use crate::{
+    aabb::AABB,
+    bvh::{BVHNode, FlatBVH, SplitMethod},
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    triangle::{
+        intersect_triangle, random_point_on_triangle, triangle_area, triangle_bounding_box,
+        triangle_tangent,
+    },
+    Float, MyRng,
+};
+
+/// Vertex buffers shared by every face of a [`TriangleMesh`].
+#[derive(Clone, Debug, Default)]
+pub struct Mesh {
+    pub positions: Vec<Point3<Float>>,
+    pub normals: Vec<Vector3<Float>>,
+    pub uvs: Vec<Point2<Float>>,
+    pub faces: Vec<Face>,
+}
+
+/// Indices into the buffers of a [`Mesh`]. Normals and UVs are optional per face.
+#[derive(Clone, Copy, Debug)]
+pub struct Face {
+    pub positions: [usize; 3],
+    pub normals: Option<[usize; 3]>,
+    pub uvs: Option<[usize; 3]>,
+}
+
+/// Why a [`Mesh`] cannot be made into a [`TriangleMesh`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvalidMesh {
+    /// The mesh has no faces.
+    Empty,
+    /// A face refers to a vertex, normal or UV that does not exist.
+    InvalidFace { face: usize },
+}
+
+pub struct TriangleMesh {
+    mesh: Arc<Mesh>,
+    bvh: FlatBVH,
+    /// Running sum of face areas, for picking a face proportionally to its area.
+    area_cdf: Vec<Float>,
+    material: Arc<Box<dyn Material>>,
+}
+
+struct MeshTriangle {
+    mesh: Arc<Mesh>,
+    face: usize,
+    material: Arc<Box<dyn Material>>,
+}
+
+impl Mesh {
+    fn validate(&self) -> Result<(), InvalidMesh> {
+        if self.faces.is_empty() {
+            return Err(InvalidMesh::Empty);
+        }
+        let invalid = self.faces.iter().position(|face| {
+            !(face.positions.iter().all(|&i| i < self.positions.len())
+                && face
+                    .normals
+                    .is_none_or(|n| n.iter().all(|&i| i < self.normals.len()))
+                && face
+                    .uvs
+                    .is_none_or(|uv| uv.iter().all(|&i| i < self.uvs.len())))
+        });
+        match invalid {
+            Some(face) => Err(InvalidMesh::InvalidFace { face }),
+            None => Ok(()),
+        }
+    }
+
+    fn vertices(&self, face: usize) -> (Point3<Float>, Point3<Float>, Point3<Float>) {
+        let [i0, i1, i2] = self.faces[face].positions;
+        (self.positions[i0], self.positions[i1], self.positions[i2])
+    }
+}
+
+impl Display for InvalidMesh {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidMesh::Empty => write!(f, "mesh has no faces"),
+            InvalidMesh::InvalidFace { face } => {
+                write!(f, "mesh face {} has an index out of range", face)
+            }
+        }
+    }
+}
+
+impl Error for InvalidMesh {}
+
+impl TriangleMesh {
+    /// Fails if the mesh has no faces or a face indexes past the end of a buffer.
+    pub fn new(
+        mesh: Mesh,
+        material: Arc<Box<dyn Material>>,
+        split_method: SplitMethod,
+        rng: &mut impl Rng,
+    ) -> Result<Self, InvalidMesh> {
+        mesh.validate()?;
+
+        let mesh = Arc::new(mesh);
+        let triangles: Vec<Box<dyn Hittable>> = (0..mesh.faces.len())
+            .map(|face| -> Box<dyn Hittable> {
+                Box::new(MeshTriangle {
+                    mesh: mesh.clone(),
+                    face,
+                    material: material.clone(),
+                })
+            })
+            .collect();
+
+        let area_cdf = (0..mesh.faces.len())
+            .scan(0.0, |total, face| {
+                let (v0, v1, v2) = mesh.vertices(face);
+                *total += triangle_area(v0, v1, v2);
+                Some(*total)
+            })
+            .collect();
+
+        Ok(Self {
+            bvh: BVHNode::with_split_method(triangles, 0.0, 1.0, split_method, rng).into(),
+            mesh,
+            area_cdf,
+            material,
+        })
+    }
+
+    pub fn mesh(&self) -> &Mesh {
+        &self.mesh
+    }
+}
+
+impl MeshTriangle {
+    fn vertices(&self) -> (Point3<Float>, Point3<Float>, Point3<Float>) {
+        self.mesh.vertices(self.face)
+    }
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(
+        &self,
+        ray: &Ray,
+        t_min: Float,
+        t_max: Float,
+        _rng: &mut MyRng,
+    ) -> Option<HitRecord<'_>> {
+        let face = &self.mesh.faces[self.face];
+        let (v0, v1, v2) = self.vertices();
+        let (t, b1, b2) = intersect_triangle(ray, v0, v1, v2, t_min, t_max)?;
+        let b0 = 1.0 - b1 - b2;
+
+        let (u, v) = if let Some([i0, i1, i2]) = face.uvs {
+            let uv = b0 * self.mesh.uvs[i0].to_vec()
+                + b1 * self.mesh.uvs[i1].to_vec()
+                + b2 * self.mesh.uvs[i2].to_vec();
+            (uv.x, uv.y)
+        } else {
+            (b1, b2)
+        };
+
+        let hit_record = HitRecord::new(
+            ray.at(t),
+            (v1 - v0).cross(v2 - v0).normalize(),
+            t,
+            u,
+            v,
+            ray,
+            &**self.material,
+        )
+        .with_tangent(triangle_tangent(
+            v0,
+            v1,
+            v2,
+            face.uvs
+                .map(|[i0, i1, i2]| (self.mesh.uvs[i0], self.mesh.uvs[i1], self.mesh.uvs[i2])),
+        ));
+
+        Some(if let Some([i0, i1, i2]) = face.normals {
+            hit_record.with_shading_normal(
+                b0 * self.mesh.normals[i0]
+                    + b1 * self.mesh.normals[i1]
+                    + b2 * self.mesh.normals[i2],
+            )
+        } else {
+            hit_record
+        })
+    }
+
+    fn bounding_box(&self, _time0: Float, _time1: Float) -> Option<AABB> {
+        let (v0, v1, v2) = self.vertices();
+        Some(triangle_bounding_box(v0, v1, v2))
+    }
+
+    /// Density of sampling this face uniformly by area, using its geometric normal.
+    fn pdf_value(
+        &self,
+        origin: Point3<Float>,
+        v: Vector3<Float>,
+        time: Float,
+        _rng: &mut MyRng,
+    ) -> Float {
+        let (v0, v1, v2) = self.vertices();
+        let ray = Ray {
+            origin,
+            direction: v,
+            time,
+        };
+        match intersect_triangle(&ray, v0, v1, v2, 0.001, Float::INFINITY) {
+            Some((t, _, _)) => {
+                let distance_squared = t * t * v.magnitude2();
+                let cosine = dot(v.normalize(), (v1 - v0).cross(v2 - v0).normalize()).abs();
+                distance_squared / (cosine * triangle_area(v0, v1, v2))
+            }
+            None => 0.0,
+        }
+    }
+
+    fn area(&self) -> Option<Float> {
+        let (v0, v1, v2) = self.vertices();
+        Some(triangle_area(v0, v1, v2))
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord<'_>> {
+        self.bvh.hit(ray, t_min, t_max, rng)
+    }
+
+    fn bounding_box(&self, time0: Float, time1: Float) -> Option<AABB> {
+        self.bvh.bounding_box(time0, time1)
+    }
+
+    /// Sums the density of every face crossed by the ray, weighted by the chance of picking it.
+    /// Only faces in BVH leaves the ray passes through are tested.
+    fn pdf_value(
+        &self,
+        origin: Point3<Float>,
+        v: Vector3<Float>,
+        time: Float,
+        rng: &mut MyRng,
+    ) -> Float {
+        let total_area = self.area_cdf.last().copied().unwrap_or(0.0);
+        let ray = Ray {
+            origin,
+            direction: v,
+            time,
+        };
+
+        let mut pdf = 0.0;
+        self.bvh
+            .for_each_candidate(&ray, 0.001, Float::INFINITY, |triangle| {
+                let area = triangle.area().unwrap_or(0.0);
+                if area > 0.0 {
+                    pdf += area * triangle.pdf_value(origin, v, time, rng);
+                }
+            });
+        pdf / total_area
+    }
+
+    fn random(&self, origin: Point3<Float>, _time: Float, rng: &mut MyRng) -> Vector3<Float> {
+        let total_area = self.area_cdf.last().copied().unwrap_or(0.0);
+        let target = rng.gen_range(0.0..total_area);
+        let face = self
+            .area_cdf
+            .partition_point(|&area| area <= target)
+            .min(self.area_cdf.len() - 1);
+        let (v0, v1, v2) = self.mesh.vertices(face);
+        random_point_on_triangle(v0, v1, v2, rng) - origin
+    }
+
+    fn supports_sampling(&self) -> bool {
+        self.area_cdf.last().is_some_and(|&area| area > 0.0)
+    }
+
+    fn area(&self) -> Option<Float> {
+        Some(self.area_cdf.last().copied().unwrap_or(0.0))
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        Some(&**self.material)
+    }
+}
@@ -0,0 +1,272 @@
+//! Trowbridge-Reitz (GGX) microfacets and Fresnel terms shared by the rough materials.
+//!
+//! Directions are in a shading frame whose z axis is the normal; see [`ShadingFrame`].
+
+use cgmath::{dot, vec3, InnerSpace, Vector3};
+use num_traits::FloatConst;
+
+use rand::Rng;
+
+use crate::{color::Color, hittable::HitRecord, onb::Onb, Float, MyRng};
+
+/// Below this alpha a distribution is treated as a perfect mirror.
+const SMOOTH_ALPHA: Float = 1e-3;
+
+/// GGX normal distribution with separate roughness along the two tangents.
+#[derive(Clone, Copy, Debug)]
+pub struct Ggx {
+    pub alpha_x: Float,
+    pub alpha_y: Float,
+}
+
+/// Orthonormal frame around a shading normal, with the first tangent along which `alpha_x`
+/// applies. The tangent follows the surface's dp/du so anisotropy turns smoothly across it.
+pub struct ShadingFrame(Onb);
+
+impl Ggx {
+    /// Maps a perceptual `roughness` in [0, 1] to alpha = roughness².
+    pub fn isotropic(roughness: Float) -> Self {
+        Self::anisotropic(roughness, 0.0)
+    }
+
+    /// `anisotropy` in [0, 1] stretches the highlight along the first tangent, as in the Disney
+    /// BRDF.
+    pub fn anisotropic(roughness: Float, anisotropy: Float) -> Self {
+        let alpha = roughness * roughness;
+        let aspect = (1.0 - 0.9 * anisotropy).sqrt();
+        Self {
+            alpha_x: (alpha / aspect).max(SMOOTH_ALPHA * 0.1),
+            alpha_y: (alpha * aspect).max(SMOOTH_ALPHA * 0.1),
+        }
+    }
+
+    /// Whether the surface is smooth enough to be sampled as a delta lobe.
+    pub fn is_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < SMOOTH_ALPHA
+    }
+
+    /// Density of microfacet normals `wm`.
+    pub fn d(&self, wm: Vector3<Float>) -> Float {
+        if wm.z <= 0.0 {
+            return 0.0;
+        }
+        let e = (wm.x / self.alpha_x).powi(2) + (wm.y / self.alpha_y).powi(2) + wm.z * wm.z;
+        1.0 / (Float::PI() * self.alpha_x * self.alpha_y * e * e)
+    }
+
+    /// Smith's auxiliary function, the ratio of hidden to visible projected microfacet area.
+    pub fn lambda(&self, w: Vector3<Float>) -> Float {
+        if w.z == 0.0 {
+            return Float::INFINITY;
+        }
+        let alpha2_tan2 =
+            ((self.alpha_x * w.x).powi(2) + (self.alpha_y * w.y).powi(2)) / (w.z * w.z);
+        ((1.0 + alpha2_tan2).sqrt() - 1.0) / 2.0
+    }
+
+    /// Fraction of microfacets visible from `w`.
+    pub fn g1(&self, w: Vector3<Float>) -> Float {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Height-correlated fraction of microfacets visible from both `wo` and `wi`.
+    pub fn g(&self, wo: Vector3<Float>, wi: Vector3<Float>) -> Float {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Samples a microfacet normal visible from `wo` (Heitz 2018), which must be above the
+    /// surface. `u1` and `u2` are uniform in [0, 1).
+    pub fn sample_visible(&self, wo: Vector3<Float>, u1: Float, u2: Float) -> Vector3<Float> {
+        let vh = vec3(self.alpha_x * wo.x, self.alpha_y * wo.y, wo.z).normalize();
+        let length2 = vh.x * vh.x + vh.y * vh.y;
+        let t1 = if length2 > 0.0 {
+            vec3(-vh.y, vh.x, 0.0) / length2.sqrt()
+        } else {
+            vec3(1.0, 0.0, 0.0)
+        };
+        let t2 = vh.cross(t1);
+
+        let r = u1.sqrt();
+        let phi = 2.0 * Float::PI() * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+
+        vec3(self.alpha_x * nh.x, self.alpha_y * nh.y, nh.z.max(1e-6)).normalize()
+    }
+
+    /// Density of [`Ggx::sample_visible`] returning `wm`.
+    pub fn pdf_visible(&self, wo: Vector3<Float>, wm: Vector3<Float>) -> Float {
+        if wo.z <= 0.0 {
+            return 0.0;
+        }
+        self.g1(wo) * dot(wo, wm).max(0.0) * self.d(wm) / wo.z
+    }
+}
+
+impl ShadingFrame {
+    pub fn new(normal: Vector3<Float>, tangent: Vector3<Float>) -> Self {
+        Self(Onb::from_w_and_u(normal, tangent))
+    }
+
+    /// The frame of the shading normal and tangent of a hit.
+    pub fn from_hit(hit_record: &HitRecord) -> Self {
+        Self::new(hit_record.normal, hit_record.tangent)
+    }
+
+    pub fn to_local(&self, v: Vector3<Float>) -> Vector3<Float> {
+        vec3(dot(v, self.0.u), dot(v, self.0.v), dot(v, self.0.w))
+    }
+
+    pub fn to_world(&self, v: Vector3<Float>) -> Vector3<Float> {
+        self.0.local(v)
+    }
+}
+
+/// Mirrors `wo` about `n`; both point away from the surface.
+pub fn reflect(wo: Vector3<Float>, n: Vector3<Float>) -> Vector3<Float> {
+    2.0 * dot(wo, n) * n - wo
+}
+
+/// Unpolarized Fresnel reflectance at a conductor with complex index of refraction
+/// `eta + i k`, one channel at a time.
+pub fn fresnel_conductor(cos_theta: Float, eta: Color, k: Color) -> Color {
+    let channel = |eta: Float, k: Float| {
+        let cos2 = cos_theta.clamp(0.0, 1.0).powi(2);
+        let sin2 = 1.0 - cos2;
+        let t0 = eta * eta - k * k - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+        let t1 = a2_plus_b2 + cos2;
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+        let t2 = 2.0 * cos2.sqrt() * a;
+        let rs = (t1 - t2) / (t1 + t2);
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let rp = rs * (t3 - t4) / (t3 + t4);
+        0.5 * (rs + rp)
+    };
+
+    Color(vec3(
+        channel(eta.x, k.x),
+        channel(eta.y, k.y),
+        channel(eta.z, k.z),
+    ))
+}
+
+/// Unpolarized Fresnel reflectance from the side of `cos_theta` > 0 into a dielectric with
+/// relative index of refraction `eta` (transmitted over incident); 1 under total internal
+/// reflection.
+pub fn fresnel_dielectric(cos_theta: Float, eta: Float) -> Float {
+    let cos_i = cos_theta.clamp(0.0, 1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    (r_parallel * r_parallel + r_perpendicular * r_perpendicular) / 2.0
+}
+
+/// Refracts `wo`, on the side `n` points to, through a boundary with relative index `eta`
+/// (transmitted over incident). `None` under total internal reflection.
+pub fn refract(wo: Vector3<Float>, n: Vector3<Float>, eta: Float) -> Option<Vector3<Float>> {
+    let cos_i = dot(wo, n);
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(-wo / eta + (cos_i / eta - cos_t) * n)
+}
+
+/// Schlick's approximation of Fresnel reflectance with reflectance `f0` at normal incidence.
+pub fn fresnel_schlick(cos_theta: Float, f0: Color) -> Color {
+    let weight = (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5);
+    Color(f0.0 + (vec3(1.0, 1.0, 1.0) - f0.0) * weight)
+}
+
+/// Samples a direction reflected off a microfacet visible from `wo`, or `None` if it points
+/// below the surface.
+pub fn sample_reflection(ggx: &Ggx, wo: Vector3<Float>, rng: &mut MyRng) -> Option<Vector3<Float>> {
+    let wm = ggx.sample_visible(wo, rng.gen(), rng.gen());
+    let wi = reflect(wo, wm);
+    if wi.z > 0.0 {
+        Some(wi)
+    } else {
+        None
+    }
+}
+
+/// Density of [`sample_reflection`] returning `wi`.
+pub fn reflection_pdf(ggx: &Ggx, wo: Vector3<Float>, wi: Vector3<Float>) -> Float {
+    if wo.z <= 0.0 || wi.z <= 0.0 {
+        return 0.0;
+    }
+    let wm = (wo + wi).normalize();
+    ggx.pdf_visible(wo, wm) / (4.0 * dot(wo, wm))
+}
+
+/// Samples reflection or refraction off a microfacet of a dielectric with relative index `eta`,
+/// choosing by Fresnel reflectance.
+pub fn sample_dielectric(
+    ggx: &Ggx,
+    eta: Float,
+    wo: Vector3<Float>,
+    rng: &mut MyRng,
+) -> Option<Vector3<Float>> {
+    let wm = ggx.sample_visible(wo, rng.gen(), rng.gen());
+    let reflected = rng.gen::<Float>() < fresnel_dielectric(dot(wo, wm), eta);
+    let wi = if reflected {
+        reflect(wo, wm)
+    } else {
+        refract(wo, wm, eta)?
+    };
+    // A steep microfacet can send the light to the other side than its lobe intends.
+    if (wi.z > 0.0) == reflected {
+        Some(wi)
+    } else {
+        None
+    }
+}
+
+/// BSDF times cosine of a rough dielectric (Walter et al. 2007) and the density of
+/// [`sample_dielectric`], for `wo` above the surface.
+pub fn dielectric_eval_pdf(
+    ggx: &Ggx,
+    eta: Float,
+    wo: Vector3<Float>,
+    wi: Vector3<Float>,
+) -> (Float, Float) {
+    if wo.z <= 0.0 || wi.z == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let reflected = wi.z > 0.0;
+    let wm = if reflected { wo + wi } else { wo + eta * wi };
+    if wm.magnitude2() == 0.0 {
+        return (0.0, 0.0);
+    }
+    let wm = wm.normalize();
+    let wm = if wm.z < 0.0 { -wm } else { wm };
+    // Microfacets facing away from either direction cannot connect them.
+    if dot(wo, wm) <= 0.0 || dot(wi, wm) * wi.z <= 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let fresnel = fresnel_dielectric(dot(wo, wm), eta);
+    if reflected {
+        let value = ggx.d(wm) * ggx.g(wo, wi) * fresnel / (4.0 * wo.z);
+        let pdf = ggx.pdf_visible(wo, wm) / (4.0 * dot(wo, wm)) * fresnel;
+        (value, pdf)
+    } else {
+        let denominator = (dot(wi, wm) + dot(wo, wm) / eta).powi(2);
+        // Like `Dielectric`, transmission leaves radiance unscaled by `1 / eta^2`; the factors
+        // cancel for a path that enters and leaves a closed object.
+        let value = ggx.d(wm) * ggx.g(wo, wi) * (1.0 - fresnel) * (dot(wi, wm) * dot(wo, wm)).abs()
+            / (wo.z * denominator);
+        let pdf = ggx.pdf_visible(wo, wm) * dot(wi, wm).abs() / denominator * (1.0 - fresnel);
+        (value, pdf)
+    }
+}
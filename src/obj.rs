@@ -0,0 +1,536 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt::{self, Display},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use cgmath::{point2, point3, vec3, Point2, Point3, Vector3};
+use rand::Rng;
+
+use crate::{
+    bvh::{BVHNode, SplitMethod},
+    color::Color,
+    hittable::Hittable,
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    mesh::{Face, InvalidMesh, Mesh, TriangleMesh},
+    texture::SolidColor,
+    Float,
+};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    MissingMaterialLibrary {
+        path: PathBuf,
+        line: usize,
+        library: PathBuf,
+        source: io::Error,
+    },
+    UnknownMaterial {
+        path: PathBuf,
+        line: usize,
+        name: String,
+    },
+    MissingTexture {
+        path: PathBuf,
+        line: usize,
+        texture: PathBuf,
+        source: image::ImageError,
+    },
+    Empty {
+        path: PathBuf,
+    },
+    InvalidMesh {
+        path: PathBuf,
+        source: InvalidMesh,
+    },
+}
+
+impl Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            ObjError::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+            ObjError::MissingMaterialLibrary {
+                path,
+                line,
+                library,
+                source,
+            } => write!(
+                f,
+                "{}:{}: cannot read material library {}: {}",
+                path.display(),
+                line,
+                library.display(),
+                source
+            ),
+            ObjError::UnknownMaterial { path, line, name } => write!(
+                f,
+                "{}:{}: material `{}` is not defined in any material library",
+                path.display(),
+                line,
+                name
+            ),
+            ObjError::MissingTexture {
+                path,
+                line,
+                texture,
+                source,
+            } => write!(
+                f,
+                "{}:{}: cannot load texture {}: {}",
+                path.display(),
+                line,
+                texture.display(),
+                source
+            ),
+            ObjError::Empty { path } => write!(f, "{}: no faces", path.display()),
+            ObjError::InvalidMesh { path, source } => write!(f, "{}: {}", path.display(), source),
+        }
+    }
+}
+
+impl Error for ObjError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ObjError::Io { source, .. } => Some(source),
+            ObjError::MissingMaterialLibrary { source, .. } => Some(source),
+            ObjError::MissingTexture { source, .. } => Some(source),
+            ObjError::InvalidMesh { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// A mesh read from an OBJ file and the material of its faces.
+pub type ObjMesh = (Mesh, Arc<Box<dyn Material>>);
+
+/// Loads a Wavefront OBJ file together with the MTL libraries it references.
+///
+/// Faces are grouped into one [`TriangleMesh`] per material; see [`read_obj`].
+pub fn load_obj(
+    path: impl AsRef<Path>,
+    split_method: SplitMethod,
+    rng: &mut impl Rng,
+) -> Result<BVHNode, ObjError> {
+    let path = path.as_ref();
+    let objects = read_obj(path)?
+        .into_iter()
+        .map(|(mesh, material)| -> Result<Box<dyn Hittable>, ObjError> {
+            let mesh = TriangleMesh::new(mesh, material, split_method, rng).map_err(|source| {
+                ObjError::InvalidMesh {
+                    path: path.to_path_buf(),
+                    source,
+                }
+            })?;
+            Ok(Box::new(mesh))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(BVHNode::with_split_method(
+        objects,
+        0.0,
+        1.0,
+        split_method,
+        rng,
+    ))
+}
+
+/// Reads a Wavefront OBJ file into one mesh per material, with n-gons fan-triangulated. Meshes
+/// come in the order of their material names, and faces that appear before any `usemtl` come
+/// first with a white [`Lambertian`].
+pub fn read_obj(path: impl AsRef<Path>) -> Result<Vec<ObjMesh>, ObjError> {
+    let path = path.as_ref();
+    let source = read_to_string(path)?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut positions: Vec<Point3<Float>> = Vec::new();
+    let mut normals: Vec<Vector3<Float>> = Vec::new();
+    let mut uvs: Vec<Point2<Float>> = Vec::new();
+
+    let mut materials: HashMap<String, Arc<Box<dyn Material>>> = HashMap::new();
+    let mut groups: Vec<(Option<String>, Vec<Face>)> = vec![(None, Vec::new())];
+
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let mut tokens = line.split_whitespace();
+        let parse_error = |message: String| ObjError::Parse {
+            path: path.to_path_buf(),
+            line: line_number,
+            message,
+        };
+
+        match tokens.next() {
+            Some("v") => {
+                let [x, y, z] = parse_floats(&mut tokens).map_err(parse_error)?;
+                positions.push(point3(x, y, z));
+            }
+            Some("vn") => {
+                let [x, y, z] = parse_floats(&mut tokens).map_err(parse_error)?;
+                normals.push(vec3(x, y, z));
+            }
+            Some("vt") => {
+                let u = parse_float(tokens.next()).map_err(parse_error)?;
+                let v = tokens.next().map_or(Ok(0.0), |v| parse_float(Some(v)));
+                uvs.push(point2(u, v.map_err(parse_error)?));
+            }
+            Some("f") => {
+                let vertices = tokens
+                    .map(|token| {
+                        parse_face_vertex(token, positions.len(), uvs.len(), normals.len())
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(parse_error)?;
+
+                if vertices.len() < 3 {
+                    return Err(parse_error(format!(
+                        "face needs at least 3 vertices, found {}",
+                        vertices.len()
+                    )));
+                }
+
+                let faces = &mut groups.last_mut().unwrap().1;
+                for i in 1..vertices.len() - 1 {
+                    let corners = [vertices[0], vertices[i], vertices[i + 1]];
+                    faces.push(Face {
+                        positions: [corners[0].0, corners[1].0, corners[2].0],
+                        uvs: match (corners[0].1, corners[1].1, corners[2].1) {
+                            (Some(a), Some(b), Some(c)) => Some([a, b, c]),
+                            _ => None,
+                        },
+                        normals: match (corners[0].2, corners[1].2, corners[2].2) {
+                            (Some(a), Some(b), Some(c)) => Some([a, b, c]),
+                            _ => None,
+                        },
+                    });
+                }
+            }
+            Some("mtllib") => {
+                let names: Vec<&str> = tokens.collect();
+                if names.is_empty() {
+                    return Err(parse_error("mtllib needs a file name".to_string()));
+                }
+                for name in names {
+                    let library = base_dir.join(name);
+                    let source = fs::read_to_string(&library).map_err(|source| {
+                        ObjError::MissingMaterialLibrary {
+                            path: path.to_path_buf(),
+                            line: line_number,
+                            library: library.clone(),
+                            source,
+                        }
+                    })?;
+                    materials.extend(parse_mtl(&library, &source)?);
+                }
+            }
+            Some("usemtl") => {
+                let name = tokens
+                    .next()
+                    .ok_or_else(|| parse_error("usemtl needs a material name".to_string()))?;
+                if !materials.contains_key(name) {
+                    return Err(ObjError::UnknownMaterial {
+                        path: path.to_path_buf(),
+                        line: line_number,
+                        name: name.to_string(),
+                    });
+                }
+                groups.push((Some(name.to_string()), Vec::new()));
+            }
+            _ => {}
+        }
+    }
+
+    let default_material: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian {
+        albedo: SolidColor {
+            color_value: Color(vec3(0.73, 0.73, 0.73)),
+        },
+    }));
+
+    // Ordered by material so that building the BVHs draws from the rng the same way every run.
+    let mut meshes: BTreeMap<Option<String>, (Mesh, IndexRemap)> = BTreeMap::new();
+    for (name, faces) in groups {
+        if faces.is_empty() {
+            continue;
+        }
+        let (mesh, remap) = meshes.entry(name).or_default();
+        for face in faces {
+            append_face(mesh, remap, face, &positions, &normals, &uvs);
+        }
+    }
+
+    if meshes.is_empty() {
+        return Err(ObjError::Empty {
+            path: path.to_path_buf(),
+        });
+    }
+
+    Ok(meshes
+        .into_iter()
+        .map(|(name, (mesh, _))| {
+            let material = name
+                .map(|name| materials[&name].clone())
+                .unwrap_or_else(|| default_material.clone());
+            (mesh, material)
+        })
+        .collect())
+}
+
+fn read_to_string(path: &Path) -> Result<String, ObjError> {
+    fs::read_to_string(path).map_err(|source| ObjError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Where the OBJ file's vertices, normals and UVs ended up in the buffers of one mesh.
+#[derive(Default)]
+struct IndexRemap {
+    positions: HashMap<usize, usize>,
+    normals: HashMap<usize, usize>,
+    uvs: HashMap<usize, usize>,
+}
+
+/// Copies `face` into `mesh`, remapping indices so every mesh stores each vertex it uses once.
+fn append_face(
+    mesh: &mut Mesh,
+    remap: &mut IndexRemap,
+    face: Face,
+    positions: &[Point3<Float>],
+    normals: &[Vector3<Float>],
+    uvs: &[Point2<Float>],
+) {
+    fn remap_indices<T: Copy>(
+        indices: [usize; 3],
+        source: &[T],
+        target: &mut Vec<T>,
+        remap: &mut HashMap<usize, usize>,
+    ) -> [usize; 3] {
+        indices.map(|i| {
+            *remap.entry(i).or_insert_with(|| {
+                target.push(source[i]);
+                target.len() - 1
+            })
+        })
+    }
+
+    let face = Face {
+        positions: remap_indices(
+            face.positions,
+            positions,
+            &mut mesh.positions,
+            &mut remap.positions,
+        ),
+        normals: face
+            .normals
+            .map(|n| remap_indices(n, normals, &mut mesh.normals, &mut remap.normals)),
+        uvs: face
+            .uvs
+            .map(|uv| remap_indices(uv, uvs, &mut mesh.uvs, &mut remap.uvs)),
+    };
+    mesh.faces.push(face);
+}
+
+fn parse_float(token: Option<&str>) -> Result<Float, String> {
+    let token = token.ok_or_else(|| "missing number".to_string())?;
+    token
+        .parse()
+        .map_err(|_| format!("invalid number `{}`", token))
+}
+
+fn parse_floats<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<[Float; 3], String> {
+    Ok([
+        parse_float(tokens.next())?,
+        parse_float(tokens.next())?,
+        parse_float(tokens.next())?,
+    ])
+}
+
+fn parse_index(token: &str, len: usize, kind: &str) -> Result<usize, String> {
+    let index: isize = token
+        .parse()
+        .map_err(|_| format!("invalid {} index `{}`", kind, token))?;
+
+    let resolved = match index {
+        0 => None,
+        i if i > 0 => Some(i as usize - 1),
+        i => len.checked_sub(i.unsigned_abs()),
+    };
+
+    resolved
+        .filter(|&i| i < len)
+        .ok_or_else(|| format!("{} index {} out of range (have {})", kind, index, len))
+}
+
+type FaceVertex = (usize, Option<usize>, Option<usize>);
+
+fn parse_face_vertex(
+    token: &str,
+    position_count: usize,
+    uv_count: usize,
+    normal_count: usize,
+) -> Result<FaceVertex, String> {
+    let mut parts = token.split('/');
+    let position = parse_index(parts.next().unwrap(), position_count, "vertex")?;
+    let uv = match parts.next() {
+        Some("") | None => None,
+        Some(i) => Some(parse_index(i, uv_count, "texture coordinate")?),
+    };
+    let normal = match parts.next() {
+        Some("") | None => None,
+        Some(i) => Some(parse_index(i, normal_count, "normal")?),
+    };
+
+    if parts.next().is_some() {
+        return Err(format!("invalid face vertex `{}`", token));
+    }
+
+    Ok((position, uv, normal))
+}
+
+#[derive(Default)]
+struct MtlMaterial {
+    diffuse: Option<Vector3<Float>>,
+    specular: Option<Vector3<Float>>,
+    emission: Option<Vector3<Float>>,
+    shininess: Option<Float>,
+    ior: Option<Float>,
+    dissolve: Option<Float>,
+    illum: Option<u32>,
+    diffuse_map: Option<(usize, PathBuf)>,
+}
+
+fn parse_mtl(
+    path: &Path,
+    source: &str,
+) -> Result<HashMap<String, Arc<Box<dyn Material>>>, ObjError> {
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut parsed: Vec<(String, MtlMaterial)> = Vec::new();
+
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let mut tokens = line.split_whitespace();
+        let parse_error = |message: String| ObjError::Parse {
+            path: path.to_path_buf(),
+            line: line_number,
+            message,
+        };
+
+        let keyword = match tokens.next() {
+            Some(keyword) if !keyword.starts_with('#') => keyword,
+            _ => continue,
+        };
+
+        if keyword == "newmtl" {
+            let name = tokens
+                .next()
+                .ok_or_else(|| parse_error("newmtl needs a material name".to_string()))?;
+            parsed.push((name.to_string(), MtlMaterial::default()));
+            continue;
+        }
+
+        let material = match parsed.last_mut() {
+            Some((_, material)) => material,
+            None => {
+                return Err(parse_error(format!(
+                    "`{}` appears before any newmtl",
+                    keyword
+                )))
+            }
+        };
+
+        match keyword {
+            "Kd" | "Ks" | "Ke" => {
+                let [r, g, b] = parse_floats(&mut tokens).map_err(parse_error)?;
+                let color = Some(vec3(r, g, b));
+                match keyword {
+                    "Kd" => material.diffuse = color,
+                    "Ks" => material.specular = color,
+                    _ => material.emission = color,
+                }
+            }
+            "Ns" => material.shininess = Some(parse_float(tokens.next()).map_err(parse_error)?),
+            "Ni" => material.ior = Some(parse_float(tokens.next()).map_err(parse_error)?),
+            "d" => material.dissolve = Some(parse_float(tokens.next()).map_err(parse_error)?),
+            "Tr" => {
+                material.dissolve = Some(1.0 - parse_float(tokens.next()).map_err(parse_error)?)
+            }
+            "illum" => {
+                let token = tokens.next().unwrap_or("");
+                material.illum = Some(
+                    token
+                        .parse()
+                        .map_err(|_| parse_error(format!("invalid illum `{}`", token)))?,
+                );
+            }
+            "map_Kd" => {
+                // Texture options come first, so the file name is the last token.
+                let name = tokens
+                    .last()
+                    .ok_or_else(|| parse_error("map_Kd needs a file name".to_string()))?;
+                material.diffuse_map = Some((line_number, base_dir.join(name)));
+            }
+            _ => {}
+        }
+    }
+
+    parsed
+        .into_iter()
+        .map(|(name, material)| Ok((name, build_material(path, material)?)))
+        .collect()
+}
+
+/// Maps an MTL description onto the closest material this renderer supports.
+fn build_material(path: &Path, mtl: MtlMaterial) -> Result<Arc<Box<dyn Material>>, ObjError> {
+    let is_zero = |c: Option<Vector3<Float>>| c.is_none_or(|c| c == vec3(0.0, 0.0, 0.0));
+
+    let material: Box<dyn Material> = if !is_zero(mtl.emission) {
+        Box::new(DiffuseLight::new(SolidColor {
+            color_value: Color(mtl.emission.unwrap()),
+        }))
+    } else if matches!(mtl.illum, Some(4) | Some(6) | Some(7) | Some(9))
+        || mtl.dissolve.is_some_and(|d| d < 1.0)
+    {
+        Box::new(Dielectric::new(
+            mtl.ior.filter(|&ir| ir > 0.0).unwrap_or(1.5),
+        ))
+    } else if matches!(mtl.illum, Some(3) | Some(5) | Some(8))
+        || (is_zero(mtl.diffuse) && !is_zero(mtl.specular))
+    {
+        let shininess = mtl.shininess.unwrap_or(0.0).max(0.0);
+        Box::new(Metal {
+            albedo: Color(mtl.specular.unwrap_or_else(|| vec3(1.0, 1.0, 1.0))),
+            fuzz: (2.0 / (shininess + 2.0)).sqrt().min(1.0),
+        })
+    } else if let Some((line, texture)) = mtl.diffuse_map {
+        let image = image::open(&texture).map_err(|source| ObjError::MissingTexture {
+            path: path.to_path_buf(),
+            line,
+            texture,
+            source,
+        })?;
+        Box::new(Lambertian { albedo: image })
+    } else {
+        Box::new(Lambertian {
+            albedo: SolidColor {
+                color_value: Color(mtl.diffuse.unwrap_or_else(|| vec3(0.8, 0.8, 0.8))),
+            },
+        })
+    };
+
+    Ok(Arc::new(material))
+}
@@ -23,6 +23,20 @@ impl Onb {
         Self { u, v, w }
     }
 
+    /// Frame around `n` whose `u` axis is `tangent` made orthogonal to `n`. Falls back to
+    /// [`Onb::from_w`] when the tangent is zero or parallel to `n`.
+    pub fn from_w_and_u(n: Vector3<Float>, tangent: Vector3<Float>) -> Self {
+        let w = n.normalize();
+        let u = tangent - w * tangent.dot(w);
+        if u.magnitude2() <= 1e-12 * tangent.magnitude2() {
+            return Self::from_w(w);
+        }
+        let u = u.normalize();
+        let v = w.cross(u);
+
+        Self { u, v, w }
+    }
+
     pub fn local(&self, a: Vector3<Float>) -> Vector3<Float> {
         self.u * a.x + self.v * a.y + self.w * a.z
     }
@@ -0,0 +1,164 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    fs::File,
+    io::{self, BufWriter, Cursor, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use exr::prelude::{Image as ExrImage, SpecificChannels, Vec2, WritableImage};
+use image::{
+    codecs::{hdr::HdrEncoder, png::PngEncoder},
+    ColorType, ImageError, Rgb,
+};
+
+use crate::render::Image;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// 8-bit ASCII PPM.
+    Ppm,
+    /// 8-bit PNG.
+    Png,
+    /// 32-bit float linear OpenEXR.
+    Exr,
+    /// Radiance RGBE, linear.
+    Hdr,
+}
+
+#[derive(Debug)]
+pub enum OutputError {
+    Io(io::Error),
+    Image(ImageError),
+    Exr(exr::error::Error),
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 4] = [
+        OutputFormat::Ppm,
+        OutputFormat::Png,
+        OutputFormat::Exr,
+        OutputFormat::Hdr,
+    ];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Ppm => "ppm",
+            OutputFormat::Png => "png",
+            OutputFormat::Exr => "exr",
+            OutputFormat::Hdr => "hdr",
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OutputFormat::ALL
+            .iter()
+            .copied()
+            .find(|format| format.extension().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!(
+                    "unknown image format `{}` (expected one of: ppm, png, exr, hdr)",
+                    s
+                )
+            })
+    }
+}
+
+impl Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputError::Io(err) => err.fmt(f),
+            OutputError::Image(err) => err.fmt(f),
+            OutputError::Exr(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for OutputError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            OutputError::Io(err) => Some(err),
+            OutputError::Image(err) => Some(err),
+            OutputError::Exr(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for OutputError {
+    fn from(err: io::Error) -> Self {
+        OutputError::Io(err)
+    }
+}
+
+impl From<ImageError> for OutputError {
+    fn from(err: ImageError) -> Self {
+        OutputError::Image(err)
+    }
+}
+
+impl From<exr::error::Error> for OutputError {
+    fn from(err: exr::error::Error) -> Self {
+        OutputError::Exr(err)
+    }
+}
+
+/// Writes `image` to `path`, or to stdout if `path` is `-`.
+pub fn write_image(path: &Path, format: OutputFormat, image: &Image) -> Result<(), OutputError> {
+    let Image {
+        width,
+        height,
+        ref pixels,
+    } = *image;
+
+    let mut out: Box<dyn Write> = if path == Path::new("-") {
+        Box::new(BufWriter::new(io::stdout()))
+    } else {
+        Box::new(BufWriter::new(File::create(path)?))
+    };
+
+    match format {
+        OutputFormat::Ppm => {
+            writeln!(out, "P3\n{} {}\n255", width, height)?;
+            for color in pixels {
+                writeln!(out, "{}", color)?;
+            }
+        }
+        OutputFormat::Png => {
+            let data: Vec<u8> = pixels.iter().flat_map(|c| c.to_rgb8()).collect();
+            PngEncoder::new(&mut out).encode(
+                &data,
+                width as u32,
+                height as u32,
+                ColorType::Rgb8,
+            )?;
+        }
+        OutputFormat::Exr => {
+            let channels = SpecificChannels::rgb(|Vec2(x, y): Vec2<usize>| {
+                let [r, g, b] = pixels[y * width + x].to_rgb32f();
+                (r, g, b)
+            });
+            // The EXR writer needs to seek, which stdout cannot do.
+            let mut buffer = Cursor::new(Vec::new());
+            ExrImage::from_channels((width, height), channels)
+                .write()
+                .to_buffered(&mut buffer)?;
+            out.write_all(buffer.get_ref())?;
+        }
+        OutputFormat::Hdr => {
+            let data: Vec<Rgb<f32>> = pixels.iter().map(|c| Rgb(c.to_rgb32f())).collect();
+            HdrEncoder::new(&mut out).encode(&data, width, height)?;
+        }
+    }
+
+    out.flush()?;
+    Ok(())
+}
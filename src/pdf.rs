@@ -13,8 +13,10 @@ pub struct CosinePdf {
     pub uvw: Onb,
 }
 
+/// Samples directions from `o` towards `hittable` as it is at `time`.
 pub struct HittablePdf<T> {
     pub o: Point3<Float>,
+    pub time: Float,
     pub hittable: T,
 }
 
@@ -46,11 +48,11 @@ impl Pdf for CosinePdf {
 
 impl<T: Hittable> Pdf for HittablePdf<T> {
     fn value(&self, direction: Vector3<Float>, rng: &mut MyRng) -> Float {
-        self.hittable.pdf_value(self.o, direction, rng)
+        self.hittable.pdf_value(self.o, direction, self.time, rng)
     }
 
     fn generate(&self, rng: &mut MyRng) -> Vector3<Float> {
-        self.hittable.random(self.o, rng)
+        self.hittable.random(self.o, self.time, rng)
     }
 }
 
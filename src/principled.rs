@@ -0,0 +1,265 @@
+use std::sync::Arc;
+
+use cgmath::{dot, vec3, InnerSpace, Vector3};
+use num_traits::FloatConst;
+use rand::Rng;
+
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    material::{BsdfSample, Material},
+    math::random_cosine_direction,
+    microfacet::{self, fresnel_schlick, Ggx, ShadingFrame},
+    texture::{SolidColor, Texture},
+    Float, MyRng,
+};
+
+/// Roughness of the clearcoat layer, which is always glossy.
+const CLEARCOAT_ROUGHNESS: Float = 0.1;
+/// Lowest roughness of the specular lobes, keeping them away from a delta lobe.
+const MIN_ROUGHNESS: Float = 0.05;
+
+/// Disney's principled BSDF (Burley 2012, with transmission from 2015): a diffuse base with
+/// sheen, a GGX specular layer that becomes a tinted metal with `metallic`, a clearcoat and
+/// rough transmission. Scalar parameters are read from the luminance of their textures.
+#[derive(Debug)]
+pub struct Principled {
+    pub base_color: Arc<dyn Texture>,
+    /// 0 for dielectrics, 1 for metals reflecting the base color.
+    pub metallic: Arc<dyn Texture>,
+    pub roughness: Arc<dyn Texture>,
+    /// Reflectance of dielectrics at normal incidence, where 0.5 means 4%.
+    pub specular: Arc<dyn Texture>,
+    /// Extra grazing reflection, mostly for cloth.
+    pub sheen: Arc<dyn Texture>,
+    /// Strength of a second, colorless glossy layer.
+    pub clearcoat: Arc<dyn Texture>,
+    /// Share of the dielectric part refracted into the object, which is solid with index `ir`.
+    pub transmission: Arc<dyn Texture>,
+    /// Stretches the specular highlight along the first tangent of the shading frame.
+    pub anisotropy: Arc<dyn Texture>,
+    pub ir: Float,
+}
+
+/// The parameters of a [`Principled`] material at one hit.
+struct Lobes {
+    frame: ShadingFrame,
+    base_color: Color,
+    roughness: Float,
+    sheen: Float,
+    specular_f0: Color,
+    specular: Ggx,
+    clearcoat: Ggx,
+    eta: Float,
+    /// Weights of the diffuse, specular, clearcoat and transmission lobes.
+    weights: [Float; 4],
+    /// Probabilities of sampling each lobe.
+    probabilities: [Float; 4],
+}
+
+const DIFFUSE: usize = 0;
+const SPECULAR: usize = 1;
+const CLEARCOAT: usize = 2;
+const TRANSMISSION: usize = 3;
+
+fn grey(value: Float) -> Arc<dyn Texture> {
+    Arc::new(SolidColor {
+        color_value: Color(vec3(value, value, value)),
+    })
+}
+
+fn schlick_weight(cos_theta: Float) -> Float {
+    (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5)
+}
+
+impl Principled {
+    /// A rough plastic with `base_color`; other parameters start at Disney's defaults.
+    pub fn new(base_color: Arc<dyn Texture>) -> Self {
+        Self {
+            base_color,
+            metallic: grey(0.0),
+            roughness: grey(0.5),
+            specular: grey(0.5),
+            sheen: grey(0.0),
+            clearcoat: grey(0.0),
+            transmission: grey(0.0),
+            anisotropy: grey(0.0),
+            ir: 1.5,
+        }
+    }
+
+    fn lobes(&self, hit_record: &HitRecord, wo: Vector3<Float>) -> Lobes {
+        let (u, v, p) = (hit_record.u, hit_record.v, hit_record.position);
+        let scalar =
+            |texture: &Arc<dyn Texture>| texture.value(u, v, p).luminance().clamp(0.0, 1.0);
+
+        let base_color = self.base_color.value(u, v, p);
+        let metallic = scalar(&self.metallic);
+        let roughness = scalar(&self.roughness).max(MIN_ROUGHNESS);
+        let transmission = scalar(&self.transmission);
+        let clearcoat = scalar(&self.clearcoat);
+
+        let dielectric_f0 = 0.08 * scalar(&self.specular);
+        let specular_f0 = vec3(dielectric_f0, dielectric_f0, dielectric_f0) * (1.0 - metallic)
+            + base_color.0 * metallic;
+
+        let weights = [
+            (1.0 - metallic) * (1.0 - transmission),
+            1.0 - transmission * (1.0 - metallic),
+            0.25 * clearcoat,
+            (1.0 - metallic) * transmission,
+        ];
+
+        // Pick lobes by how much they reflect towards `wo`.
+        let cos_o = dot(hit_record.normal, wo).max(0.0);
+        let importance = [
+            weights[DIFFUSE] * base_color.luminance(),
+            weights[SPECULAR] * fresnel_schlick(cos_o, Color(specular_f0)).luminance(),
+            weights[CLEARCOAT] * fresnel_schlick(cos_o, Color(vec3(0.04, 0.04, 0.04))).luminance(),
+            weights[TRANSMISSION],
+        ];
+        let total: Float = importance.iter().sum();
+        let probabilities = if total > 0.0 {
+            importance.map(|i| i / total)
+        } else {
+            [0.0; 4]
+        };
+
+        Lobes {
+            frame: ShadingFrame::from_hit(hit_record),
+            base_color,
+            roughness,
+            sheen: scalar(&self.sheen),
+            specular_f0: Color(specular_f0),
+            specular: Ggx::anisotropic(roughness, scalar(&self.anisotropy)),
+            clearcoat: Ggx::isotropic(CLEARCOAT_ROUGHNESS),
+            eta: if hit_record.front_face {
+                self.ir
+            } else {
+                1.0 / self.ir
+            },
+            weights,
+            probabilities,
+        }
+    }
+}
+
+impl Lobes {
+    /// `eval` and `pdf` for local directions.
+    fn eval_pdf(&self, wo: Vector3<Float>, wi: Vector3<Float>) -> (Color, Float) {
+        let mut value = vec3(0.0, 0.0, 0.0);
+        let mut pdf = 0.0;
+        if wo.z <= 0.0 {
+            return (Color(value), pdf);
+        }
+
+        if wi.z > 0.0 {
+            let wm = (wo + wi).normalize();
+            let cos_d = dot(wi, wm);
+
+            if self.weights[DIFFUSE] > 0.0 {
+                let fd90 = 0.5 + 2.0 * self.roughness * cos_d * cos_d;
+                let retro = (1.0 + (fd90 - 1.0) * schlick_weight(wi.z))
+                    * (1.0 + (fd90 - 1.0) * schlick_weight(wo.z));
+                let luminance = self.base_color.luminance();
+                let tint = if luminance > 0.0 {
+                    self.base_color.0 / luminance
+                } else {
+                    vec3(1.0, 1.0, 1.0)
+                };
+                let sheen =
+                    (vec3(1.0, 1.0, 1.0) + tint) / 2.0 * (self.sheen * schlick_weight(cos_d));
+                value += (self.base_color.0 * (retro / Float::PI()) + sheen)
+                    * (self.weights[DIFFUSE] * wi.z);
+                pdf += self.probabilities[DIFFUSE] * wi.z / Float::PI();
+            }
+
+            let ggx = &self.specular;
+            let fresnel = fresnel_schlick(dot(wo, wm), self.specular_f0);
+            value +=
+                fresnel.0 * (self.weights[SPECULAR] * ggx.d(wm) * ggx.g(wo, wi) / (4.0 * wo.z));
+            pdf += self.probabilities[SPECULAR] * microfacet::reflection_pdf(ggx, wo, wi);
+
+            if self.weights[CLEARCOAT] > 0.0 {
+                let ggx = &self.clearcoat;
+                let fresnel = fresnel_schlick(dot(wo, wm), Color(vec3(0.04, 0.04, 0.04)));
+                value += fresnel.0
+                    * (self.weights[CLEARCOAT] * ggx.d(wm) * ggx.g(wo, wi) / (4.0 * wo.z));
+                pdf += self.probabilities[CLEARCOAT] * microfacet::reflection_pdf(ggx, wo, wi);
+            }
+        }
+
+        if self.weights[TRANSMISSION] > 0.0 {
+            let (transmitted, transmission_pdf) =
+                microfacet::dielectric_eval_pdf(&self.specular, self.eta, wo, wi);
+            // Refraction is tinted by the base color, reflection off the glass is not.
+            let tint = if wi.z < 0.0 {
+                self.base_color.0
+            } else {
+                vec3(1.0, 1.0, 1.0)
+            };
+            value += tint * (self.weights[TRANSMISSION] * transmitted);
+            pdf += self.probabilities[TRANSMISSION] * transmission_pdf;
+        }
+
+        (Color(value), pdf)
+    }
+}
+
+impl Material for Principled {
+    fn eval(&self, hit_record: &HitRecord, wo: Vector3<Float>, wi: Vector3<Float>) -> Color {
+        let lobes = self.lobes(hit_record, wo);
+        let (wo, wi) = (lobes.frame.to_local(wo), lobes.frame.to_local(wi));
+        lobes.eval_pdf(wo, wi).0
+    }
+
+    fn sample(
+        &self,
+        hit_record: &HitRecord,
+        wo: Vector3<Float>,
+        rng: &mut MyRng,
+    ) -> Option<BsdfSample> {
+        let lobes = self.lobes(hit_record, wo);
+        let wo = lobes.frame.to_local(wo);
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        let mut target: Float = rng.gen();
+        let mut lobe = TRANSMISSION;
+        for (i, probability) in lobes.probabilities.iter().enumerate() {
+            if target < *probability {
+                lobe = i;
+                break;
+            }
+            target -= probability;
+        }
+        if lobes.probabilities[lobe] == 0.0 {
+            return None;
+        }
+
+        let wi = match lobe {
+            DIFFUSE => random_cosine_direction(rng),
+            SPECULAR => microfacet::sample_reflection(&lobes.specular, wo, rng)?,
+            CLEARCOAT => microfacet::sample_reflection(&lobes.clearcoat, wo, rng)?,
+            _ => microfacet::sample_dielectric(&lobes.specular, lobes.eta, wo, rng)?,
+        };
+
+        let (value, pdf) = lobes.eval_pdf(wo, wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            wi: lobes.frame.to_world(wi).normalize(),
+            weight: Color(value.0 / pdf),
+            pdf,
+            is_delta: false,
+        })
+    }
+
+    fn pdf(&self, hit_record: &HitRecord, wo: Vector3<Float>, wi: Vector3<Float>) -> Float {
+        let lobes = self.lobes(hit_record, wo);
+        let (wo, wi) = (lobes.frame.to_local(wo), lobes.frame.to_local(wi));
+        lobes.eval_pdf(wo, wi).1
+    }
+}
@@ -12,4 +12,14 @@ impl Ray {
     pub fn at(&self, t: Float) -> Point3<Float> {
         self.origin + t * self.direction
     }
+
+    /// Continues the light path of `self` from `origin`. The time of the camera sample is kept,
+    /// so every segment of a path sees moving objects at the same instant.
+    pub fn spawn(&self, origin: Point3<Float>, direction: Vector3<Float>) -> Ray {
+        Ray {
+            origin,
+            direction,
+            time: self.time,
+        }
+    }
 }
@@ -0,0 +1,933 @@
+use std::{
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use cgmath::{prelude::*, vec3, vec4, Vector3};
+use rand::prelude::*;
+use rayon::prelude::*;
+
+use crate::{
+    color::{Color, SampledColor},
+    delta_light::DeltaLight,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    scene::{RenderSettings, Scene},
+    spectrum::{SampledSpectrum, SampledWavelengths},
+    Float, MyRng,
+};
+
+/// A rendered image, stored row by row from the top.
+#[derive(Clone, Debug)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<SampledColor>,
+}
+
+/// How [`ray_color_mis`] weighs a sample against the other strategy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MisHeuristic {
+    Balance,
+    /// The power heuristic with an exponent of two.
+    #[default]
+    Power,
+}
+
+/// The estimator [`Renderer`] uses for scenes with lights.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Integrator {
+    /// One direction from an even mixture of light and BSDF sampling, see [`ray_color`].
+    Mixture,
+    /// Light and BSDF samples combined with multiple importance sampling, see
+    /// [`ray_color_mis`].
+    Mis(MisHeuristic),
+    /// Iterative multiple importance sampling with Russian roulette, see [`path_trace`].
+    Path(MisHeuristic),
+    /// [`Integrator::Path`] at sampled wavelengths, see [`path_trace_spectral`].
+    Spectral(MisHeuristic),
+}
+
+#[derive(Clone, Debug)]
+pub struct Renderer {
+    pub image_width: usize,
+    pub image_height: usize,
+    pub samples_per_pixel: usize,
+    pub max_depth: usize,
+    /// Mixed into the seed of every pixel's sampler.
+    pub seed: u64,
+    pub integrator: Integrator,
+    /// Bounces before [`Integrator::Path`] may end a path with Russian roulette.
+    pub roulette_depth: usize,
+}
+
+/// Renders `scene` with its own render settings.
+pub fn render(scene: &Scene) -> Image {
+    Renderer::new(&scene.settings).render(scene)
+}
+
+/// How many nested objects [`Media`] keeps track of.
+const MAX_MEDIA: usize = 8;
+
+/// The objects a path is inside, innermost last. Light is attenuated by the innermost one on
+/// every segment of the path and of its shadow rays. The stack lives inline so copying it for a
+/// bounce does not allocate; past [`MAX_MEDIA`] objects the outermost are forgotten.
+#[derive(Clone, Copy, Default)]
+struct Media<'a> {
+    stack: [Option<&'a dyn Material>; MAX_MEDIA],
+    len: usize,
+}
+
+pub fn ray_color<H: Hittable + ?Sized, L: Hittable + ?Sized>(
+    ray: &Ray,
+    background: Color,
+    world: &H,
+    lights: &L,
+    delta_lights: &[Box<dyn DeltaLight>],
+    depth: usize,
+    rng: &mut MyRng,
+) -> Color {
+    mixture_radiance(
+        ray,
+        background,
+        world,
+        lights,
+        delta_lights,
+        &Media::default(),
+        depth,
+        rng,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn mixture_radiance<'a, H: Hittable + ?Sized, L: Hittable + ?Sized>(
+    ray: &Ray,
+    background: Color,
+    world: &'a H,
+    lights: &L,
+    delta_lights: &[Box<dyn DeltaLight>],
+    media: &Media<'a>,
+    depth: usize,
+    rng: &mut MyRng,
+) -> Color {
+    if depth == 0 {
+        return Color(vec3(0.0, 0.0, 0.0));
+    }
+    if let Some(hit_record) = world.hit(ray, 0.001, Float::INFINITY, rng) {
+        let transmittance = media.transmittance(hit_record.t * ray.direction.magnitude());
+        let emitted = hit_record.material.emitted(
+            ray,
+            &hit_record,
+            hit_record.u,
+            hit_record.v,
+            hit_record.position,
+        );
+        let wo = -ray.direction.normalize();
+
+        let color = if let Some(sample) = hit_record.material.sample(&hit_record, wo, rng) {
+            if sample.is_delta {
+                let scatterd = ray.spawn(hit_record.position, sample.wi);
+                emitted.0
+                    + sample.weight.0.mul_element_wise(
+                        mixture_radiance(
+                            &scatterd,
+                            background,
+                            world,
+                            lights,
+                            delta_lights,
+                            &media.crossed(&hit_record, sample.wi),
+                            depth - 1,
+                            rng,
+                        )
+                        .0,
+                    )
+            } else {
+                // An even mixture of light sampling and the BSDF sample drawn above.
+                let direction = if rng.gen() {
+                    sample.wi
+                } else {
+                    lights.random(hit_record.position, ray.time, rng)
+                };
+                let scatterd = ray.spawn(hit_record.position, direction);
+                let wi = direction.normalize();
+                let pdf = 0.5 * lights.pdf_value(hit_record.position, direction, ray.time, rng)
+                    + 0.5 * hit_record.material.pdf(&hit_record, wo, wi);
+
+                emitted.0
+                    + delta_direct_light(
+                        ray,
+                        &hit_record,
+                        wo,
+                        world,
+                        delta_lights,
+                        media,
+                        depth,
+                        rng,
+                    )
+                    + hit_record
+                        .material
+                        .eval(&hit_record, wo, wi)
+                        .0
+                        .mul_element_wise(
+                            mixture_radiance(
+                                &scatterd,
+                                background,
+                                world,
+                                lights,
+                                delta_lights,
+                                &media.crossed(&hit_record, wi),
+                                depth - 1,
+                                rng,
+                            )
+                            .0 / pdf,
+                        )
+            }
+        } else {
+            emitted.0
+        };
+        Color(transmittance.0.mul_element_wise(color))
+    } else {
+        background
+    }
+}
+
+/// Estimates direct light at every non-delta bounce from both a light sample and a BSDF sample,
+/// weighted with `heuristic`.
+#[allow(clippy::too_many_arguments)]
+pub fn ray_color_mis<H: Hittable + ?Sized, L: Hittable + ?Sized>(
+    ray: &Ray,
+    background: Color,
+    world: &H,
+    lights: &L,
+    delta_lights: &[Box<dyn DeltaLight>],
+    heuristic: MisHeuristic,
+    depth: usize,
+    rng: &mut MyRng,
+) -> Color {
+    mis_radiance(
+        ray,
+        background,
+        world,
+        lights,
+        delta_lights,
+        heuristic,
+        &Media::default(),
+        None,
+        depth,
+        rng,
+    )
+}
+
+/// `bsdf_pdf` is the density the previous bounce sampled `ray` with, or `None` if light sampling
+/// could not have produced it.
+#[allow(clippy::too_many_arguments)]
+fn mis_radiance<'a, H: Hittable + ?Sized, L: Hittable + ?Sized>(
+    ray: &Ray,
+    background: Color,
+    world: &'a H,
+    lights: &L,
+    delta_lights: &[Box<dyn DeltaLight>],
+    heuristic: MisHeuristic,
+    media: &Media<'a>,
+    bsdf_pdf: Option<Float>,
+    depth: usize,
+    rng: &mut MyRng,
+) -> Color {
+    if depth == 0 {
+        return Color(vec3(0.0, 0.0, 0.0));
+    }
+    let hit_record = match world.hit(ray, 0.001, Float::INFINITY, rng) {
+        Some(hit_record) => hit_record,
+        None => return background,
+    };
+    let transmittance = media.transmittance(hit_record.t * ray.direction.magnitude());
+
+    let mut radiance = hit_record
+        .material
+        .emitted(
+            ray,
+            &hit_record,
+            hit_record.u,
+            hit_record.v,
+            hit_record.position,
+        )
+        .0;
+    if let Some(bsdf_pdf) = bsdf_pdf {
+        if radiance != vec3(0.0, 0.0, 0.0) {
+            let light_pdf = lights.pdf_value(ray.origin, ray.direction, ray.time, rng);
+            radiance *= heuristic.weight(bsdf_pdf, light_pdf);
+        }
+    }
+
+    let wo = -ray.direction.normalize();
+    let sample = match hit_record.material.sample(&hit_record, wo, rng) {
+        Some(sample) => sample,
+        None => return Color(transmittance.0.mul_element_wise(radiance)),
+    };
+
+    // The BSDF sample that shares the light sample has no bounce left to find it.
+    if !sample.is_delta && depth > 1 {
+        radiance += direct_light(ray, &hit_record, wo, world, lights, heuristic, media, rng);
+    }
+    if !sample.is_delta {
+        radiance +=
+            delta_direct_light(ray, &hit_record, wo, world, delta_lights, media, depth, rng);
+    }
+
+    let scatterd = ray.spawn(hit_record.position, sample.wi);
+    let next_bsdf_pdf = (!sample.is_delta).then_some(sample.pdf);
+    radiance += sample.weight.0.mul_element_wise(
+        mis_radiance(
+            &scatterd,
+            background,
+            world,
+            lights,
+            delta_lights,
+            heuristic,
+            &media.crossed(&hit_record, sample.wi),
+            next_bsdf_pdf,
+            depth - 1,
+            rng,
+        )
+        .0,
+    );
+
+    Color(transmittance.0.mul_element_wise(radiance))
+}
+
+impl<'a> Media<'a> {
+    /// Light left after travelling `distance` through the innermost object.
+    fn transmittance(&self, distance: Float) -> Color {
+        match self.stack[..self.len].last() {
+            Some(Some(medium)) if distance.is_finite() => medium.transmittance(distance),
+            _ => Color(vec3(1.0, 1.0, 1.0)),
+        }
+    }
+
+    /// Enters the object at `hit_record` if `wi` goes through its front face, or leaves it if
+    /// `wi` goes through its back face. Scattering inside a volume crosses nothing.
+    fn cross(&mut self, hit_record: &HitRecord<'a>, wi: Vector3<Float>) {
+        // The normal faces the incoming ray, so a direction below it goes through the surface.
+        if wi.dot(hit_record.normal) >= 0.0 || !hit_record.material.bounds_medium() {
+            return;
+        }
+        if hit_record.front_face {
+            if self.len == MAX_MEDIA {
+                self.stack.copy_within(1.., 0);
+                self.len -= 1;
+            }
+            self.stack[self.len] = Some(hit_record.material);
+            self.len += 1;
+        } else if let Some(i) = self.stack[..self.len].iter().rposition(|medium| {
+            medium.is_some_and(|medium| std::ptr::addr_eq(medium, hit_record.material))
+        }) {
+            self.stack.copy_within(i + 1..self.len, i);
+            self.len -= 1;
+            self.stack[self.len] = None;
+        }
+    }
+
+    fn crossed(&self, hit_record: &HitRecord<'a>, wi: Vector3<Float>) -> Self {
+        let mut media = *self;
+        media.cross(hit_record, wi);
+        media
+    }
+}
+
+/// Light arriving at `hit_record` directly from a light sample, scattered towards `wo` and
+/// weighted against BSDF sampling with `heuristic`.
+#[allow(clippy::too_many_arguments)]
+fn direct_light<'a, H: Hittable + ?Sized, L: Hittable + ?Sized>(
+    ray: &Ray,
+    hit_record: &HitRecord<'a>,
+    wo: Vector3<Float>,
+    world: &H,
+    lights: &L,
+    heuristic: MisHeuristic,
+    media: &Media<'a>,
+    rng: &mut MyRng,
+) -> Vector3<Float> {
+    match light_sample(ray, hit_record, wo, world, lights, heuristic, media, rng) {
+        Some((f, emitted, scale)) => f.0.mul_element_wise(emitted.0) * scale,
+        None => vec3(0.0, 0.0, 0.0),
+    }
+}
+
+/// The factors of [`direct_light`]: the BSDF towards a light sample, the light emitted from
+/// there, attenuated by the media on the way, and the MIS weight over the light density. `None`
+/// if no light arrives.
+#[allow(clippy::too_many_arguments)]
+fn light_sample<'a, H: Hittable + ?Sized, L: Hittable + ?Sized>(
+    ray: &Ray,
+    hit_record: &HitRecord<'a>,
+    wo: Vector3<Float>,
+    world: &H,
+    lights: &L,
+    heuristic: MisHeuristic,
+    media: &Media<'a>,
+    rng: &mut MyRng,
+) -> Option<(Color, Color, Float)> {
+    let light_ray = ray.spawn(
+        hit_record.position,
+        lights.random(hit_record.position, ray.time, rng),
+    );
+    let light_pdf = lights.pdf_value(light_ray.origin, light_ray.direction, ray.time, rng);
+    if light_pdf <= 0.0 {
+        return None;
+    }
+
+    let wi = light_ray.direction.normalize();
+    let f = hit_record.material.eval(hit_record, wo, wi);
+    if f.0 == vec3(0.0, 0.0, 0.0) {
+        return None;
+    }
+
+    let light_hit = world.hit(&light_ray, 0.001, Float::INFINITY, rng)?;
+    let light_emitted = light_hit.material.emitted(
+        &light_ray,
+        &light_hit,
+        light_hit.u,
+        light_hit.v,
+        light_hit.position,
+    );
+    let transmittance = media
+        .crossed(hit_record, wi)
+        .transmittance(light_hit.t * light_ray.direction.magnitude());
+    let weight = heuristic.weight(light_pdf, hit_record.material.pdf(hit_record, wo, wi));
+    Some((
+        f,
+        Color(light_emitted.0.mul_element_wise(transmittance.0)),
+        weight / light_pdf,
+    ))
+}
+
+/// Light arriving at `hit_record` straight from every delta light, scattered towards `wo`. Like
+/// light from a surface it counts as a bounce, so none arrives at the last `depth`.
+#[allow(clippy::too_many_arguments)]
+fn delta_direct_light<'a, H: Hittable + ?Sized>(
+    ray: &Ray,
+    hit_record: &HitRecord<'a>,
+    wo: Vector3<Float>,
+    world: &H,
+    delta_lights: &[Box<dyn DeltaLight>],
+    media: &Media<'a>,
+    depth: usize,
+    rng: &mut MyRng,
+) -> Vector3<Float> {
+    let mut radiance = vec3(0.0, 0.0, 0.0);
+    if depth > 1 {
+        for light in delta_lights {
+            if let Some((f, irradiance)) =
+                delta_light_sample(ray, hit_record, wo, world, light.as_ref(), media, rng)
+            {
+                radiance += f.0.mul_element_wise(irradiance.0);
+            }
+        }
+    }
+    radiance
+}
+
+/// The factors of light from `light` in [`delta_direct_light`]: the BSDF towards it and its
+/// irradiance, attenuated by the media on the way. `None` if it is blocked or not scattered
+/// towards `wo`.
+fn delta_light_sample<'a, H: Hittable + ?Sized>(
+    ray: &Ray,
+    hit_record: &HitRecord<'a>,
+    wo: Vector3<Float>,
+    world: &H,
+    light: &dyn DeltaLight,
+    media: &Media<'a>,
+    rng: &mut MyRng,
+) -> Option<(Color, Color)> {
+    let sample = light.sample(hit_record.position, rng)?;
+    let f = hit_record.material.eval(hit_record, wo, sample.wi);
+    if f.0 == vec3(0.0, 0.0, 0.0) {
+        return None;
+    }
+
+    let shadow_ray = ray.spawn(hit_record.position, sample.wi);
+    if world
+        .hit(&shadow_ray, 0.001, sample.distance - 0.001, rng)
+        .is_some()
+    {
+        return None;
+    }
+    let transmittance = media
+        .crossed(hit_record, sample.wi)
+        .transmittance(sample.distance);
+    Some((
+        f,
+        Color(sample.irradiance.0.mul_element_wise(transmittance.0)),
+    ))
+}
+
+/// Iterative version of [`ray_color_mis`] that tracks the throughput of the path and, from bounce
+/// `roulette_depth` on, ends it at random with a probability that grows as the throughput drops.
+/// Surviving paths are reweighted, so the estimate stays unbiased. Without `lights` only BSDFs are
+/// sampled, like [`ray_color_without_light_objects`].
+#[allow(clippy::too_many_arguments)]
+pub fn path_trace<H: Hittable + ?Sized, L: Hittable + ?Sized>(
+    ray: &Ray,
+    background: Color,
+    world: &H,
+    lights: Option<&L>,
+    delta_lights: &[Box<dyn DeltaLight>],
+    heuristic: MisHeuristic,
+    max_depth: usize,
+    roulette_depth: usize,
+    rng: &mut MyRng,
+) -> Color {
+    let mut radiance = vec3(0.0, 0.0, 0.0);
+    let mut throughput = vec3(1.0, 1.0, 1.0);
+    let mut ray = ray.clone();
+    let mut media = Media::default();
+    // Density the last bounce sampled `ray` with, if light sampling could have produced it too.
+    let mut bsdf_pdf = None;
+
+    for bounce in 0..max_depth {
+        let hit_record = match world.hit(&ray, 0.001, Float::INFINITY, rng) {
+            Some(hit_record) => hit_record,
+            None => {
+                radiance += throughput.mul_element_wise(background.0);
+                break;
+            }
+        };
+        throughput = throughput.mul_element_wise(
+            media
+                .transmittance(hit_record.t * ray.direction.magnitude())
+                .0,
+        );
+
+        let emitted = hit_record.material.emitted(
+            &ray,
+            &hit_record,
+            hit_record.u,
+            hit_record.v,
+            hit_record.position,
+        );
+        if emitted.0 != vec3(0.0, 0.0, 0.0) {
+            let weight = match (bsdf_pdf, lights) {
+                (Some(bsdf_pdf), Some(lights)) => heuristic.weight(
+                    bsdf_pdf,
+                    lights.pdf_value(ray.origin, ray.direction, ray.time, rng),
+                ),
+                _ => 1.0,
+            };
+            radiance += throughput.mul_element_wise(emitted.0) * weight;
+        }
+
+        let wo = -ray.direction.normalize();
+        let sample = match hit_record.material.sample(&hit_record, wo, rng) {
+            Some(sample) => sample,
+            None => break,
+        };
+
+        if let Some(lights) = lights.filter(|_| !sample.is_delta && bounce + 1 < max_depth) {
+            radiance += throughput.mul_element_wise(direct_light(
+                &ray,
+                &hit_record,
+                wo,
+                world,
+                lights,
+                heuristic,
+                &media,
+                rng,
+            ));
+        }
+        if !sample.is_delta {
+            radiance += throughput.mul_element_wise(delta_direct_light(
+                &ray,
+                &hit_record,
+                wo,
+                world,
+                delta_lights,
+                &media,
+                max_depth - bounce,
+                rng,
+            ));
+        }
+
+        throughput = throughput.mul_element_wise(sample.weight.0);
+        bsdf_pdf = (!sample.is_delta).then_some(sample.pdf);
+        media.cross(&hit_record, sample.wi);
+        ray = ray.spawn(hit_record.position, sample.wi);
+
+        if bounce + 1 >= roulette_depth {
+            let survival = throughput.x.max(throughput.y).max(throughput.z).min(0.95);
+            if survival <= 0.0 || rng.gen::<Float>() >= survival {
+                break;
+            }
+            throughput /= survival;
+        }
+    }
+
+    Color(radiance)
+}
+
+/// [`path_trace`] carrying light at a few sampled wavelengths instead of RGB. Colors of
+/// materials and lights are upsampled to spectra as the path meets them, dispersive materials
+/// scatter at the hero wavelength alone, and the result is converted back to RGB.
+#[allow(clippy::too_many_arguments)]
+pub fn path_trace_spectral<H: Hittable + ?Sized, L: Hittable + ?Sized>(
+    ray: &Ray,
+    background: Color,
+    world: &H,
+    lights: Option<&L>,
+    delta_lights: &[Box<dyn DeltaLight>],
+    heuristic: MisHeuristic,
+    max_depth: usize,
+    roulette_depth: usize,
+    rng: &mut MyRng,
+) -> Color {
+    let mut wavelengths = SampledWavelengths::sample_visible(rng.gen());
+    let mut radiance = SampledSpectrum::zero();
+    let mut throughput = vec4(1.0, 1.0, 1.0, 1.0);
+    let mut ray = ray.clone();
+    let mut media = Media::default();
+    let mut bsdf_pdf = None;
+
+    for bounce in 0..max_depth {
+        let mut hit_record = match world.hit(&ray, 0.001, Float::INFINITY, rng) {
+            Some(hit_record) => hit_record,
+            None => {
+                radiance += throughput.mul_element_wise(wavelengths.upsample(background));
+                break;
+            }
+        };
+        hit_record.wavelength = Some(wavelengths.hero());
+        throughput = throughput.mul_element_wise(
+            wavelengths.upsample(media.transmittance(hit_record.t * ray.direction.magnitude())),
+        );
+
+        let emitted = hit_record.material.emitted(
+            &ray,
+            &hit_record,
+            hit_record.u,
+            hit_record.v,
+            hit_record.position,
+        );
+        if emitted.0 != vec3(0.0, 0.0, 0.0) {
+            let weight = match (bsdf_pdf, lights) {
+                (Some(bsdf_pdf), Some(lights)) => heuristic.weight(
+                    bsdf_pdf,
+                    lights.pdf_value(ray.origin, ray.direction, ray.time, rng),
+                ),
+                _ => 1.0,
+            };
+            radiance += throughput.mul_element_wise(wavelengths.upsample(emitted)) * weight;
+        }
+
+        if hit_record.material.is_dispersive() {
+            wavelengths.terminate_secondary();
+        }
+
+        let wo = -ray.direction.normalize();
+        let sample = match hit_record.material.sample(&hit_record, wo, rng) {
+            Some(sample) => sample,
+            None => break,
+        };
+
+        if let Some(lights) = lights.filter(|_| !sample.is_delta && bounce + 1 < max_depth) {
+            if let Some((f, emitted, scale)) =
+                light_sample(&ray, &hit_record, wo, world, lights, heuristic, &media, rng)
+            {
+                radiance += throughput
+                    .mul_element_wise(wavelengths.upsample(f))
+                    .mul_element_wise(wavelengths.upsample(emitted))
+                    * scale;
+            }
+        }
+        if !sample.is_delta && bounce + 1 < max_depth {
+            for light in delta_lights {
+                if let Some((f, irradiance)) =
+                    delta_light_sample(&ray, &hit_record, wo, world, light.as_ref(), &media, rng)
+                {
+                    radiance += throughput
+                        .mul_element_wise(wavelengths.upsample(f))
+                        .mul_element_wise(wavelengths.upsample(irradiance));
+                }
+            }
+        }
+
+        throughput = throughput.mul_element_wise(wavelengths.upsample(sample.weight));
+        bsdf_pdf = (!sample.is_delta).then_some(sample.pdf);
+        media.cross(&hit_record, sample.wi);
+        ray = ray.spawn(hit_record.position, sample.wi);
+
+        if bounce + 1 >= roulette_depth {
+            let survival = throughput
+                .x
+                .max(throughput.y)
+                .max(throughput.z)
+                .max(throughput.w)
+                .min(0.95);
+            if survival <= 0.0 || rng.gen::<Float>() >= survival {
+                break;
+            }
+            throughput /= survival;
+        }
+    }
+
+    wavelengths.to_rgb(radiance)
+}
+
+/// Samples BSDFs alone, adding light from `delta_lights`, which BSDF samples cannot find.
+pub fn ray_color_without_light_objects<H: Hittable + ?Sized>(
+    ray: &Ray,
+    background: Color,
+    world: &H,
+    delta_lights: &[Box<dyn DeltaLight>],
+    depth: usize,
+    rng: &mut MyRng,
+) -> Color {
+    bsdf_radiance(
+        ray,
+        background,
+        world,
+        delta_lights,
+        &Media::default(),
+        depth,
+        rng,
+    )
+}
+
+fn bsdf_radiance<'a, H: Hittable + ?Sized>(
+    ray: &Ray,
+    background: Color,
+    world: &'a H,
+    delta_lights: &[Box<dyn DeltaLight>],
+    media: &Media<'a>,
+    depth: usize,
+    rng: &mut MyRng,
+) -> Color {
+    if depth == 0 {
+        return Color(vec3(0.0, 0.0, 0.0));
+    }
+    if let Some(hit_record) = world.hit(ray, 0.001, Float::INFINITY, rng) {
+        let transmittance = media.transmittance(hit_record.t * ray.direction.magnitude());
+        let emitted = hit_record.material.emitted(
+            ray,
+            &hit_record,
+            hit_record.u,
+            hit_record.v,
+            hit_record.position,
+        );
+        let wo = -ray.direction.normalize();
+
+        let color = if let Some(sample) = hit_record.material.sample(&hit_record, wo, rng) {
+            let direct = if sample.is_delta {
+                vec3(0.0, 0.0, 0.0)
+            } else {
+                delta_direct_light(ray, &hit_record, wo, world, delta_lights, media, depth, rng)
+            };
+            let scatterd = ray.spawn(hit_record.position, sample.wi);
+            emitted.0
+                + direct
+                + sample.weight.0.mul_element_wise(
+                    bsdf_radiance(
+                        &scatterd,
+                        background,
+                        world,
+                        delta_lights,
+                        &media.crossed(&hit_record, sample.wi),
+                        depth - 1,
+                        rng,
+                    )
+                    .0,
+                )
+        } else {
+            emitted.0
+        };
+        Color(transmittance.0.mul_element_wise(color))
+    } else {
+        background
+    }
+}
+
+impl MisHeuristic {
+    /// Weight of a sample drawn with density `pdf` when the other strategy has `other_pdf`.
+    pub fn weight(self, pdf: Float, other_pdf: Float) -> Float {
+        let (a, b) = match self {
+            MisHeuristic::Balance => (pdf, other_pdf),
+            MisHeuristic::Power => (pdf * pdf, other_pdf * other_pdf),
+        };
+        if a + b > 0.0 {
+            a / (a + b)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Default for Integrator {
+    fn default() -> Self {
+        Integrator::Path(MisHeuristic::default())
+    }
+}
+
+impl FromStr for Integrator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mixture" => Ok(Integrator::Mixture),
+            "mis" | "mis-power" => Ok(Integrator::Mis(MisHeuristic::Power)),
+            "mis-balance" => Ok(Integrator::Mis(MisHeuristic::Balance)),
+            "path" | "path-power" => Ok(Integrator::Path(MisHeuristic::Power)),
+            "path-balance" => Ok(Integrator::Path(MisHeuristic::Balance)),
+            "spectral" | "spectral-power" => Ok(Integrator::Spectral(MisHeuristic::Power)),
+            "spectral-balance" => Ok(Integrator::Spectral(MisHeuristic::Balance)),
+            _ => Err(format!(
+                "unknown integrator `{}` (expected path, path-balance, spectral, spectral-balance, mis, mis-balance or mixture)",
+                s
+            )),
+        }
+    }
+}
+
+impl Image {
+    pub fn pixel(&self, x: usize, y: usize) -> SampledColor {
+        self.pixels[y * self.width + x]
+    }
+}
+
+impl Renderer {
+    pub fn new(settings: &RenderSettings) -> Self {
+        Self {
+            image_width: settings.image_width,
+            image_height: ((settings.image_width as Float / settings.aspect_ratio).round()
+                as usize)
+                .max(2),
+            samples_per_pixel: settings.samples_per_pixel,
+            max_depth: settings.max_depth,
+            seed: 0,
+            integrator: Integrator::default(),
+            roulette_depth: settings.roulette_depth,
+        }
+    }
+
+    pub fn render(&self, scene: &Scene) -> Image {
+        self.render_with_progress(scene, |_| {})
+    }
+
+    /// Like [`Renderer::render`], calling `progress` with the number of remaining scanlines
+    /// each time one finishes.
+    pub fn render_with_progress(&self, scene: &Scene, progress: impl Fn(usize) + Sync) -> Image {
+        let Self {
+            image_width,
+            image_height,
+            samples_per_pixel,
+            max_depth,
+            integrator,
+            roulette_depth,
+            ..
+        } = *self;
+        assert!(image_width >= 2 && image_height >= 2);
+        debug_assert!(
+            scene
+                .lights
+                .as_ref()
+                .is_none_or(|lights| lights.supports_sampling()),
+            "every light must support sampling"
+        );
+
+        let camera = scene
+            .camera
+            .build(image_width as Float / image_height as Float);
+        let sample_seed = self.seed.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        let sacans_remaining = AtomicUsize::new(image_height);
+
+        let rows: Vec<Vec<SampledColor>> = (0..image_height)
+            .into_par_iter()
+            .rev()
+            .map(|j| {
+                let row = (0..image_width)
+                    .into_par_iter()
+                    .map(|i| {
+                        let mut rng = MyRng::seed_from_u64(
+                            sample_seed.wrapping_add((j * image_width + i) as u64),
+                        );
+                        let mut pixel_color = Color(vec3(0.0, 0.0, 0.0));
+
+                        for _ in 0..samples_per_pixel {
+                            let u = (i as Float + rng.gen::<Float>()) / (image_width - 1) as Float;
+                            let v = (j as Float + rng.gen::<Float>()) / (image_height - 1) as Float;
+
+                            let ray = camera.get_ray(u, v, &mut rng);
+                            pixel_color = Color(
+                                pixel_color.0
+                                    + match (scene.lights.as_ref(), integrator) {
+                                        (Some(lights), Integrator::Mixture) => ray_color(
+                                            &ray,
+                                            scene.background,
+                                            &scene.world,
+                                            lights,
+                                            &scene.delta_lights,
+                                            max_depth,
+                                            &mut rng,
+                                        ),
+                                        (Some(lights), Integrator::Mis(heuristic)) => {
+                                            ray_color_mis(
+                                                &ray,
+                                                scene.background,
+                                                &scene.world,
+                                                lights,
+                                                &scene.delta_lights,
+                                                heuristic,
+                                                max_depth,
+                                                &mut rng,
+                                            )
+                                        }
+                                        (lights, Integrator::Path(heuristic)) => path_trace(
+                                            &ray,
+                                            scene.background,
+                                            &scene.world,
+                                            lights,
+                                            &scene.delta_lights,
+                                            heuristic,
+                                            max_depth,
+                                            roulette_depth,
+                                            &mut rng,
+                                        ),
+                                        (lights, Integrator::Spectral(heuristic)) => {
+                                            path_trace_spectral(
+                                                &ray,
+                                                scene.background,
+                                                &scene.world,
+                                                lights,
+                                                &scene.delta_lights,
+                                                heuristic,
+                                                max_depth,
+                                                roulette_depth,
+                                                &mut rng,
+                                            )
+                                        }
+                                        (None, _) => ray_color_without_light_objects(
+                                            &ray,
+                                            scene.background,
+                                            &scene.world,
+                                            &scene.delta_lights,
+                                            max_depth,
+                                            &mut rng,
+                                        ),
+                                    }
+                                    .0,
+                            );
+                        }
+
+                        pixel_color.into_sampled(samples_per_pixel)
+                    })
+                    .collect();
+                progress(sacans_remaining.fetch_sub(1, Ordering::Relaxed) - 1);
+                row
+            })
+            .collect();
+
+        Image {
+            width: image_width,
+            height: image_height,
+            pixels: rows.into_iter().flatten().collect(),
+        }
+    }
+}
@@ -0,0 +1,1415 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt::{self, Display},
+    fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use cgmath::{point3, vec3, Angle, Deg, InnerSpace, Point3, Quaternion, Rad, Rotation3, Vector3};
+use rand::Rng;
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::{
+    aabox::AABox,
+    aarect::{XYRect, XZRect, YZRect},
+    animation::{Animated, AnimatedTransform, Keyframe},
+    bvh::{BVHNode, FlatBVH, SplitMethod},
+    camera::Camera,
+    color::Color,
+    constant_medium::ConstantMedium,
+    delta_light::{DeltaLight, DistantLight, PointLight, SpotLight},
+    hittable::{FlipFace, Hittable},
+    instance::Instance,
+    light::{emitted_power, LightList, LightsBuilder},
+    material::{
+        ComplexIor, Conductor, Dielectric, DiffuseLight, Lambertian, Material, Metal,
+        RoughDielectric,
+    },
+    microfacet::Ggx,
+    moving_sphere::MovingSphere,
+    obj::{load_obj, ObjError},
+    principled::Principled,
+    spectrum::{self, Dispersion},
+    sphere::Sphere,
+    texture::{CheckerTexture, NoiseTexture256, SolidColor, Texture},
+    transform::{Affine, Transform},
+    triangle::Triangle,
+    Float,
+};
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderSettings {
+    #[serde(rename = "width")]
+    pub image_width: usize,
+    pub aspect_ratio: Float,
+    pub samples_per_pixel: usize,
+    pub max_depth: usize,
+    /// Bounces before Russian roulette may end a path.
+    pub roulette_depth: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct CameraSettings {
+    pub look_from: Point3<Float>,
+    pub look_at: Point3<Float>,
+    pub vup: Vector3<Float>,
+    pub vfov: Deg<Float>,
+    pub aperture: Float,
+    pub focus_dist: Float,
+    pub time0: Float,
+    pub time1: Float,
+}
+
+pub struct Scene {
+    pub world: FlatBVH,
+    pub lights: Option<LightList>,
+    /// Lights without a surface, which only light sampling reaches.
+    pub delta_lights: Vec<Box<dyn DeltaLight>>,
+    pub background: Color,
+    pub camera: CameraSettings,
+    pub settings: RenderSettings,
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    Invalid {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    Mesh {
+        path: PathBuf,
+        line: usize,
+        source: Box<ObjError>,
+    },
+    Texture {
+        path: PathBuf,
+        line: usize,
+        texture: PathBuf,
+        source: image::ImageError,
+    },
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            image_width: 400,
+            aspect_ratio: 16.0 / 9.0,
+            samples_per_pixel: 100,
+            max_depth: 50,
+            roulette_depth: 5,
+        }
+    }
+}
+
+impl CameraSettings {
+    pub fn build(&self, aspect_ratio: Float) -> Camera {
+        Camera::new(
+            self.look_from,
+            self.look_at,
+            self.vup,
+            self.vfov,
+            aspect_ratio,
+            self.aperture,
+            self.focus_dist,
+            self.time0,
+            self.time1,
+        )
+    }
+}
+
+impl Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            SceneError::Parse { path, source } => write!(f, "{}: {}", path.display(), source),
+            SceneError::Invalid {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+            SceneError::Mesh { path, line, source } => write!(
+                f,
+                "{}:{}: cannot load mesh: {}",
+                path.display(),
+                line,
+                source
+            ),
+            SceneError::Texture {
+                path,
+                line,
+                texture,
+                source,
+            } => write!(
+                f,
+                "{}:{}: cannot load texture {}: {}",
+                path.display(),
+                line,
+                texture.display(),
+                source
+            ),
+        }
+    }
+}
+
+impl Error for SceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneError::Io { source, .. } => Some(source),
+            SceneError::Parse { source, .. } => Some(source),
+            SceneError::Mesh { source, .. } => Some(source),
+            SceneError::Texture { source, .. } => Some(source),
+            SceneError::Invalid { .. } => None,
+        }
+    }
+}
+
+type Vec3 = [Float; 3];
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDesc {
+    render: Option<Spanned<RenderSettings>>,
+    camera: Spanned<CameraDesc>,
+    #[serde(default)]
+    background: Vec3,
+    #[serde(default)]
+    textures: BTreeMap<String, Spanned<TextureDesc>>,
+    #[serde(default)]
+    materials: BTreeMap<String, Spanned<MaterialDesc>>,
+    /// Shared geometry placed with `instance` objects.
+    #[serde(default)]
+    geometry: BTreeMap<String, Vec<Spanned<ObjectDesc>>>,
+    #[serde(default)]
+    objects: Vec<Spanned<ObjectDesc>>,
+    #[serde(default)]
+    lights: Vec<Spanned<ObjectDesc>>,
+    #[serde(default)]
+    delta_lights: Vec<Spanned<DeltaLightDesc>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDesc {
+    look_from: Vec3,
+    look_at: Vec3,
+    #[serde(default = "default_vup")]
+    vup: Vec3,
+    vfov: Float,
+    #[serde(default)]
+    aperture: Float,
+    #[serde(default = "default_focus_distance")]
+    focus_distance: Float,
+    #[serde(default)]
+    time0: Float,
+    #[serde(default = "default_time1")]
+    time1: Float,
+}
+
+fn default_vup() -> Vec3 {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_focus_distance() -> Float {
+    10.0
+}
+
+fn default_time1() -> Float {
+    1.0
+}
+
+/// A texture given inline as an RGB triple, a grey level or a texture table, or by name.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TextureRef {
+    Color(Vec3),
+    Grey(Float),
+    Named(String),
+    Inline(Box<TextureDesc>),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum TextureDesc {
+    Solid { color: Vec3 },
+    Checker { odd: TextureRef, even: TextureRef },
+    Noise { scale: Float },
+    Image { path: PathBuf },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MaterialRef {
+    Named(String),
+    Inline(Box<MaterialDesc>),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialDesc {
+    Lambertian {
+        albedo: TextureRef,
+    },
+    Metal {
+        albedo: Vec3,
+        #[serde(default)]
+        fuzz: Float,
+    },
+    /// A GGX metal given by a `metal` preset name or by `eta` and `k`.
+    Conductor {
+        metal: Option<String>,
+        eta: Option<Vec3>,
+        k: Option<Vec3>,
+        #[serde(default)]
+        roughness: Float,
+        #[serde(default)]
+        anisotropy: Float,
+    },
+    /// Glass, optionally tinted by an `absorption` coefficient per unit length or by the
+    /// `color` left after light travels `distance` inside.
+    Dielectric {
+        ir: Option<Float>,
+        absorption: Option<Vec3>,
+        color: Option<Vec3>,
+        distance: Option<Float>,
+        /// Wavelength-dependent index for spectral renders.
+        dispersion: Option<DispersionDesc>,
+    },
+    /// Frosted glass, with roughness read from the luminance of a texture.
+    RoughDielectric {
+        ir: Float,
+        roughness: TextureRef,
+    },
+    /// Disney's principled BSDF; parameters left out keep their defaults.
+    Principled {
+        base_color: TextureRef,
+        metallic: Option<TextureRef>,
+        roughness: Option<TextureRef>,
+        specular: Option<TextureRef>,
+        sheen: Option<TextureRef>,
+        clearcoat: Option<TextureRef>,
+        transmission: Option<TextureRef>,
+        anisotropy: Option<TextureRef>,
+        ir: Option<Float>,
+    },
+    DiffuseLight(DiffuseLightDesc),
+}
+
+/// An emitter colored by a texture or a blackbody temperature in kelvin, optionally made
+/// brighter by `scale` or set to emit `power` watts from each object it lights.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DiffuseLightDesc {
+    emit: Option<TextureRef>,
+    temperature: Option<Float>,
+    scale: Option<Float>,
+    power: Option<Float>,
+    #[serde(default)]
+    two_sided: bool,
+}
+
+/// A light without a surface, colored by `color` or a blackbody `temperature` (white if
+/// neither is given). Point and spot lights take an `intensity` in watts per steradian or their
+/// `power` in watts; distant lights take the `irradiance` on a surface facing them.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum DeltaLightDesc {
+    Point {
+        position: Vec3,
+        color: Option<Vec3>,
+        temperature: Option<Float>,
+        intensity: Option<Float>,
+        power: Option<Float>,
+    },
+    Spot {
+        position: Vec3,
+        /// The axis of the cone.
+        direction: Vec3,
+        /// Angle in degrees between the axis and the edge of the cone.
+        #[serde(default = "default_cone_angle")]
+        cone_angle: Float,
+        /// Angle in degrees over which the light fades out towards the edge.
+        #[serde(default = "default_cone_delta")]
+        cone_delta: Float,
+        /// Relative intensities at evenly spaced angles from the axis to the opposite
+        /// direction.
+        #[serde(default)]
+        profile: Vec<Float>,
+        color: Option<Vec3>,
+        temperature: Option<Float>,
+        intensity: Option<Float>,
+        power: Option<Float>,
+    },
+    Distant {
+        /// The direction the light travels in.
+        direction: Vec3,
+        /// In degrees; the sun's is about 0.53.
+        #[serde(default)]
+        angular_diameter: Float,
+        color: Option<Vec3>,
+        temperature: Option<Float>,
+        #[serde(default = "default_irradiance")]
+        irradiance: Float,
+    },
+}
+
+fn default_cone_angle() -> Float {
+    30.0
+}
+
+fn default_cone_delta() -> Float {
+    5.0
+}
+
+fn default_irradiance() -> Float {
+    1.0
+}
+
+/// A dispersion preset by name or a table of coefficients.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DispersionDesc {
+    Preset(String),
+    Cauchy { a: Float, b: Float },
+    Sellmeier { b: [Float; 3], c: [Float; 3] },
+}
+
+#[derive(Deserialize)]
+struct ObjectDesc {
+    #[serde(flatten)]
+    shape: ShapeDesc,
+    material: Option<MaterialRef>,
+    #[serde(default)]
+    flip_face: bool,
+    #[serde(default)]
+    transform: Vec<TransformDesc>,
+    /// Motion applied after `transform`.
+    #[serde(default)]
+    keyframes: Vec<Spanned<KeyframeDesc>>,
+    /// Keeps an emissive object out of light sampling.
+    #[serde(default)]
+    exclude_from_lights: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ShapeDesc {
+    Sphere {
+        center: Vec3,
+        radius: Float,
+    },
+    MovingSphere {
+        center0: Vec3,
+        center1: Vec3,
+        #[serde(default)]
+        time0: Float,
+        #[serde(default = "default_time1")]
+        time1: Float,
+        radius: Float,
+    },
+    XyRect {
+        x0: Float,
+        x1: Float,
+        y0: Float,
+        y1: Float,
+        k: Float,
+    },
+    XzRect {
+        x0: Float,
+        x1: Float,
+        z0: Float,
+        z1: Float,
+        k: Float,
+    },
+    YzRect {
+        y0: Float,
+        y1: Float,
+        z0: Float,
+        z1: Float,
+        k: Float,
+    },
+    #[serde(rename = "box")]
+    AABox {
+        min: Vec3,
+        max: Vec3,
+    },
+    Triangle {
+        v0: Vec3,
+        v1: Vec3,
+        v2: Vec3,
+    },
+    Obj {
+        path: PathBuf,
+    },
+    ConstantMedium {
+        boundary: Box<ObjectDesc>,
+        density: Float,
+        albedo: TextureRef,
+    },
+    /// Places named geometry. The material, if given, overrides the geometry's own.
+    Instance {
+        geometry: String,
+    },
+}
+
+/// Transforms are applied in the order they are listed. Angles are in degrees.
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum TransformDesc {
+    Translate(Vec3),
+    RotateX(Float),
+    RotateY(Float),
+    RotateZ(Float),
+    Rotate(AxisAngleDesc),
+    Scale(ScaleDesc),
+    LookAt {
+        from: Vec3,
+        at: Vec3,
+        #[serde(default = "default_vup")]
+        up: Vec3,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyframeDesc {
+    time: Float,
+    #[serde(default)]
+    translate: Vec3,
+    rotate: Option<AxisAngleDesc>,
+    scale: Option<ScaleDesc>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AxisAngleDesc {
+    axis: Vec3,
+    angle: Float,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScaleDesc {
+    Uniform(Float),
+    PerAxis(Vec3),
+}
+
+/// Loads a TOML scene description.
+///
+/// Relative paths to meshes and images are resolved against the directory of the scene file.
+/// `split_method` is used for the world and mesh BVHs.
+pub fn load_scene(
+    path: impl AsRef<Path>,
+    split_method: SplitMethod,
+    rng: &mut impl Rng,
+) -> Result<Scene, SceneError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|source| SceneError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let desc: SceneDesc = toml::from_str(&source).map_err(|source| SceneError::Parse {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    let mut loader = Loader {
+        path,
+        source: &source,
+        base_dir: path.parent().unwrap_or_else(|| Path::new("")),
+        texture_descs: &desc.textures,
+        textures: BTreeMap::new(),
+        geometry_descs: &desc.geometry,
+        geometry: BTreeMap::new(),
+        material_descs: &desc.materials,
+        materials: BTreeMap::new(),
+        time0: desc.camera.get_ref().time0,
+        time1: desc.camera.get_ref().time1,
+        split_method,
+        rng,
+    };
+    loader.load(&desc)
+}
+
+struct Loader<'a, R> {
+    path: &'a Path,
+    source: &'a str,
+    base_dir: &'a Path,
+    texture_descs: &'a BTreeMap<String, Spanned<TextureDesc>>,
+    textures: BTreeMap<String, Option<Arc<dyn Texture>>>,
+    geometry_descs: &'a BTreeMap<String, Vec<Spanned<ObjectDesc>>>,
+    geometry: BTreeMap<String, Option<Arc<dyn Hittable>>>,
+    material_descs: &'a BTreeMap<String, Spanned<MaterialDesc>>,
+    materials: BTreeMap<String, Arc<Box<dyn Material>>>,
+    time0: Float,
+    time1: Float,
+    split_method: SplitMethod,
+    rng: &'a mut R,
+}
+
+fn to_point(v: Vec3) -> Point3<Float> {
+    point3(v[0], v[1], v[2])
+}
+
+fn to_vector(v: Vec3) -> Vector3<Float> {
+    vec3(v[0], v[1], v[2])
+}
+
+impl<'a, R: Rng> Loader<'a, R> {
+    fn invalid(&self, span: Range<usize>, message: impl Into<String>) -> SceneError {
+        SceneError::Invalid {
+            path: self.path.to_path_buf(),
+            line: self.line(span),
+            message: message.into(),
+        }
+    }
+
+    fn line(&self, span: Range<usize>) -> usize {
+        self.source[..span.start].matches('\n').count() + 1
+    }
+
+    fn load(&mut self, desc: &SceneDesc) -> Result<Scene, SceneError> {
+        let settings = match &desc.render {
+            Some(render) => {
+                let settings = render.get_ref().clone();
+                if settings.image_width < 2
+                    || settings.samples_per_pixel == 0
+                    || settings.aspect_ratio <= 0.0
+                {
+                    return Err(self.invalid(
+                        render.span(),
+                        "width must be at least 2, samples_per_pixel positive and aspect_ratio positive",
+                    ));
+                }
+                settings
+            }
+            None => RenderSettings::default(),
+        };
+
+        let camera = desc.camera.get_ref();
+        if !(0.0..180.0).contains(&camera.vfov) || camera.vfov == 0.0 {
+            return Err(self.invalid(desc.camera.span(), "vfov must be in (0, 180) degrees"));
+        }
+        if camera.time1 < camera.time0 {
+            return Err(self.invalid(desc.camera.span(), "time1 must not be before time0"));
+        }
+        let camera = CameraSettings {
+            look_from: to_point(camera.look_from),
+            look_at: to_point(camera.look_at),
+            vup: to_vector(camera.vup),
+            vfov: Deg(camera.vfov),
+            aperture: camera.aperture,
+            focus_dist: camera.focus_distance,
+            time0: camera.time0,
+            time1: camera.time1,
+        };
+
+        for (name, material) in self.material_descs {
+            let built = self.material(material.get_ref(), material.span())?;
+            self.materials.insert(name.clone(), built);
+        }
+
+        if desc.objects.is_empty() {
+            return Err(self.invalid(0..0, "scene has no objects"));
+        }
+
+        let mut lights = LightsBuilder::default();
+        let objects = self
+            .objects(&desc.objects)?
+            .into_iter()
+            .zip(&desc.objects)
+            .map(|(object, object_desc)| {
+                if object_desc.get_ref().exclude_from_lights {
+                    object
+                } else {
+                    lights.discover(object)
+                }
+            })
+            .collect();
+
+        let null_material: Arc<Box<dyn Material>> = Arc::new(Box::new(()));
+        for light in &desc.lights {
+            let object = self.object(light.get_ref(), light.span(), Some(null_material.clone()))?;
+            if !object.supports_sampling() {
+                return Err(self.invalid(
+                    light.span(),
+                    "this shape cannot be sampled, so it cannot be used as a light",
+                ));
+            }
+            lights.add(object);
+        }
+
+        let delta_lights = desc
+            .delta_lights
+            .iter()
+            .map(|light| self.delta_light(light.get_ref(), light.span()))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Scene {
+            world: BVHNode::with_split_method(
+                objects,
+                self.time0,
+                self.time1,
+                self.split_method,
+                self.rng,
+            )
+            .into(),
+            lights: lights.build(),
+            delta_lights,
+            background: Color(to_vector(desc.background)),
+            camera,
+            settings,
+        })
+    }
+
+    fn objects(
+        &mut self,
+        descs: &[Spanned<ObjectDesc>],
+    ) -> Result<Vec<Box<dyn Hittable>>, SceneError> {
+        let mut objects = Vec::new();
+        for object in descs {
+            let material = match &object.get_ref().material {
+                Some(material) => Some(self.material_ref(material, object.span())?),
+                None => None,
+            };
+            let built = self.object(object.get_ref(), object.span(), material)?;
+            objects.push(self.with_light_power(object.get_ref(), object.span(), built)?);
+        }
+        Ok(objects)
+    }
+
+    /// Rebuilds `object` if its material is a light given in watts, once its area is known.
+    fn with_light_power(
+        &mut self,
+        desc: &ObjectDesc,
+        span: Range<usize>,
+        object: Box<dyn Hittable>,
+    ) -> Result<Box<dyn Hittable>, SceneError> {
+        let material_descs = self.material_descs;
+        let light = match &desc.material {
+            Some(MaterialRef::Named(name)) => material_descs.get(name).map(|desc| desc.get_ref()),
+            Some(MaterialRef::Inline(desc)) => Some(desc.as_ref()),
+            None => None,
+        };
+        let light = match light {
+            Some(MaterialDesc::DiffuseLight(light)) if light.power.is_some() => light,
+            _ => return Ok(object),
+        };
+
+        if object.area().is_none() {
+            return Err(self.invalid(span, "power needs a shape with a known area"));
+        }
+        // The object was built with the light at its unscaled radiance.
+        let unit_power = emitted_power(object.as_ref());
+        let material: Arc<Box<dyn Material>> = Arc::new(Box::new(self.diffuse_light(
+            light,
+            Some(unit_power),
+            span.clone(),
+        )?));
+        self.object(desc, span, Some(material))
+    }
+
+    /// Builds the BVH of the named geometry on first use and shares it afterwards.
+    fn named_geometry(
+        &mut self,
+        name: &str,
+        span: Range<usize>,
+    ) -> Result<Arc<dyn Hittable>, SceneError> {
+        match self.geometry.get(name) {
+            Some(Some(geometry)) => return Ok(geometry.clone()),
+            Some(None) => {
+                return Err(self.invalid(span, format!("geometry `{}` instances itself", name)))
+            }
+            None => {}
+        }
+
+        let descs = self
+            .geometry_descs
+            .get(name)
+            .ok_or_else(|| self.invalid(span.clone(), format!("unknown geometry `{}`", name)))?;
+        if descs.is_empty() {
+            return Err(self.invalid(span, format!("geometry `{}` is empty", name)));
+        }
+
+        self.geometry.insert(name.to_string(), None);
+        let objects = self.objects(descs)?;
+        let geometry: Arc<dyn Hittable> = Arc::new(FlatBVH::from(BVHNode::with_split_method(
+            objects,
+            self.time0,
+            self.time1,
+            self.split_method,
+            self.rng,
+        )));
+        self.geometry
+            .insert(name.to_string(), Some(geometry.clone()));
+        Ok(geometry)
+    }
+
+    fn named_texture(
+        &mut self,
+        name: &str,
+        span: Range<usize>,
+    ) -> Result<Arc<dyn Texture>, SceneError> {
+        match self.textures.get(name) {
+            Some(Some(texture)) => return Ok(texture.clone()),
+            Some(None) => {
+                return Err(self.invalid(span, format!("texture `{}` refers to itself", name)))
+            }
+            None => {}
+        }
+
+        let desc = self
+            .texture_descs
+            .get(name)
+            .ok_or_else(|| self.invalid(span, format!("unknown texture `{}`", name)))?;
+
+        // Mark as in progress so that cycles are reported instead of recursing forever.
+        self.textures.insert(name.to_string(), None);
+        let texture = self.texture(desc.get_ref(), desc.span())?;
+        self.textures
+            .insert(name.to_string(), Some(texture.clone()));
+        Ok(texture)
+    }
+
+    fn texture_ref(
+        &mut self,
+        texture: &TextureRef,
+        span: Range<usize>,
+    ) -> Result<Arc<dyn Texture>, SceneError> {
+        match texture {
+            TextureRef::Color(color) => Ok(Arc::new(SolidColor {
+                color_value: Color(to_vector(*color)),
+            })),
+            TextureRef::Grey(value) => Ok(Arc::new(SolidColor {
+                color_value: Color(vec3(*value, *value, *value)),
+            })),
+            TextureRef::Named(name) => self.named_texture(name, span),
+            TextureRef::Inline(desc) => self.texture(desc, span),
+        }
+    }
+
+    fn texture(
+        &mut self,
+        desc: &TextureDesc,
+        span: Range<usize>,
+    ) -> Result<Arc<dyn Texture>, SceneError> {
+        Ok(match desc {
+            TextureDesc::Solid { color } => Arc::new(SolidColor {
+                color_value: Color(to_vector(*color)),
+            }),
+            TextureDesc::Checker { odd, even } => Arc::new(CheckerTexture {
+                odd: self.texture_ref(odd, span.clone())?,
+                even: self.texture_ref(even, span)?,
+            }),
+            TextureDesc::Noise { scale } => Arc::new(NoiseTexture256::new(*scale, self.rng)),
+            TextureDesc::Image { path } => {
+                let texture = self.base_dir.join(path);
+                let image = image::open(&texture).map_err(|source| SceneError::Texture {
+                    path: self.path.to_path_buf(),
+                    line: self.line(span),
+                    texture,
+                    source,
+                })?;
+                Arc::new(image)
+            }
+        })
+    }
+
+    fn material_ref(
+        &mut self,
+        material: &MaterialRef,
+        span: Range<usize>,
+    ) -> Result<Arc<Box<dyn Material>>, SceneError> {
+        match material {
+            MaterialRef::Named(name) => self
+                .materials
+                .get(name)
+                .cloned()
+                .ok_or_else(|| self.invalid(span, format!("unknown material `{}`", name))),
+            MaterialRef::Inline(desc) => self.material(desc, span),
+        }
+    }
+
+    fn material(
+        &mut self,
+        desc: &MaterialDesc,
+        span: Range<usize>,
+    ) -> Result<Arc<Box<dyn Material>>, SceneError> {
+        let material: Box<dyn Material> = match desc {
+            MaterialDesc::Lambertian { albedo } => Box::new(Lambertian {
+                albedo: self.texture_ref(albedo, span)?,
+            }),
+            MaterialDesc::Metal { albedo, fuzz } => {
+                if !(0.0..=1.0).contains(fuzz) {
+                    return Err(self.invalid(span, "fuzz must be in [0, 1]"));
+                }
+                Box::new(Metal {
+                    albedo: Color(to_vector(*albedo)),
+                    fuzz: *fuzz,
+                })
+            }
+            MaterialDesc::Conductor {
+                metal,
+                eta,
+                k,
+                roughness,
+                anisotropy,
+            } => {
+                let ior = match (metal, eta, k) {
+                    (Some(name), None, None) => ComplexIor::preset(name).ok_or_else(|| {
+                        self.invalid(
+                            span.clone(),
+                            format!(
+                                "unknown metal `{}` (expected gold, copper, aluminium or silver)",
+                                name
+                            ),
+                        )
+                    })?,
+                    (None, Some(eta), Some(k)) => ComplexIor {
+                        eta: Color(to_vector(*eta)),
+                        k: Color(to_vector(*k)),
+                    },
+                    _ => {
+                        return Err(self
+                            .invalid(span, "conductor needs either `metal` or both `eta` and `k`"))
+                    }
+                };
+                if !(0.0..=1.0).contains(roughness) {
+                    return Err(self.invalid(span, "roughness must be in [0, 1]"));
+                }
+                if !(0.0..=1.0).contains(anisotropy) {
+                    return Err(self.invalid(span, "anisotropy must be in [0, 1]"));
+                }
+                Box::new(Conductor {
+                    ior,
+                    distribution: Ggx::anisotropic(*roughness, *anisotropy),
+                })
+            }
+            MaterialDesc::Dielectric {
+                ir,
+                absorption,
+                color,
+                distance,
+                dispersion,
+            } => {
+                let dispersion = match dispersion {
+                    None => None,
+                    Some(DispersionDesc::Preset(name)) => {
+                        Some(Dispersion::preset(name).ok_or_else(|| {
+                            self.invalid(
+                                span.clone(),
+                                format!(
+                                    "unknown dispersion `{}` (expected bk7, fused_silica or diamond)",
+                                    name
+                                ),
+                            )
+                        })?)
+                    }
+                    Some(DispersionDesc::Cauchy { a, b }) => Some(Dispersion::Cauchy { a: *a, b: *b }),
+                    Some(DispersionDesc::Sellmeier { b, c }) => {
+                        Some(Dispersion::Sellmeier { b: *b, c: *c })
+                    }
+                };
+                // Without an explicit index, RGB renders use the one at the sodium D line.
+                let ir = match (ir, dispersion) {
+                    (Some(ir), _) => *ir,
+                    (None, Some(dispersion)) => dispersion.ior(587.6),
+                    (None, None) => {
+                        return Err(self.invalid(span, "dielectric needs `ir` or `dispersion`"))
+                    }
+                };
+                if ir <= 0.0 || !ir.is_finite() {
+                    return Err(self.invalid(span, "ir must be positive"));
+                }
+                let mut dielectric = match (absorption, color, distance) {
+                    (None, None, None) => Dielectric::new(ir),
+                    (Some(absorption), None, None) => {
+                        if absorption.iter().any(|a| *a < 0.0) {
+                            return Err(self.invalid(span, "absorption must not be negative"));
+                        }
+                        Dielectric {
+                            absorption: Color(to_vector(*absorption)),
+                            ..Dielectric::new(ir)
+                        }
+                    }
+                    (None, Some(color), distance) => {
+                        let distance = distance.unwrap_or(1.0);
+                        if distance <= 0.0 {
+                            return Err(self.invalid(span, "distance must be positive"));
+                        }
+                        Dielectric::tinted(ir, Color(to_vector(*color)), distance)
+                    }
+                    _ => {
+                        return Err(self.invalid(
+                            span,
+                            "dielectric takes either `absorption` or `color` with an optional `distance`",
+                        ))
+                    }
+                };
+                dielectric.dispersion = dispersion;
+                Box::new(dielectric)
+            }
+            MaterialDesc::RoughDielectric { ir, roughness } => {
+                if *ir <= 0.0 {
+                    return Err(self.invalid(span, "ir must be positive"));
+                }
+                Box::new(RoughDielectric {
+                    ir: *ir,
+                    roughness: self.texture_ref(roughness, span)?,
+                })
+            }
+            MaterialDesc::Principled {
+                base_color,
+                metallic,
+                roughness,
+                specular,
+                sheen,
+                clearcoat,
+                transmission,
+                anisotropy,
+                ir,
+            } => {
+                let mut principled = Principled::new(self.texture_ref(base_color, span.clone())?);
+                for (desc, texture) in [
+                    (metallic, &mut principled.metallic),
+                    (roughness, &mut principled.roughness),
+                    (specular, &mut principled.specular),
+                    (sheen, &mut principled.sheen),
+                    (clearcoat, &mut principled.clearcoat),
+                    (transmission, &mut principled.transmission),
+                    (anisotropy, &mut principled.anisotropy),
+                ] {
+                    if let Some(desc) = desc {
+                        *texture = self.texture_ref(desc, span.clone())?;
+                    }
+                }
+                if let Some(ir) = ir {
+                    if *ir <= 0.0 {
+                        return Err(self.invalid(span, "ir must be positive"));
+                    }
+                    principled.ir = *ir;
+                }
+                Box::new(principled)
+            }
+            MaterialDesc::DiffuseLight(light) => Box::new(self.diffuse_light(light, None, span)?),
+        };
+
+        Ok(Arc::new(material))
+    }
+
+    /// Builds a light. Its `power` is reached by scaling `unit_power`, the power of the object at
+    /// the unscaled radiance, and ignored until [`Loader::with_light_power`] knows it.
+    fn diffuse_light(
+        &mut self,
+        desc: &DiffuseLightDesc,
+        unit_power: Option<Float>,
+        span: Range<usize>,
+    ) -> Result<DiffuseLight<Arc<dyn Texture>>, SceneError> {
+        let emit = match (&desc.emit, desc.temperature) {
+            (Some(emit), None) => self.texture_ref(emit, span.clone())?,
+            (None, Some(kelvin)) if kelvin > 0.0 => Arc::new(SolidColor {
+                color_value: spectrum::blackbody(kelvin),
+            }),
+            (None, Some(_)) => return Err(self.invalid(span, "temperature must be positive")),
+            _ => return Err(self.invalid(span, "give either emit or temperature")),
+        };
+
+        let mut light = DiffuseLight::new(emit);
+        light.two_sided = desc.two_sided;
+        match (desc.scale, desc.power) {
+            (Some(_), Some(_)) => return Err(self.invalid(span, "give either scale or power")),
+            (Some(scale), None) => {
+                if scale < 0.0 {
+                    return Err(self.invalid(span, "scale must not be negative"));
+                }
+                light.scale = scale;
+            }
+            (None, Some(power)) => {
+                if power < 0.0 {
+                    return Err(self.invalid(span, "power must not be negative"));
+                }
+                if let Some(unit_power) = unit_power {
+                    if unit_power <= 0.0 {
+                        return Err(
+                            self.invalid(span, "power needs an emitter with some luminance")
+                        );
+                    }
+                    light.scale = power / unit_power;
+                }
+            }
+            (None, None) => {}
+        }
+        Ok(light)
+    }
+
+    fn delta_light(
+        &self,
+        desc: &DeltaLightDesc,
+        span: Range<usize>,
+    ) -> Result<Box<dyn DeltaLight>, SceneError> {
+        let unit_vector = |loader: &Self, v: Vec3| {
+            let v = to_vector(v);
+            if v.magnitude2() > 0.0 {
+                Ok(v.normalize())
+            } else {
+                Err(loader.invalid(span.clone(), "direction must not be zero"))
+            }
+        };
+
+        match desc {
+            DeltaLightDesc::Point {
+                position,
+                color,
+                temperature,
+                intensity,
+                power,
+            } => {
+                let mut light = PointLight {
+                    position: to_point(*position),
+                    intensity: self.light_color(*color, *temperature, span.clone())?,
+                };
+                let scale = self.light_intensity(*intensity, *power, light.power(), span)?;
+                light.intensity = Color(light.intensity.0 * scale);
+                Ok(Box::new(light))
+            }
+            DeltaLightDesc::Spot {
+                position,
+                direction,
+                cone_angle,
+                cone_delta,
+                profile,
+                color,
+                temperature,
+                intensity,
+                power,
+            } => {
+                if !(*cone_angle > 0.0 && *cone_angle <= 180.0) {
+                    return Err(self.invalid(span, "cone_angle must be in (0, 180] degrees"));
+                }
+                if !(0.0..=*cone_angle).contains(cone_delta) {
+                    return Err(self.invalid(span, "cone_delta must be in [0, cone_angle]"));
+                }
+                if profile.iter().any(|value| value.is_nan() || *value < 0.0) {
+                    return Err(self.invalid(span, "profile values must not be negative"));
+                }
+                let mut light = SpotLight {
+                    position: to_point(*position),
+                    direction: unit_vector(self, *direction)?,
+                    intensity: self.light_color(*color, *temperature, span.clone())?,
+                    cos_total_width: Deg(*cone_angle).cos(),
+                    cos_falloff_start: Deg(cone_angle - cone_delta).cos(),
+                    profile: profile.clone(),
+                };
+                let scale = self.light_intensity(*intensity, *power, light.power(), span)?;
+                light.intensity = Color(light.intensity.0 * scale);
+                Ok(Box::new(light))
+            }
+            DeltaLightDesc::Distant {
+                direction,
+                angular_diameter,
+                color,
+                temperature,
+                irradiance,
+            } => {
+                if !(0.0..180.0).contains(angular_diameter) {
+                    return Err(self.invalid(span, "angular_diameter must be in [0, 180) degrees"));
+                }
+                if *irradiance < 0.0 {
+                    return Err(self.invalid(span, "irradiance must not be negative"));
+                }
+                Ok(Box::new(DistantLight {
+                    direction: unit_vector(self, *direction)?,
+                    irradiance: Color(
+                        self.light_color(*color, *temperature, span.clone())?.0 * *irradiance,
+                    ),
+                    angular_diameter: Rad::from(Deg(*angular_diameter)).0,
+                }))
+            }
+        }
+    }
+
+    /// The color of a delta light: `color`, a blackbody at `temperature` or white.
+    fn light_color(
+        &self,
+        color: Option<Vec3>,
+        temperature: Option<Float>,
+        span: Range<usize>,
+    ) -> Result<Color, SceneError> {
+        match (color, temperature) {
+            (Some(color), None) => Ok(Color(to_vector(color))),
+            (None, Some(kelvin)) if kelvin > 0.0 => Ok(spectrum::blackbody(kelvin)),
+            (None, Some(_)) => Err(self.invalid(span, "temperature must be positive")),
+            (None, None) => Ok(Color(vec3(1.0, 1.0, 1.0))),
+            (Some(_), Some(_)) => Err(self.invalid(span, "give either color or temperature")),
+        }
+    }
+
+    /// What to multiply the color of a light emitting `unit_power` by to reach `intensity` or
+    /// `power`.
+    fn light_intensity(
+        &self,
+        intensity: Option<Float>,
+        power: Option<Float>,
+        unit_power: Float,
+        span: Range<usize>,
+    ) -> Result<Float, SceneError> {
+        match (intensity, power) {
+            (Some(_), Some(_)) => Err(self.invalid(span, "give either intensity or power")),
+            (Some(intensity), None) if intensity >= 0.0 => Ok(intensity),
+            (Some(_), None) => Err(self.invalid(span, "intensity must not be negative")),
+            (None, Some(power)) if power >= 0.0 && unit_power > 0.0 => Ok(power / unit_power),
+            (None, Some(_)) => Err(self.invalid(
+                span,
+                "power must not be negative and needs a light that emits",
+            )),
+            (None, None) => Ok(1.0),
+        }
+    }
+
+    fn object(
+        &mut self,
+        desc: &ObjectDesc,
+        span: Range<usize>,
+        material: Option<Arc<Box<dyn Material>>>,
+    ) -> Result<Box<dyn Hittable>, SceneError> {
+        let require_material = |loader: &Self| {
+            material
+                .clone()
+                .ok_or_else(|| loader.invalid(span.clone(), "object needs a material"))
+        };
+        let check = |loader: &Self, ok: bool, message: &str| {
+            if ok {
+                Ok(())
+            } else {
+                Err(loader.invalid(span.clone(), message))
+            }
+        };
+
+        let mut hittable: Box<dyn Hittable> = match &desc.shape {
+            ShapeDesc::Sphere { center, radius } => {
+                check(self, *radius != 0.0, "radius must not be zero")?;
+                Box::new(Sphere {
+                    center: to_point(*center),
+                    radius: *radius,
+                    material: require_material(self)?,
+                })
+            }
+            ShapeDesc::MovingSphere {
+                center0,
+                center1,
+                time0,
+                time1,
+                radius,
+            } => {
+                check(self, *radius != 0.0, "radius must not be zero")?;
+                check(self, time0 < time1, "time0 must be before time1")?;
+                Box::new(MovingSphere {
+                    center0: to_point(*center0),
+                    center1: to_point(*center1),
+                    time0: *time0,
+                    time1: *time1,
+                    radius: *radius,
+                    material: require_material(self)?,
+                })
+            }
+            ShapeDesc::XyRect { x0, x1, y0, y1, k } => {
+                check(self, x0 < x1 && y0 < y1, "rect bounds must be increasing")?;
+                Box::new(XYRect {
+                    x0: *x0,
+                    x1: *x1,
+                    y0: *y0,
+                    y1: *y1,
+                    k: *k,
+                    material: require_material(self)?,
+                })
+            }
+            ShapeDesc::XzRect { x0, x1, z0, z1, k } => {
+                check(self, x0 < x1 && z0 < z1, "rect bounds must be increasing")?;
+                Box::new(XZRect {
+                    x0: *x0,
+                    x1: *x1,
+                    z0: *z0,
+                    z1: *z1,
+                    k: *k,
+                    material: require_material(self)?,
+                })
+            }
+            ShapeDesc::YzRect { y0, y1, z0, z1, k } => {
+                check(self, y0 < y1 && z0 < z1, "rect bounds must be increasing")?;
+                Box::new(YZRect {
+                    y0: *y0,
+                    y1: *y1,
+                    z0: *z0,
+                    z1: *z1,
+                    k: *k,
+                    material: require_material(self)?,
+                })
+            }
+            ShapeDesc::AABox { min, max } => {
+                check(
+                    self,
+                    (0..3).all(|i| min[i] < max[i]),
+                    "box min must be below max on every axis",
+                )?;
+                let material = require_material(self)?;
+                Box::new(AABox::new(
+                    to_point(*min),
+                    to_point(*max),
+                    material,
+                    self.rng,
+                ))
+            }
+            ShapeDesc::Triangle { v0, v1, v2 } => Box::new(Triangle {
+                v0: to_point(*v0),
+                v1: to_point(*v1),
+                v2: to_point(*v2),
+                material: require_material(self)?,
+            }),
+            ShapeDesc::Obj { path } => {
+                check(
+                    self,
+                    desc.material.is_none(),
+                    "obj meshes take their materials from the MTL file",
+                )?;
+                let mesh = load_obj(self.base_dir.join(path), self.split_method, self.rng)
+                    .map_err(|source| SceneError::Mesh {
+                        path: self.path.to_path_buf(),
+                        line: self.line(span.clone()),
+                        source: Box::new(source),
+                    })?;
+                Box::new(mesh)
+            }
+            ShapeDesc::ConstantMedium {
+                boundary,
+                density,
+                albedo,
+            } => {
+                check(self, *density > 0.0, "density must be positive")?;
+                let boundary_material: Arc<Box<dyn Material>> = Arc::new(Box::new(()));
+                let boundary = self.object(boundary, span.clone(), Some(boundary_material))?;
+                let albedo = self.texture_ref(albedo, span.clone())?;
+                Box::new(ConstantMedium::new(boundary, *density, Box::new(albedo)))
+            }
+            ShapeDesc::Instance { geometry } => {
+                let geometry = self.named_geometry(geometry, span.clone())?;
+                let to_world = self.transform(&desc.transform, span.clone())?;
+                let instance = Instance::new(geometry, to_world, self.time0, self.time1);
+                match material {
+                    Some(material) => Box::new(instance.with_material(material)),
+                    None => Box::new(instance),
+                }
+            }
+        };
+
+        if desc.flip_face {
+            hittable = Box::new(FlipFace(hittable));
+        }
+
+        // Instances carry their static transform themselves.
+        let is_instance = matches!(desc.shape, ShapeDesc::Instance { .. });
+        if !desc.transform.is_empty() && !is_instance {
+            let transform = self.transform(&desc.transform, span)?;
+            hittable = Box::new(Transform::new(hittable, transform, self.time0, self.time1));
+        }
+
+        if !desc.keyframes.is_empty() {
+            let animation = self.animation(&desc.keyframes)?;
+            hittable = Box::new(Animated::new(hittable, animation, self.time0, self.time1));
+        }
+
+        Ok(hittable)
+    }
+
+    fn animation(&self, descs: &[Spanned<KeyframeDesc>]) -> Result<AnimatedTransform, SceneError> {
+        let mut keyframes: Vec<Keyframe> = Vec::new();
+        for desc in descs {
+            let span = desc.span();
+            let KeyframeDesc {
+                time,
+                translate,
+                rotate,
+                scale,
+            } = desc.get_ref();
+
+            if !time.is_finite() {
+                return Err(self.invalid(span, "keyframe times must be finite"));
+            }
+            if keyframes.iter().any(|k| k.time == *time) {
+                return Err(self.invalid(span, "keyframe times must be distinct"));
+            }
+            let mut keyframe = Keyframe::new(*time).translate(to_vector(*translate));
+            if let Some(rotation) = rotate {
+                let (axis, angle) = self.axis_angle(rotation, span.clone())?;
+                keyframe = keyframe.rotate(Quaternion::from_axis_angle(axis.normalize(), angle));
+            }
+            if let Some(scale) = scale {
+                keyframe = keyframe.scale(self.scale(scale, span)?);
+            }
+            keyframes.push(keyframe);
+        }
+        Ok(AnimatedTransform::new(keyframes))
+    }
+
+    fn axis_angle(
+        &self,
+        desc: &AxisAngleDesc,
+        span: Range<usize>,
+    ) -> Result<(Vector3<Float>, Deg<Float>), SceneError> {
+        if desc.axis == [0.0; 3] {
+            return Err(self.invalid(span, "rotation axis must not be zero"));
+        }
+        Ok((to_vector(desc.axis), Deg(desc.angle)))
+    }
+
+    fn scale(&self, desc: &ScaleDesc, span: Range<usize>) -> Result<Vector3<Float>, SceneError> {
+        let factors = match desc {
+            ScaleDesc::Uniform(factor) => vec3(*factor, *factor, *factor),
+            ScaleDesc::PerAxis(factors) => to_vector(*factors),
+        };
+        if factors.x == 0.0 || factors.y == 0.0 || factors.z == 0.0 {
+            return Err(self.invalid(span, "scale factors must not be zero"));
+        }
+        Ok(factors)
+    }
+
+    fn transform(&self, descs: &[TransformDesc], span: Range<usize>) -> Result<Affine, SceneError> {
+        let mut transform = Affine::identity();
+        for desc in descs {
+            transform = match desc {
+                TransformDesc::Translate(offset) => transform.translate(to_vector(*offset)),
+                TransformDesc::RotateX(angle) => transform.rotate_x(Deg(*angle)),
+                TransformDesc::RotateY(angle) => transform.rotate_y(Deg(*angle)),
+                TransformDesc::RotateZ(angle) => transform.rotate_z(Deg(*angle)),
+                TransformDesc::Rotate(rotation) => {
+                    let (axis, angle) = self.axis_angle(rotation, span.clone())?;
+                    transform.rotate(axis, angle)
+                }
+                TransformDesc::Scale(scale) => transform.scale(self.scale(scale, span.clone())?),
+                TransformDesc::LookAt { from, at, up } => {
+                    let (from, at, up) = (to_point(*from), to_point(*at), to_vector(*up));
+                    if from == at || up.cross(at - from) == vec3(0.0, 0.0, 0.0) {
+                        return Err(self.invalid(
+                            span,
+                            "look_at needs distinct from and at, and up not along the view",
+                        ));
+                    }
+                    transform.then(Affine::look_at(from, at, up))
+                }
+            };
+        }
+        Ok(transform)
+    }
+}
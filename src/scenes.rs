@@ -0,0 +1,793 @@
+use std::sync::Arc;
+
+use cgmath::{point3, prelude::*, vec3, Deg};
+use image::load_from_memory;
+use rand::Rng;
+
+use crate::{
+    aabox::AABox,
+    aarect::{XYRect, XZRect, YZRect},
+    bvh::{BVHNode, FlatBVH, SplitMethod},
+    color::Color,
+    constant_medium::ConstantMedium,
+    hittable::{FlipFace, Hittable, RotateY, Translate},
+    instance::Instance,
+    light::LightsBuilder,
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    moving_sphere::MovingSphere,
+    scene::{CameraSettings, RenderSettings, Scene},
+    sphere::Sphere,
+    texture::{CheckerTexture, NoiseTexture256, SolidColor},
+    transform::Affine,
+    Float,
+};
+
+fn random_scene(rng: &mut impl Rng) -> Vec<Box<dyn Hittable>> {
+    let ground_material: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian {
+        albedo: CheckerTexture {
+            even: SolidColor {
+                color_value: Color(vec3(0.2, 0.3, 0.1)),
+            },
+            odd: SolidColor {
+                color_value: Color(vec3(0.9, 0.9, 0.9)),
+            },
+        },
+    }));
+
+    let mut world: Vec<Box<dyn Hittable>> = vec![Box::new(Sphere {
+        center: point3(0.0, -1000.0, 0.0),
+        radius: 1000.0,
+        material: ground_material,
+    })];
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat: Float = rng.gen();
+            let center = point3(
+                a as Float + 0.9 * rng.gen::<Float>(),
+                0.2,
+                b as Float + 0.9 * rng.gen::<Float>(),
+            );
+
+            if (center - point3(4.0, 0.2, 0.0)).magnitude() > 0.9 {
+                let hittable: Box<dyn Hittable> = match choose_mat {
+                    x if x < 0.8 => {
+                        let albedo =
+                            Color(rng.gen::<Color>().0.mul_element_wise(rng.gen::<Color>().0));
+                        let center2 = center + vec3(0.0, rng.gen_range(0.0..0.5), 0.0);
+                        let material: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian {
+                            albedo: SolidColor {
+                                color_value: albedo,
+                            },
+                        }));
+                        Box::new(MovingSphere {
+                            center0: center,
+                            center1: center2,
+                            time0: 0.0,
+                            time1: 1.0,
+                            radius: 0.2,
+                            material,
+                        })
+                    }
+                    x if x < 0.95 => {
+                        let albedo = Color(vec3(
+                            rng.gen_range(0.5..1.0),
+                            rng.gen_range(0.5..1.0),
+                            rng.gen_range(0.5..1.0),
+                        ));
+                        let fuzz = rng.gen_range(0.5..1.0);
+                        let material: Arc<Box<dyn Material>> =
+                            Arc::new(Box::new(Metal { albedo, fuzz }));
+                        Box::new(Sphere {
+                            center,
+                            radius: 0.2,
+                            material,
+                        })
+                    }
+                    _ => {
+                        let material: Arc<Box<dyn Material>> =
+                            Arc::new(Box::new(Dielectric::new(1.5)));
+                        Box::new(Sphere {
+                            center,
+                            radius: 0.2,
+                            material,
+                        })
+                    }
+                };
+                world.push(hittable);
+            }
+        }
+    }
+
+    world.push(Box::new(Sphere {
+        center: point3(0.0, 1.0, 0.0),
+        radius: 1.0,
+        material: Arc::new(Box::new(Dielectric::new(1.5))),
+    }));
+
+    world.push(Box::new(Sphere {
+        center: point3(-4.0, 1.0, 0.0),
+        radius: 1.0,
+        material: Arc::new(Box::new(Lambertian {
+            albedo: SolidColor {
+                color_value: Color(vec3(0.4, 0.2, 0.1)),
+            },
+        })),
+    }));
+
+    world.push(Box::new(Sphere {
+        center: point3(4.0, 1.0, 0.0),
+        radius: 1.0,
+        material: Arc::new(Box::new(Metal {
+            albedo: Color(vec3(0.7, 0.6, 0.5)),
+            fuzz: 0.0,
+        })),
+    }));
+
+    world
+}
+
+fn two_spheres() -> Vec<Box<dyn Hittable>> {
+    let checker_material: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian {
+        albedo: CheckerTexture {
+            even: SolidColor {
+                color_value: Color(vec3(0.2, 0.3, 0.1)),
+            },
+            odd: SolidColor {
+                color_value: Color(vec3(0.9, 0.9, 0.9)),
+            },
+        },
+    }));
+
+    let world: Vec<Box<dyn Hittable>> = vec![
+        Box::new(Sphere {
+            center: point3(0.0, -10.0, 0.0),
+            radius: 10.0,
+            material: checker_material.clone(),
+        }),
+        Box::new(Sphere {
+            center: point3(0.0, 10.0, 0.0),
+            radius: 10.0,
+            material: checker_material.clone(),
+        }),
+    ];
+
+    world
+}
+
+fn two_perlin_spheres(rng: &mut impl Rng) -> Vec<Box<dyn Hittable>> {
+    let pertext: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian {
+        albedo: NoiseTexture256::new(4.0, rng),
+    }));
+
+    let world: Vec<Box<dyn Hittable>> = vec![
+        Box::new(Sphere {
+            center: point3(0.0, -1000.0, 0.0),
+            radius: 1000.0,
+            material: pertext.clone(),
+        }),
+        Box::new(Sphere {
+            center: point3(0.0, 2.0, 0.0),
+            radius: 2.0,
+            material: pertext.clone(),
+        }),
+    ];
+
+    world
+}
+
+fn earth() -> Vec<Box<dyn Hittable>> {
+    const EARTH_JPG: &[u8] = include_bytes!("../assets/earthmap.jpg");
+    let image = load_from_memory(EARTH_JPG).unwrap();
+    let earth_surface: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian { albedo: image }));
+
+    let globe = Box::new(Sphere {
+        center: point3(0.0, 0.0, 0.0),
+        radius: 2.0,
+        material: earth_surface,
+    });
+
+    vec![globe]
+}
+
+fn simple_light(rng: &mut impl Rng) -> Vec<Box<dyn Hittable>> {
+    let pertext: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian {
+        albedo: NoiseTexture256::new(4.0, rng),
+    }));
+
+    let difflight: Arc<Box<dyn Material>> = Arc::new(Box::new(DiffuseLight::new(SolidColor {
+        color_value: Color(vec3(4.0, 4.0, 4.0)),
+    })));
+
+    let world: Vec<Box<dyn Hittable>> = vec![
+        Box::new(Sphere {
+            center: point3(0.0, -1000.0, 0.0),
+            radius: 1000.0,
+            material: pertext.clone(),
+        }),
+        Box::new(Sphere {
+            center: point3(0.0, 2.0, 0.0),
+            radius: 2.0,
+            material: pertext.clone(),
+        }),
+        Box::new(XYRect {
+            x0: 3.0,
+            x1: 5.0,
+            y0: 1.0,
+            y1: 3.0,
+            k: -2.0,
+            material: difflight,
+        }),
+    ];
+
+    world
+}
+
+fn cornel_box(rng: &mut impl Rng) -> Vec<Box<dyn Hittable>> {
+    let red: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian {
+        albedo: SolidColor {
+            color_value: Color(vec3(0.65, 0.05, 0.05)),
+        },
+    }));
+
+    let white: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian {
+        albedo: SolidColor {
+            color_value: Color(vec3(0.73, 0.73, 0.73)),
+        },
+    }));
+
+    let green: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian {
+        albedo: SolidColor {
+            color_value: Color(vec3(0.12, 0.45, 0.15)),
+        },
+    }));
+
+    let light: Arc<Box<dyn Material>> = Arc::new(Box::new(DiffuseLight::new(SolidColor {
+        color_value: Color(vec3(15.0, 15.0, 15.0)),
+    })));
+
+    let aluminum: Arc<Box<dyn Material>> = Arc::new(Box::new(Metal {
+        fuzz: 0.0,
+        albedo: Color(vec3(0.8, 0.85, 0.88)),
+    }));
+
+    let box1 = AABox::new(
+        point3(0.0, 0.0, 0.0),
+        point3(165.0, 330.0, 165.0),
+        aluminum,
+        rng,
+    );
+    let box1 = RotateY::new(box1, 0.0, 1.0, Deg(15.0));
+    let box1 = Box::new(Translate {
+        hittable: box1,
+        offset: vec3(265.0, 0.0, 295.0),
+    });
+
+    /*
+    let box2 = AABox::new(
+        point3(0.0, 0.0, 0.0),
+        point3(165.0, 165.0, 165.0),
+        white.clone(),
+        rng,
+    );
+    let box2 = RotateY::new(box2, 0.0, 1.0, Deg(-18.0));
+    let box2 = Box::new(Translate {
+        hittable: box2,
+        offset: vec3(130.0, 0.0, 65.0),
+    });
+    */
+
+    let grass: Arc<Box<dyn Material>> = Arc::new(Box::new(Dielectric::new(1.5)));
+
+    let world: Vec<Box<dyn Hittable>> = vec![
+        Box::new(YZRect {
+            y0: 0.0,
+            y1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 555.0,
+            material: green,
+        }),
+        Box::new(YZRect {
+            y0: 0.0,
+            y1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 0.0,
+            material: red,
+        }),
+        Box::new(FlipFace(XZRect {
+            x0: 213.0,
+            x1: 343.0,
+            z0: 227.0,
+            z1: 332.0,
+            k: 554.0,
+            material: light,
+        })),
+        Box::new(XZRect {
+            x0: 0.0,
+            x1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 0.0,
+            material: white.clone(),
+        }),
+        Box::new(XZRect {
+            x0: 0.0,
+            x1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 555.0,
+            material: white.clone(),
+        }),
+        Box::new(XYRect {
+            x0: 0.0,
+            x1: 555.0,
+            y0: 0.0,
+            y1: 555.0,
+            k: 555.0,
+            material: white.clone(),
+        }),
+        box1,
+        // box2,
+        Box::new(Sphere {
+            center: point3(190.0, 90.0, 190.0),
+            radius: 90.0,
+            material: grass,
+        }),
+    ];
+
+    world
+}
+
+fn cornel_smoke(rng: &mut impl Rng) -> Vec<Box<dyn Hittable>> {
+    let red: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian {
+        albedo: SolidColor {
+            color_value: Color(vec3(0.65, 0.05, 0.05)),
+        },
+    }));
+
+    let white: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian {
+        albedo: SolidColor {
+            color_value: Color(vec3(0.73, 0.73, 0.73)),
+        },
+    }));
+
+    let green: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian {
+        albedo: SolidColor {
+            color_value: Color(vec3(0.12, 0.45, 0.15)),
+        },
+    }));
+
+    let light: Arc<Box<dyn Material>> = Arc::new(Box::new(DiffuseLight::new(SolidColor {
+        color_value: Color(vec3(7.0, 7.0, 7.0)),
+    })));
+
+    let box1 = AABox::new(
+        point3(0.0, 0.0, 0.0),
+        point3(165.0, 330.0, 165.0),
+        white.clone(),
+        rng,
+    );
+    let box1 = RotateY::new(box1, 0.0, 1.0, Deg(15.0));
+    let box1 = Translate {
+        hittable: box1,
+        offset: vec3(265.0, 0.0, 295.0),
+    };
+
+    let box2 = AABox::new(
+        point3(0.0, 0.0, 0.0),
+        point3(165.0, 165.0, 165.0),
+        white.clone(),
+        rng,
+    );
+    let box2 = RotateY::new(box2, 0.0, 1.0, Deg(-18.0));
+    let box2 = Translate {
+        hittable: box2,
+        offset: vec3(130.0, 0.0, 65.0),
+    };
+
+    let smoke1 = Box::new(ConstantMedium::new(
+        box1,
+        0.01,
+        Box::new(SolidColor {
+            color_value: Color(vec3(0.0, 0.0, 0.0)),
+        }),
+    ));
+
+    let smoke2 = Box::new(ConstantMedium::new(
+        box2,
+        0.01,
+        Box::new(SolidColor {
+            color_value: Color(vec3(1.0, 1.0, 1.0)),
+        }),
+    ));
+
+    let world: Vec<Box<dyn Hittable>> = vec![
+        Box::new(YZRect {
+            y0: 0.0,
+            y1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 555.0,
+            material: green,
+        }),
+        Box::new(YZRect {
+            y0: 0.0,
+            y1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 0.0,
+            material: red,
+        }),
+        Box::new(FlipFace(XZRect {
+            x0: 113.0,
+            x1: 443.0,
+            z0: 127.0,
+            z1: 432.0,
+            k: 554.0,
+            material: light,
+        })),
+        Box::new(XZRect {
+            x0: 0.0,
+            x1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 0.0,
+            material: white.clone(),
+        }),
+        Box::new(XZRect {
+            x0: 0.0,
+            x1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 555.0,
+            material: white.clone(),
+        }),
+        Box::new(XYRect {
+            x0: 0.0,
+            x1: 555.0,
+            y0: 0.0,
+            y1: 555.0,
+            k: 555.0,
+            material: white.clone(),
+        }),
+        smoke1,
+        smoke2,
+    ];
+
+    world
+}
+
+fn final_scene(split_method: SplitMethod, rng: &mut impl Rng) -> Vec<Box<dyn Hittable>> {
+    let ground: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian {
+        albedo: SolidColor {
+            color_value: Color(vec3(0.48, 0.83, 0.53)),
+        },
+    }));
+
+    const BOXES_PER_SIDE: usize = 20;
+
+    let mut boxes1: Vec<Box<dyn Hittable>> = Vec::new();
+
+    for i in 0..BOXES_PER_SIDE {
+        for j in 0..BOXES_PER_SIDE {
+            let w = 100.0;
+            let x0 = -1000.0 + i as Float * w;
+            let z0 = -1000.0 + j as Float * w;
+            let y0 = 0.0;
+            let x1 = x0 + w;
+            let y1 = rng.gen_range(1.0..101.0);
+            let z1 = z0 + w;
+
+            boxes1.push(Box::new(AABox::new(
+                point3(x0, y0, z0),
+                point3(x1, y1, z1),
+                ground.clone(),
+                rng,
+            )));
+        }
+    }
+
+    let mut objects: Vec<Box<dyn Hittable>> = vec![Box::new(BVHNode::with_split_method(
+        boxes1,
+        0.0,
+        1.0,
+        split_method,
+        rng,
+    ))];
+
+    let light: Arc<Box<dyn Material>> = Arc::new(Box::new(DiffuseLight::new(SolidColor {
+        color_value: Color(vec3(7.0, 7.0, 7.0)),
+    })));
+
+    objects.push(Box::new(FlipFace(XZRect {
+        x0: 123.0,
+        x1: 423.0,
+        z0: 147.0,
+        z1: 412.0,
+        k: 554.0,
+        material: light,
+    })));
+
+    let center1 = point3(400.0, 400.0, 200.0);
+    let center2 = center1 + vec3(30.0, 0.0, 0.0);
+
+    let moving_sphere_material: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian {
+        albedo: SolidColor {
+            color_value: Color(vec3(0.7, 0.3, 0.1)),
+        },
+    }));
+
+    objects.push(Box::new(MovingSphere {
+        center0: center1,
+        center1: center2,
+        time0: 0.0,
+        time1: 1.0,
+        radius: 50.0,
+        material: moving_sphere_material,
+    }));
+
+    objects.push(Box::new(Sphere {
+        center: point3(260.0, 150.0, 45.0),
+        radius: 50.0,
+        material: Arc::new(Box::new(Dielectric::new(1.5))),
+    }));
+
+    objects.push(Box::new(Sphere {
+        center: point3(0.0, 150.0, 145.0),
+        radius: 50.0,
+        material: Arc::new(Box::new(Metal {
+            albedo: Color(vec3(0.8, 0.8, 0.9)),
+            fuzz: 1.0,
+        })),
+    }));
+
+    let boundary = Sphere {
+        center: point3(360.0, 150.0, 145.0),
+        radius: 70.0,
+        material: Arc::new(Box::new(Dielectric::new(1.5))),
+    };
+
+    objects.push(Box::new(Sphere {
+        center: point3(360.0, 150.0, 145.0),
+        radius: 70.0,
+        material: Arc::new(Box::new(Dielectric::new(1.5))),
+    }));
+    objects.push(Box::new(ConstantMedium::new(
+        boundary,
+        0.2,
+        Box::new(SolidColor {
+            color_value: Color(vec3(0.2, 0.4, 0.9)),
+        }),
+    )));
+
+    let boundary = Sphere {
+        center: point3(0.0, 0.0, 0.0),
+        radius: 5000.0,
+        material: Arc::new(Box::new(Dielectric::new(1.5))),
+    };
+    objects.push(Box::new(ConstantMedium::new(
+        boundary,
+        0.0001,
+        Box::new(SolidColor {
+            color_value: Color(vec3(1.0, 1.0, 1.0)),
+        }),
+    )));
+
+    let emat: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian {
+        albedo: load_from_memory(include_bytes!("../assets/earthmap.jpg")).unwrap(),
+    }));
+
+    objects.push(Box::new(Sphere {
+        center: point3(400.0, 200.0, 400.0),
+        radius: 100.0,
+        material: emat,
+    }));
+
+    let pertext: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian {
+        albedo: NoiseTexture256::new(0.1, rng),
+    }));
+    objects.push(Box::new(Sphere {
+        center: point3(220.0, 280.0, 300.0),
+        radius: 80.0,
+        material: pertext,
+    }));
+
+    let white: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian {
+        albedo: SolidColor {
+            color_value: Color(vec3(0.73, 0.73, 0.73)),
+        },
+    }));
+    let sphere: Arc<dyn Hittable> = Arc::new(Sphere {
+        center: point3(0.0, 0.0, 0.0),
+        radius: 10.0,
+        material: white,
+    });
+
+    let ns = 1000;
+    let boxes2: Vec<Box<dyn Hittable>> = (0..ns)
+        .map(|_| -> Box<dyn Hittable> {
+            let offset = vec3(
+                rng.gen_range(0.0..165.0),
+                rng.gen_range(0.0..165.0),
+                rng.gen_range(0.0..165.0),
+            );
+            Box::new(Instance::new(
+                sphere.clone(),
+                Affine::identity()
+                    .translate(offset)
+                    .rotate_y(Deg(15.0))
+                    .translate(vec3(-100.0, 270.0, 395.0)),
+                0.0,
+                1.0,
+            ))
+        })
+        .collect();
+
+    let boxes2 = BVHNode::with_split_method(boxes2, 0.0, 1.0, split_method, rng);
+
+    objects.push(Box::new(FlatBVH::from(boxes2)));
+    objects
+}
+
+/// Renders built into the binary: `(name, description)`.
+pub const BUILTIN_SCENES: [(&str, &str); 8] = [
+    (
+        "random",
+        "Random spheres from the cover of Ray Tracing in One Weekend",
+    ),
+    ("two-spheres", "Two checkered spheres"),
+    ("two-perlin-spheres", "Two spheres with Perlin noise"),
+    ("earth", "Image-textured globe"),
+    ("simple-light", "Perlin spheres lit by a rectangle light"),
+    (
+        "cornell-box",
+        "Cornell box with an aluminum box and a glass sphere",
+    ),
+    ("cornell-smoke", "Cornell box with two smoke boxes"),
+    ("final", "Final scene of Ray Tracing: The Next Week"),
+];
+
+/// Builds the built-in scene `name`, using `split_method` for the BVHs it creates.
+pub fn builtin_scene(name: &str, split_method: SplitMethod, rng: &mut impl Rng) -> Option<Scene> {
+    let null_mat: Arc<Box<dyn Material>> = Arc::new(Box::new(()));
+    let mut settings = RenderSettings::default();
+
+    // Lights are discovered from emissive materials; these are sampled in addition.
+    let (world, extra_lights, background, look_from, look_at, vfov, aperture): (
+        _,
+        Vec<Box<dyn Hittable>>,
+        _,
+        _,
+        _,
+        _,
+        _,
+    ) = match name {
+        "random" => {
+            settings.samples_per_pixel = 500;
+            (
+                random_scene(rng),
+                Vec::new(),
+                Color(vec3(0.70, 0.80, 1.00)),
+                point3(13.0, 2.0, 3.0),
+                point3(0.0, 0.0, 0.0),
+                Deg(20.0),
+                0.1,
+            )
+        }
+        "two-spheres" => (
+            two_spheres(),
+            Vec::new(),
+            Color(vec3(0.70, 0.80, 1.00)),
+            point3(13.0, 2.0, 3.0),
+            point3(0.0, 0.0, 0.0),
+            Deg(20.0),
+            0.0,
+        ),
+        "two-perlin-spheres" => (
+            two_perlin_spheres(rng),
+            Vec::new(),
+            Color(vec3(0.70, 0.80, 1.00)),
+            point3(13.0, 2.0, 3.0),
+            point3(0.0, 0.0, 0.0),
+            Deg(20.0),
+            0.0,
+        ),
+        "earth" => (
+            earth(),
+            Vec::new(),
+            Color(vec3(0.70, 0.80, 1.00)),
+            point3(13.0, 2.0, 3.0),
+            point3(0.0, 0.0, 0.0),
+            Deg(20.0),
+            0.0,
+        ),
+        "simple-light" => {
+            settings.samples_per_pixel = 400;
+            (
+                simple_light(rng),
+                Vec::new(),
+                Color(vec3(0.0, 0.0, 0.0)),
+                point3(26.0, 3.0, 6.0),
+                point3(0.0, 2.0, 0.0),
+                Deg(20.0),
+                0.0,
+            )
+        }
+        "cornell-box" => {
+            settings.aspect_ratio = 1.0;
+            settings.image_width = 600;
+            settings.samples_per_pixel = 100;
+            (
+                cornel_box(rng),
+                vec![Box::new(Sphere {
+                    center: point3(190.0, 90.0, 190.0),
+                    radius: 90.0,
+                    material: null_mat,
+                })],
+                Color(vec3(0.0, 0.0, 0.0)),
+                point3(278.0, 278.0, -800.0),
+                point3(278.0, 278.0, 0.0),
+                Deg(40.0),
+                0.0,
+            )
+        }
+        "cornell-smoke" => {
+            settings.aspect_ratio = 1.0;
+            settings.image_width = 600;
+            settings.samples_per_pixel = 200;
+            (
+                cornel_smoke(rng),
+                Vec::new(),
+                Color(vec3(0.0, 0.0, 0.0)),
+                point3(278.0, 278.0, -800.0),
+                point3(278.0, 278.0, 0.0),
+                Deg(40.0),
+                0.0,
+            )
+        }
+        "final" => {
+            settings.aspect_ratio = 1.0;
+            settings.image_width = 800;
+            settings.samples_per_pixel = 10000;
+            (
+                final_scene(split_method, rng),
+                Vec::new(),
+                Color(vec3(0.0, 0.0, 0.0)),
+                point3(478.0, 278.0, -600.0),
+                point3(278.0, 278.0, 0.0),
+                Deg(40.0),
+                0.0,
+            )
+        }
+        _ => return None,
+    };
+
+    let mut lights = LightsBuilder::default();
+    let world = world
+        .into_iter()
+        .map(|object| lights.discover(object))
+        .collect();
+    for light in extra_lights {
+        lights.add(light);
+    }
+
+    Some(Scene {
+        world: BVHNode::with_split_method(world, 0.0, 1.0, split_method, rng).into(),
+        lights: lights.build(),
+        delta_lights: Vec::new(),
+        background,
+        camera: CameraSettings {
+            look_from,
+            look_at,
+            vup: vec3(0.0, 1.0, 0.0),
+            vfov,
+            aperture,
+            focus_dist: 10.0,
+            time0: 0.0,
+            time1: 1.0,
+        },
+        settings,
+    })
+}
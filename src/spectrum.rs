@@ -0,0 +1,255 @@
+//! Spectral rendering: the wavelengths a path carries, RGB upsampling, dispersion and the
+//! conversion back to RGB.
+
+use cgmath::{vec3, Matrix3, SquareMatrix, Vector3, Vector4};
+
+use crate::{color::Color, Float};
+
+pub const WAVELENGTH_MIN: Float = 360.0;
+pub const WAVELENGTH_MAX: Float = 830.0;
+/// Number of wavelengths every spectral path carries.
+pub const SAMPLES: usize = 4;
+
+/// Values at the wavelengths of a [`SampledWavelengths`].
+pub type SampledSpectrum = Vector4<Float>;
+
+/// Wavelengths in nanometres that a path carries light at, led by the hero wavelength that
+/// wavelength-dependent scattering follows.
+#[derive(Clone, Copy, Debug)]
+pub struct SampledWavelengths {
+    pub lambda: [Float; SAMPLES],
+    /// Density each wavelength was sampled with, zero once it no longer contributes.
+    pub pdf: [Float; SAMPLES],
+}
+
+/// Index of refraction that varies with wavelength. Coefficients take wavelengths in
+/// micrometres, as they are usually tabulated.
+#[derive(Clone, Copy, Debug)]
+pub enum Dispersion {
+    /// `n = a + b / λ²`.
+    Cauchy { a: Float, b: Float },
+    /// `n² = 1 + Σ b λ² / (λ² - c)`.
+    Sellmeier { b: [Float; 3], c: [Float; 3] },
+}
+
+/// Integral of the CIE ȳ matching function below.
+const CIE_Y_INTEGRAL: Float = 106.922;
+
+/// XYZ to linear sRGB, adapted (Bradford) so that an equal-energy spectrum comes out white.
+const XYZ_TO_RGB: Matrix3<Float> = Matrix3::new(
+    3.1478095, -0.9947474, 0.0635155, //
+    -1.6628463, 1.9535709, -0.2145109, //
+    -0.4805744, 0.0397402, 1.1515952,
+);
+
+/// Basis spectra of Smits' RGB to spectrum conversion, in ten bins from 380 to 720 nm.
+const SMITS_WHITE: [Float; 10] = [
+    1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000,
+];
+const SMITS_CYAN: [Float; 10] = [
+    0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000,
+];
+const SMITS_MAGENTA: [Float; 10] = [
+    1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959,
+];
+const SMITS_YELLOW: [Float; 10] = [
+    0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840,
+];
+const SMITS_RED: [Float; 10] = [
+    0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149,
+];
+const SMITS_GREEN: [Float; 10] = [
+    0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025,
+];
+const SMITS_BLUE: [Float; 10] = [
+    1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496,
+];
+
+/// Linear sRGB that each basis spectrum converts back to. Smits fitted the spectra to other
+/// primaries, so red comes back up to 11% too strong unless the amounts are corrected.
+const SMITS_WHITE_RGB: [Float; 3] = [1.000310, 0.999404, 0.999922];
+const SMITS_CYAN_RGB: [Float; 3] = [-0.107959, 0.990284, 0.996136];
+const SMITS_MAGENTA_RGB: [Float; 3] = [1.101105, 0.007843, 0.997721];
+const SMITS_YELLOW_RGB: [Float; 3] = [1.008361, 1.000030, 0.005728];
+const SMITS_RED_RGB: [Float; 3] = [1.107955, 0.009204, 0.003791];
+const SMITS_GREEN_RGB: [Float; 3] = [-0.101057, 0.991703, 0.002150];
+const SMITS_BLUE_RGB: [Float; 3] = [-0.008384, -0.000483, 0.994228];
+
+type SmitsBasis = (&'static [Float; 10], [Float; 3]);
+
+const WHITE: SmitsBasis = (&SMITS_WHITE, SMITS_WHITE_RGB);
+const CYAN: SmitsBasis = (&SMITS_CYAN, SMITS_CYAN_RGB);
+const MAGENTA: SmitsBasis = (&SMITS_MAGENTA, SMITS_MAGENTA_RGB);
+const YELLOW: SmitsBasis = (&SMITS_YELLOW, SMITS_YELLOW_RGB);
+const RED: SmitsBasis = (&SMITS_RED, SMITS_RED_RGB);
+const GREEN: SmitsBasis = (&SMITS_GREEN, SMITS_GREEN_RGB);
+const BLUE: SmitsBasis = (&SMITS_BLUE, SMITS_BLUE_RGB);
+
+/// A Gaussian with different widths left and right of its mean.
+fn piecewise_gaussian(x: Float, mean: Float, left: Float, right: Float) -> Float {
+    let sigma = if x < mean { left } else { right };
+    (-0.5 * ((x - mean) / sigma).powi(2)).exp()
+}
+
+/// CIE 1931 color matching functions at `lambda`, in the multi-lobe fit of Wyman et al. 2013.
+pub fn cie_xyz(lambda: Float) -> [Float; 3] {
+    [
+        1.056 * piecewise_gaussian(lambda, 599.8, 37.9, 31.0)
+            + 0.362 * piecewise_gaussian(lambda, 442.0, 16.0, 26.7)
+            - 0.065 * piecewise_gaussian(lambda, 501.1, 20.4, 26.2),
+        0.821 * piecewise_gaussian(lambda, 568.8, 46.9, 40.5)
+            + 0.286 * piecewise_gaussian(lambda, 530.9, 16.3, 31.1),
+        1.217 * piecewise_gaussian(lambda, 437.0, 11.8, 36.0)
+            + 0.681 * piecewise_gaussian(lambda, 459.0, 26.0, 13.8),
+    ]
+}
+
+/// Spectral radiance of a blackbody at `kelvin` by Planck's law, at `lambda` in nanometres.
+pub fn planck(lambda: Float, kelvin: Float) -> Float {
+    const C: Float = 299_792_458.0;
+    const H: Float = 6.626_070_15e-34;
+    const KB: Float = 1.380_649e-23;
+
+    let metres = lambda * 1e-9;
+    2.0 * H * C * C / (metres.powi(5) * ((H * C / (metres * KB * kelvin)).exp() - 1.0))
+}
+
+/// Linear sRGB color of a blackbody at `kelvin`, scaled to a luminance of one.
+pub fn blackbody(kelvin: Float) -> Color {
+    let mut xyz = vec3(0.0, 0.0, 0.0);
+    for nm in WAVELENGTH_MIN as usize..=WAVELENGTH_MAX as usize {
+        let [x, y, z] = cie_xyz(nm as Float);
+        xyz += vec3(x, y, z) * planck(nm as Float, kelvin);
+    }
+    let rgb = XYZ_TO_RGB * xyz;
+    let color = Color(vec3(rgb.x.max(0.0), rgb.y.max(0.0), rgb.z.max(0.0)));
+    Color(color.0 / color.luminance())
+}
+
+/// Smits' spectrum for `color`: white for the smallest channel plus a secondary and a primary
+/// color for the rest.
+struct SmitsSpectrum {
+    spectra: [&'static [Float; 10]; 3],
+    amounts: Vector3<Float>,
+}
+
+impl SmitsSpectrum {
+    fn new(color: Color) -> Self {
+        let (r, g, b) = (color.x, color.y, color.z);
+        let bases = if r <= g && r <= b {
+            [WHITE, CYAN, if g <= b { BLUE } else { GREEN }]
+        } else if g <= r && g <= b {
+            [WHITE, MAGENTA, if r <= b { BLUE } else { RED }]
+        } else {
+            [WHITE, YELLOW, if r <= g { GREEN } else { RED }]
+        };
+
+        // The amounts of the three spectra that convert back to `color`, rather than Smits'
+        // nominal ones of the smallest channel and the two differences.
+        let to_rgb = Matrix3::from_cols(bases[0].1.into(), bases[1].1.into(), bases[2].1.into());
+        let amounts = to_rgb.invert().expect("the basis colors are independent") * color.0;
+
+        Self {
+            spectra: bases.map(|(spectrum, _)| spectrum),
+            amounts,
+        }
+    }
+
+    fn value(&self, lambda: Float) -> Float {
+        let bin = (((lambda - 380.0) / 34.0) as isize).clamp(0, 9) as usize;
+        (self.amounts.x * self.spectra[0][bin]
+            + self.amounts.y * self.spectra[1][bin]
+            + self.amounts.z * self.spectra[2][bin])
+            .max(0.0)
+    }
+}
+
+impl SampledWavelengths {
+    /// Spreads the wavelengths evenly from `u` in [0, 1) through a density that favors the
+    /// visible range (the one pbrt-v4 uses).
+    pub fn sample_visible(u: Float) -> Self {
+        let mut lambda = [0.0; SAMPLES];
+        let mut pdf = [0.0; SAMPLES];
+        for i in 0..SAMPLES {
+            let u = (u + i as Float / SAMPLES as Float).fract();
+            lambda[i] = (538.0 - 138.888889 * (0.85691062 - 1.82750197 * u).atanh())
+                .clamp(WAVELENGTH_MIN, WAVELENGTH_MAX);
+            pdf[i] = 0.0039398042 / (0.0072 * (lambda[i] - 538.0)).cosh().powi(2);
+        }
+        Self { lambda, pdf }
+    }
+
+    pub fn hero(&self) -> Float {
+        self.lambda[0]
+    }
+
+    /// Keeps only the hero wavelength, for paths that scatter differently by wavelength.
+    pub fn terminate_secondary(&mut self) {
+        if self.pdf[1..].iter().any(|&pdf| pdf > 0.0) {
+            self.pdf[0] /= SAMPLES as Float;
+            for pdf in &mut self.pdf[1..] {
+                *pdf = 0.0;
+            }
+        }
+    }
+
+    /// A reflectance or emission given as RGB at these wavelengths, after Smits 1999.
+    pub fn upsample(&self, color: Color) -> SampledSpectrum {
+        let spectrum = SmitsSpectrum::new(color);
+        Vector4::new(
+            spectrum.value(self.lambda[0]),
+            spectrum.value(self.lambda[1]),
+            spectrum.value(self.lambda[2]),
+            spectrum.value(self.lambda[3]),
+        )
+    }
+
+    /// Linear sRGB of `radiance` carried at these wavelengths.
+    pub fn to_rgb(&self, radiance: SampledSpectrum) -> Color {
+        let mut xyz = vec3(0.0, 0.0, 0.0);
+        for i in 0..SAMPLES {
+            if self.pdf[i] > 0.0 {
+                let [x, y, z] = cie_xyz(self.lambda[i]);
+                xyz += vec3(x, y, z) * (radiance[i] / self.pdf[i]);
+            }
+        }
+        Color(XYZ_TO_RGB * (xyz / (SAMPLES as Float * CIE_Y_INTEGRAL)))
+    }
+}
+
+impl Dispersion {
+    pub const BK7: Self = Self::Sellmeier {
+        b: [1.03961212, 0.231792344, 1.01046945],
+        c: [0.00600069867, 0.0200179144, 103.560653],
+    };
+    pub const FUSED_SILICA: Self = Self::Sellmeier {
+        b: [0.6961663, 0.4079426, 0.8974794],
+        c: [0.00467914826, 0.0135120631, 97.9340025],
+    };
+    pub const DIAMOND: Self = Self::Sellmeier {
+        b: [4.3356, 0.3306, 0.0],
+        c: [0.011236, 0.030625, 0.0],
+    };
+
+    /// Looks up a preset by name: `bk7`, `fused_silica` or `diamond`.
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "bk7" => Some(Self::BK7),
+            "fused_silica" => Some(Self::FUSED_SILICA),
+            "diamond" => Some(Self::DIAMOND),
+            _ => None,
+        }
+    }
+
+    /// Index of refraction at `wavelength` in nanometres.
+    pub fn ior(&self, wavelength: Float) -> Float {
+        let micrometres = wavelength / 1000.0;
+        let l2 = micrometres * micrometres;
+        match self {
+            Self::Cauchy { a, b } => a + b / l2,
+            Self::Sellmeier { b, c } => {
+                (1.0 + (0..3).map(|i| b[i] * l2 / (l2 - c[i])).sum::<Float>()).sqrt()
+            }
+        }
+    }
+}
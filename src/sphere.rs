@@ -5,9 +5,9 @@ use num_traits::FloatConst;
 
 use crate::{
     aabb::AABB,
-    hittable::{HitRecord, Hittable},
+    hittable::{area_to_solid_angle_pdf, HitRecord, Hittable},
     material::Material,
-    math::{random_to_sphere, sphere_uv},
+    math::{random_in_unit_sphere, random_to_sphere, sphere_tangent, sphere_uv},
     onb::Onb,
     ray::Ray,
     Float, MyRng,
@@ -27,7 +27,7 @@ impl Hittable for Sphere {
         t_min: Float,
         t_max: Float,
         _rng: &mut MyRng,
-    ) -> Option<HitRecord> {
+    ) -> Option<HitRecord<'_>> {
         let oc = ray.origin - self.center;
         let a = ray.direction.magnitude2();
         let half_b = dot(oc, ray.direction);
@@ -51,15 +51,10 @@ impl Hittable for Sphere {
         let outward_normal = (position - self.center) / self.radius;
         let (u, v) = sphere_uv(EuclideanSpace::from_vec(outward_normal));
 
-        Some(HitRecord::new(
-            position,
-            outward_normal,
-            root,
-            u,
-            v,
-            ray,
-            self.material.clone(),
-        ))
+        Some(
+            HitRecord::new(position, outward_normal, root, u, v, ray, &**self.material)
+                .with_tangent(sphere_tangent(outward_normal, self.radius)),
+        )
     }
 
     fn bounding_box(&self, _time0: Float, _time1: Float) -> Option<AABB> {
@@ -69,32 +64,62 @@ impl Hittable for Sphere {
         })
     }
 
-    fn pdf_value(&self, o: Point3<Float>, v: Vector3<Float>, rng: &mut MyRng) -> Float {
+    fn pdf_value(
+        &self,
+        o: Point3<Float>,
+        v: Vector3<Float>,
+        time: Float,
+        rng: &mut MyRng,
+    ) -> Float {
         self.hit(
             &Ray {
                 origin: o,
                 direction: v,
-                time: 0.0,
+                time,
             },
             0.001,
             Float::INFINITY,
             rng,
         )
-        .map(|_| {
-            let cos_theta_max =
-                (1.0 - self.radius * self.radius / (self.center - o).magnitude2()).sqrt();
+        .map(|hit_record| {
+            let distance_squared = (self.center - o).magnitude2();
+            // From inside, the cone of directions towards the sphere is everything.
+            if distance_squared < self.radius * self.radius {
+                return area_to_solid_angle_pdf(
+                    &hit_record,
+                    v,
+                    4.0 * Float::PI() * self.radius * self.radius,
+                );
+            }
+            let cos_theta_max = (1.0 - self.radius * self.radius / distance_squared).sqrt();
             let solid_angle = 2.0 * Float::PI() * (1.0 - cos_theta_max);
             1.0 / solid_angle
         })
         .unwrap_or(0.0)
     }
 
-    fn random(&self, o: Point3<Float>, rng: &mut MyRng) -> Vector3<Float> {
+    fn random(&self, o: Point3<Float>, _time: Float, rng: &mut MyRng) -> Vector3<Float> {
         let direction = self.center - o;
+        if direction.magnitude2() < self.radius * self.radius {
+            let point = self.center + self.radius * random_in_unit_sphere(rng).normalize();
+            return point - o;
+        }
         let distance_squared = direction.magnitude2();
 
         let uvw = Onb::from_w(direction);
 
         uvw.local(random_to_sphere(self.radius, distance_squared, rng))
     }
+
+    fn supports_sampling(&self) -> bool {
+        true
+    }
+
+    fn area(&self) -> Option<Float> {
+        Some(4.0 * Float::PI() * self.radius * self.radius)
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        Some(&**self.material)
+    }
 }
@@ -1,7 +1,7 @@
 use cgmath::{vec3, Point3};
 use image::{DynamicImage, GenericImageView};
 use rand::Rng;
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
 
 use crate::{color::Color, perlin::Perlin, Float};
 
@@ -37,6 +37,12 @@ impl<const POINT_COUNT: usize> NoiseTexture<POINT_COUNT> {
     }
 }
 
+impl<T: Texture + ?Sized> Texture for Arc<T> {
+    fn value(&self, u: Float, v: Float, point: Point3<Float>) -> Color {
+        self.as_ref().value(u, v, point)
+    }
+}
+
 impl Texture for SolidColor {
     fn value(&self, _u: Float, _v: Float, _point: Point3<Float>) -> Color {
         self.color_value
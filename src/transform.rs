@@ -0,0 +1,327 @@
+use std::sync::Arc;
+
+use cgmath::{
+    point3, vec3, InnerSpace, Matrix, Matrix4, Point3, Rad, SquareMatrix, Transform as _, Vector3,
+    Vector4,
+};
+
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    Float, MyRng,
+};
+
+/// An invertible affine transform, kept together with its inverse.
+///
+/// The builder methods apply their step after the transforms already in `self`, so
+/// `Affine::identity().scale(...).rotate_y(...).translate(...)` scales first and translates last.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Affine {
+    matrix: Matrix4<Float>,
+    inverse: Matrix4<Float>,
+}
+
+/// Applies an [`Affine`] transform to a hittable.
+pub struct Transform<T> {
+    hittable: T,
+    transform: Affine,
+    aabb: Option<AABB>,
+}
+
+impl Default for Affine {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Affine {
+    pub fn identity() -> Self {
+        Self {
+            matrix: Matrix4::identity(),
+            inverse: Matrix4::identity(),
+        }
+    }
+
+    /// Returns `None` if `matrix` is singular or not affine.
+    pub fn from_matrix(matrix: Matrix4<Float>) -> Option<Self> {
+        if matrix.row(3) != Vector4::unit_w() {
+            return None;
+        }
+        let inverse = matrix.invert()?;
+        Some(Self { matrix, inverse })
+    }
+
+    /// Maps local space to a frame at `eye` whose +z axis points at `target` and whose +y axis is
+    /// as close to `up` as possible.
+    pub fn look_at(eye: Point3<Float>, target: Point3<Float>, up: Vector3<Float>) -> Self {
+        let w = (target - eye).normalize();
+        let u = up.cross(w).normalize();
+        let v = w.cross(u);
+        let matrix = Matrix4::from_cols(
+            u.extend(0.0),
+            v.extend(0.0),
+            w.extend(0.0),
+            eye.to_homogeneous(),
+        );
+        Self::from_matrix(matrix)
+            .expect("look_at needs distinct eye and target and up not along the view")
+    }
+
+    pub fn matrix(&self) -> Matrix4<Float> {
+        self.matrix
+    }
+
+    pub fn inverse(&self) -> Self {
+        Self {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    /// Applies `self`, then `next`.
+    pub fn then(self, next: Affine) -> Self {
+        Self {
+            matrix: next.matrix * self.matrix,
+            inverse: self.inverse * next.inverse,
+        }
+    }
+
+    pub fn translate(self, offset: Vector3<Float>) -> Self {
+        self.then(Self {
+            matrix: Matrix4::from_translation(offset),
+            inverse: Matrix4::from_translation(-offset),
+        })
+    }
+
+    pub fn scale(self, factors: Vector3<Float>) -> Self {
+        assert!(
+            factors.x != 0.0 && factors.y != 0.0 && factors.z != 0.0,
+            "scale factors must not be zero"
+        );
+        self.then(Self {
+            matrix: Matrix4::from_nonuniform_scale(factors.x, factors.y, factors.z),
+            inverse: Matrix4::from_nonuniform_scale(
+                1.0 / factors.x,
+                1.0 / factors.y,
+                1.0 / factors.z,
+            ),
+        })
+    }
+
+    pub fn uniform_scale(self, factor: Float) -> Self {
+        self.scale(vec3(factor, factor, factor))
+    }
+
+    pub fn rotate_x(self, angle: impl Into<Rad<Float>>) -> Self {
+        self.rotate(Vector3::unit_x(), angle)
+    }
+
+    pub fn rotate_y(self, angle: impl Into<Rad<Float>>) -> Self {
+        self.rotate(Vector3::unit_y(), angle)
+    }
+
+    pub fn rotate_z(self, angle: impl Into<Rad<Float>>) -> Self {
+        self.rotate(Vector3::unit_z(), angle)
+    }
+
+    /// Rotates counterclockwise about `axis`, looking down the axis towards the origin.
+    pub fn rotate(self, axis: Vector3<Float>, angle: impl Into<Rad<Float>>) -> Self {
+        let rotation = Matrix4::from_axis_angle(axis.normalize(), angle.into());
+        self.then(Self {
+            matrix: rotation,
+            // Rotations are orthogonal.
+            inverse: rotation.transpose(),
+        })
+    }
+
+    pub fn transform_point(&self, p: Point3<Float>) -> Point3<Float> {
+        self.matrix.transform_point(p)
+    }
+
+    pub fn transform_vector(&self, v: Vector3<Float>) -> Vector3<Float> {
+        self.matrix.transform_vector(v)
+    }
+
+    /// Transforms a surface normal with the inverse transpose. The result is not normalized.
+    pub fn transform_normal(&self, n: Vector3<Float>) -> Vector3<Float> {
+        self.inverse.transpose().transform_vector(n)
+    }
+
+    pub fn transform_ray(&self, ray: &Ray) -> Ray {
+        // The direction is not renormalized, so ray parameters are the same in both spaces.
+        ray.spawn(
+            self.transform_point(ray.origin),
+            self.transform_vector(ray.direction),
+        )
+    }
+
+    /// Bounds the eight transformed corners of `aabb`.
+    pub fn transform_aabb(&self, aabb: AABB) -> AABB {
+        let mut minimum = point3(Float::INFINITY, Float::INFINITY, Float::INFINITY);
+        let mut maximum = point3(
+            Float::NEG_INFINITY,
+            Float::NEG_INFINITY,
+            Float::NEG_INFINITY,
+        );
+
+        for i in 0..8 {
+            let corner = point3(
+                if i & 1 == 0 {
+                    aabb.minimum.x
+                } else {
+                    aabb.maximum.x
+                },
+                if i & 2 == 0 {
+                    aabb.minimum.y
+                } else {
+                    aabb.maximum.y
+                },
+                if i & 4 == 0 {
+                    aabb.minimum.z
+                } else {
+                    aabb.maximum.z
+                },
+            );
+            let p = self.transform_point(corner);
+            for c in 0..3 {
+                minimum[c] = minimum[c].min(p[c]);
+                maximum[c] = maximum[c].max(p[c]);
+            }
+        }
+
+        AABB { minimum, maximum }
+    }
+
+    /// Maps a hit found in local space to world space.
+    pub fn transform_hit<'a>(&self, mut hit_record: HitRecord<'a>) -> HitRecord<'a> {
+        hit_record.position = self.transform_point(hit_record.position);
+        // An affine map keeps the normal on the side facing the ray, so `front_face` stays valid.
+        hit_record.normal = self.transform_normal(hit_record.normal).normalize();
+        hit_record.tangent = self.transform_vector(hit_record.tangent);
+        hit_record
+    }
+
+    /// How much the transform scales surface areas. This is exact for rotations and uniform
+    /// scales and an estimate otherwise.
+    pub fn area_scale(&self) -> Float {
+        self.matrix.determinant().abs().powf(2.0 / 3.0)
+    }
+
+    /// Density of sampling world direction `v` from `origin` by sampling `hittable` in the local
+    /// space of `self` and mapping the direction back.
+    ///
+    /// The linear part `B` of the inverse maps a unit world direction `w` to `B w`, which
+    /// stretches solid angle by `|det B| / |B w|^3`.
+    pub fn transform_pdf_value<H: Hittable + ?Sized>(
+        &self,
+        hittable: &H,
+        origin: Point3<Float>,
+        v: Vector3<Float>,
+        time: Float,
+        rng: &mut MyRng,
+    ) -> Float {
+        let inverse = self.inverse();
+        let local_direction = inverse.transform_vector(v.normalize());
+        let local_pdf =
+            hittable.pdf_value(inverse.transform_point(origin), local_direction, time, rng);
+        let jacobian = self.inverse.determinant().abs() / local_direction.magnitude().powi(3);
+
+        local_pdf * jacobian
+    }
+
+    /// Samples `hittable` in the local space of `self` and returns the world direction.
+    pub fn transform_random<H: Hittable + ?Sized>(
+        &self,
+        hittable: &H,
+        origin: Point3<Float>,
+        time: Float,
+        rng: &mut MyRng,
+    ) -> Vector3<Float> {
+        let local_origin = self.inverse().transform_point(origin);
+        self.transform_vector(hittable.random(local_origin, time, rng))
+    }
+}
+
+impl<T: Hittable> Transform<T> {
+    /// `transform` maps the local space of `hittable` to world space.
+    pub fn new(hittable: T, transform: Affine, time0: Float, time1: Float) -> Self {
+        let aabb = hittable
+            .bounding_box(time0, time1)
+            .map(|aabb| transform.transform_aabb(aabb));
+        Self {
+            hittable,
+            transform,
+            aabb,
+        }
+    }
+
+    pub fn transform(&self) -> &Affine {
+        &self.transform
+    }
+
+    /// The parts of the transformed object, each under this transform and bounded by the box of
+    /// the whole object.
+    pub(crate) fn transformed_parts(&self) -> Vec<Transform<Arc<dyn Hittable>>> {
+        self.hittable
+            .parts()
+            .into_iter()
+            .map(|part| Transform {
+                hittable: part,
+                transform: self.transform,
+                aabb: self.aabb,
+            })
+            .collect()
+    }
+}
+
+impl<T: Hittable> Hittable for Transform<T> {
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float, rng: &mut MyRng) -> Option<HitRecord<'_>> {
+        let local = self.transform.inverse().transform_ray(ray);
+        self.hittable
+            .hit(&local, t_min, t_max, rng)
+            .map(|hit_record| self.transform.transform_hit(hit_record))
+    }
+
+    fn bounding_box(&self, _time0: Float, _time1: Float) -> Option<AABB> {
+        self.aabb
+    }
+
+    fn pdf_value(
+        &self,
+        origin: Point3<Float>,
+        v: Vector3<Float>,
+        time: Float,
+        rng: &mut MyRng,
+    ) -> Float {
+        self.transform
+            .transform_pdf_value(&self.hittable, origin, v, time, rng)
+    }
+
+    fn random(&self, origin: Point3<Float>, time: Float, rng: &mut MyRng) -> Vector3<Float> {
+        self.transform
+            .transform_random(&self.hittable, origin, time, rng)
+    }
+
+    fn supports_sampling(&self) -> bool {
+        self.hittable.supports_sampling()
+    }
+
+    fn area(&self) -> Option<Float> {
+        self.hittable
+            .area()
+            .map(|area| area * self.transform.area_scale())
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        self.hittable.material()
+    }
+
+    fn parts(&self) -> Vec<Arc<dyn Hittable>> {
+        self.transformed_parts()
+            .into_iter()
+            .map(|part| -> Arc<dyn Hittable> { Arc::new(part) })
+            .collect()
+    }
+}
@@ -0,0 +1,175 @@
+use std::sync::Arc;
+
+use cgmath::{dot, point3, EuclideanSpace, InnerSpace, Point2, Point3, Vector3};
+use rand::Rng;
+
+use crate::{
+    aabb::AABB,
+    hittable::{area_to_solid_angle_pdf, HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    Float, MyRng,
+};
+
+pub struct Triangle {
+    pub v0: Point3<Float>,
+    pub v1: Point3<Float>,
+    pub v2: Point3<Float>,
+    pub material: Arc<Box<dyn Material>>,
+}
+
+/// Möller–Trumbore intersection. Returns `(t, b1, b2)` where `b1` and `b2` are the
+/// barycentric weights of `v1` and `v2`.
+pub fn intersect_triangle(
+    ray: &Ray,
+    v0: Point3<Float>,
+    v1: Point3<Float>,
+    v2: Point3<Float>,
+    t_min: Float,
+    t_max: Float,
+) -> Option<(Float, Float, Float)> {
+    const EPSILON: Float = 1e-12;
+
+    let edge1 = v1 - v0;
+    let edge2 = v2 - v0;
+    let pvec = ray.direction.cross(edge2);
+    let det = dot(edge1, pvec);
+
+    if det.abs() < EPSILON {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+
+    let tvec = ray.origin - v0;
+    let b1 = dot(tvec, pvec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+
+    let qvec = tvec.cross(edge1);
+    let b2 = dot(ray.direction, qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let t = dot(edge2, qvec) * inv_det;
+    if t < t_min || t > t_max {
+        return None;
+    }
+
+    Some((t, b1, b2))
+}
+
+pub fn triangle_bounding_box(v0: Point3<Float>, v1: Point3<Float>, v2: Point3<Float>) -> AABB {
+    const PADDING: Float = 0.0001;
+
+    AABB {
+        minimum: point3(
+            v0.x.min(v1.x).min(v2.x) - PADDING,
+            v0.y.min(v1.y).min(v2.y) - PADDING,
+            v0.z.min(v1.z).min(v2.z) - PADDING,
+        ),
+        maximum: point3(
+            v0.x.max(v1.x).max(v2.x) + PADDING,
+            v0.y.max(v1.y).max(v2.y) + PADDING,
+            v0.z.max(v1.z).max(v2.z) + PADDING,
+        ),
+    }
+}
+
+pub fn triangle_area(v0: Point3<Float>, v1: Point3<Float>, v2: Point3<Float>) -> Float {
+    0.5 * (v1 - v0).cross(v2 - v0).magnitude()
+}
+
+/// The tangent dp/du of a triangle whose vertices have texture coordinates `uvs`. Without
+/// usable coordinates, `u` is taken to run along the edge from `v0` to `v1`.
+pub fn triangle_tangent(
+    v0: Point3<Float>,
+    v1: Point3<Float>,
+    v2: Point3<Float>,
+    uvs: Option<(Point2<Float>, Point2<Float>, Point2<Float>)>,
+) -> Vector3<Float> {
+    let (e1, e2) = (v1 - v0, v2 - v0);
+    if let Some((uv0, uv1, uv2)) = uvs {
+        let (d1, d2) = (uv1 - uv0, uv2 - uv0);
+        let determinant = d1.x * d2.y - d1.y * d2.x;
+        if determinant.abs() > 1e-12 {
+            return (d2.y * e1 - d1.y * e2) / determinant;
+        }
+    }
+    e1
+}
+
+/// Uniformly distributed point on the triangle.
+pub fn random_point_on_triangle(
+    v0: Point3<Float>,
+    v1: Point3<Float>,
+    v2: Point3<Float>,
+    rng: &mut MyRng,
+) -> Point3<Float> {
+    let su0 = rng.gen::<Float>().sqrt();
+    let b1 = su0 * (1.0 - rng.gen::<Float>());
+    let b2 = su0 - b1;
+    Point3::from_vec((1.0 - su0) * v0.to_vec() + b1 * v1.to_vec() + b2 * v2.to_vec())
+}
+
+impl Triangle {
+    pub fn normal(&self) -> Vector3<Float> {
+        (self.v1 - self.v0).cross(self.v2 - self.v0).normalize()
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(
+        &self,
+        ray: &Ray,
+        t_min: Float,
+        t_max: Float,
+        _rng: &mut MyRng,
+    ) -> Option<HitRecord<'_>> {
+        let (t, b1, b2) = intersect_triangle(ray, self.v0, self.v1, self.v2, t_min, t_max)?;
+
+        Some(
+            HitRecord::new(ray.at(t), self.normal(), t, b1, b2, ray, &**self.material)
+                .with_tangent(self.v1 - self.v0),
+        )
+    }
+
+    fn bounding_box(&self, _time0: Float, _time1: Float) -> Option<AABB> {
+        Some(triangle_bounding_box(self.v0, self.v1, self.v2))
+    }
+
+    fn pdf_value(
+        &self,
+        origin: Point3<Float>,
+        v: Vector3<Float>,
+        time: Float,
+        rng: &mut MyRng,
+    ) -> Float {
+        let ray = Ray {
+            origin,
+            direction: v,
+            time,
+        };
+        self.hit(&ray, 0.001, Float::INFINITY, rng)
+            .map_or(0.0, |hit_record| {
+                area_to_solid_angle_pdf(&hit_record, v, triangle_area(self.v0, self.v1, self.v2))
+            })
+    }
+
+    fn random(&self, origin: Point3<Float>, _time: Float, rng: &mut MyRng) -> Vector3<Float> {
+        random_point_on_triangle(self.v0, self.v1, self.v2, rng) - origin
+    }
+
+    fn supports_sampling(&self) -> bool {
+        true
+    }
+
+    fn area(&self) -> Option<Float> {
+        Some(triangle_area(self.v0, self.v1, self.v2))
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        Some(&**self.material)
+    }
+}
@@ -0,0 +1,189 @@
+//! Every way of building and laying out a BVH finds the same hits as testing every object.
+
+use std::sync::Arc;
+
+use cgmath::{point3, vec3, InnerSpace};
+use rand::{Rng, SeedableRng};
+use raytracing_1w::{
+    bvh::{BVHNode, BVHStats, FlatBVH, SahOptions, SplitMethod},
+    hittable::Hittable,
+    math::random_in_unit_sphere,
+    ray::Ray,
+    sphere::Sphere,
+    Float, MyRng,
+};
+
+/// Overlapping spheres of many sizes, including some sharing a center.
+fn spheres(count: usize) -> Vec<Box<dyn Hittable>> {
+    let mut rng = MyRng::seed_from_u64(1);
+    (0..count)
+        .map(|i| -> Box<dyn Hittable> {
+            let center = if i % 10 == 0 {
+                point3(1.0, 1.0, 1.0)
+            } else {
+                point3(
+                    rng.gen_range(-10.0..10.0),
+                    rng.gen_range(-10.0..10.0),
+                    rng.gen_range(-10.0..10.0),
+                )
+            };
+            Box::new(Sphere {
+                center,
+                radius: rng.gen_range(0.01..1.5),
+                material: Arc::new(Box::new(())),
+            })
+        })
+        .collect()
+}
+
+/// Rays from inside and outside the spheres, some along the axes.
+fn rays(count: usize) -> Vec<(Ray, Float)> {
+    let mut rng = MyRng::seed_from_u64(2);
+    (0..count)
+        .map(|i| {
+            let origin = point3(
+                rng.gen_range(-15.0..15.0),
+                rng.gen_range(-15.0..15.0),
+                rng.gen_range(-15.0..15.0),
+            );
+            let direction = match i % 4 {
+                0 => vec3(0.0, 0.0, 1.0),
+                1 => vec3(-1.0, 0.0, 0.0),
+                _ => random_in_unit_sphere(&mut rng).normalize(),
+            };
+            let t_max = if i % 3 == 0 { 8.0 } else { Float::INFINITY };
+            let ray = Ray {
+                origin,
+                direction,
+                time: 0.0,
+            };
+            (ray, t_max)
+        })
+        .collect()
+}
+
+/// The distances at which `object` is hit along each ray.
+fn hits(object: &dyn Hittable, rays: &[(Ray, Float)]) -> Vec<Option<Float>> {
+    let mut rng = MyRng::seed_from_u64(3);
+    rays.iter()
+        .map(|(ray, t_max)| {
+            object
+                .hit(ray, 0.001, *t_max, &mut rng)
+                .map(|hit_record| hit_record.t)
+        })
+        .collect()
+}
+
+fn build(split_method: SplitMethod) -> BVHNode {
+    let mut rng = MyRng::seed_from_u64(4);
+    BVHNode::with_split_method(spheres(500), 0.0, 1.0, split_method, &mut rng)
+}
+
+fn split_methods() -> Vec<SplitMethod> {
+    vec![
+        SplitMethod::Median,
+        SplitMethod::Sah(SahOptions::default()),
+        SplitMethod::Sah(SahOptions {
+            bins: 2,
+            max_leaf_size: 1,
+            ..SahOptions::default()
+        }),
+    ]
+}
+
+#[test]
+fn every_split_method_finds_the_nearest_hit() {
+    let rays = rays(5000);
+    let objects = spheres(500);
+    let objects: &[Box<dyn Hittable>] = &objects;
+    let expected = hits(&objects, &rays);
+    assert!(expected.iter().filter(|hit| hit.is_some()).count() > 500);
+
+    for split_method in split_methods() {
+        let bvh = build(split_method);
+        assert_eq!(bvh.stats(0.125, 1.0).primitive_count, 500);
+        assert_eq!(hits(&bvh, &rays), expected, "{:?}", split_method);
+    }
+}
+
+#[test]
+fn sah_is_cheaper_than_median_splits() {
+    let median = build(SplitMethod::Median).stats(0.125, 1.0);
+    let sah = build(SplitMethod::Sah(SahOptions::default())).stats(0.125, 1.0);
+    assert!(
+        sah.sah_cost < median.sah_cost,
+        "{:?} is not cheaper than {:?}",
+        sah,
+        median
+    );
+}
+
+#[test]
+fn flat_bvh_finds_the_same_hits_as_the_tree() {
+    let rays = rays(5000);
+
+    for split_method in split_methods() {
+        let tree = build(split_method);
+        let expected = hits(&tree, &rays);
+        let tree_stats = tree.stats(0.125, 1.0);
+
+        let flat = FlatBVH::from(tree);
+        let flat_stats = flat.stats(0.125, 1.0);
+        // The costs are summed in a different order.
+        assert!((flat_stats.sah_cost - tree_stats.sah_cost).abs() < 1e-9 * tree_stats.sah_cost);
+        assert_eq!(
+            BVHStats {
+                sah_cost: tree_stats.sah_cost,
+                ..flat_stats
+            },
+            tree_stats,
+            "{:?}",
+            split_method
+        );
+        assert_eq!(hits(&flat, &rays), expected, "{:?}", split_method);
+    }
+}
+
+#[test]
+fn sah_depth_stays_within_the_traversal_stack() {
+    // Centroids spaced exponentially put all but the last into one bin at every level, so
+    // splitting by cost alone peels off one primitive at a time.
+    let spheres = || -> Vec<Box<dyn Hittable>> {
+        (0..600)
+            .map(|i| -> Box<dyn Hittable> {
+                Box::new(Sphere {
+                    center: point3((2.0 as Float).powi(i), 0.0, 0.0),
+                    radius: 0.5,
+                    material: Arc::new(Box::new(())),
+                })
+            })
+            .collect()
+    };
+    let rays: Vec<(Ray, Float)> = (0..30)
+        .map(|i| {
+            let ray = Ray {
+                origin: point3((2.0 as Float).powi(i), 10.0, 0.0),
+                direction: vec3(0.0, -1.0, 0.0),
+                time: 0.0,
+            };
+            (ray, Float::INFINITY)
+        })
+        .collect();
+    let objects = spheres();
+    let objects: &[Box<dyn Hittable>] = &objects;
+    let expected = hits(&objects, &rays);
+    assert!(expected.iter().all(|hit| hit.is_some()));
+
+    let mut rng = MyRng::seed_from_u64(4);
+    let tree = BVHNode::with_split_method(
+        spheres(),
+        0.0,
+        1.0,
+        SplitMethod::Sah(SahOptions::default()),
+        &mut rng,
+    );
+    // The depth `FlatBVH` can traverse.
+    assert!(tree.stats(0.125, 1.0).depth <= 64);
+    let flat = FlatBVH::from(tree);
+    assert_eq!(hits(&flat, &rays), expected);
+}
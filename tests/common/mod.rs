@@ -0,0 +1,35 @@
+//! Helpers shared by the integration tests.
+
+use std::{
+    fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+/// A directory of files written for a test, removed again when dropped.
+pub struct TestDir(PathBuf);
+
+/// Writes `files` into a fresh directory named after the test.
+pub fn write_files(test: &str, files: &[(&str, &str)]) -> TestDir {
+    let dir = std::env::temp_dir().join(format!("raytracing-1w-{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for (name, contents) in files {
+        fs::write(dir.join(name), contents).unwrap();
+    }
+    TestDir(dir)
+}
+
+impl Deref for TestDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,155 @@
+//! Light from points, spots and distant lights.
+
+use cgmath::{point3, vec3, InnerSpace, Point3};
+use num_traits::FloatConst;
+use rand::SeedableRng;
+use raytracing_1w::{
+    color::Color,
+    delta_light::{DeltaLight, DistantLight, PointLight, SpotLight},
+    math::random_in_unit_sphere,
+    Float, MyRng,
+};
+
+const POSITION: Point3<Float> = Point3::new(1.0, 2.0, 3.0);
+
+fn spot(cone_angle: Float, cone_delta: Float, profile: Vec<Float>) -> SpotLight {
+    SpotLight {
+        position: POSITION,
+        direction: vec3(0.0, -1.0, 0.0),
+        intensity: Color(vec3(2.0, 2.0, 2.0)),
+        cos_total_width: cone_angle.to_radians().cos(),
+        cos_falloff_start: (cone_angle - cone_delta).to_radians().cos(),
+        profile,
+    }
+}
+
+/// The luminance of the total power reaching a sphere around `light`, by integrating the
+/// irradiance at points spread uniformly over it.
+fn power_through_sphere(light: &dyn DeltaLight) -> Float {
+    const SAMPLES: usize = 200_000;
+    const RADIUS: Float = 2.0;
+
+    let mut rng = MyRng::seed_from_u64(1);
+    let sum: Float = (0..SAMPLES)
+        .filter_map(|_| {
+            let origin = POSITION + RADIUS * random_in_unit_sphere(&mut rng).normalize();
+            light.sample(origin, &mut rng)
+        })
+        .map(|sample| sample.irradiance.luminance())
+        .sum();
+    sum / SAMPLES as Float * 4.0 * Float::PI() * RADIUS * RADIUS
+}
+
+#[test]
+fn point_light_falls_off_with_the_square_of_distance() {
+    let light = PointLight {
+        position: POSITION,
+        intensity: Color(vec3(3.0, 2.0, 1.0)),
+    };
+    let mut rng = MyRng::seed_from_u64(1);
+
+    for &distance in &[0.5, 1.0, 2.0, 10.0] {
+        let direction = vec3(1.0, -2.0, 0.5).normalize();
+        let sample = light
+            .sample(POSITION + distance * direction, &mut rng)
+            .unwrap();
+        assert!((sample.wi + direction).magnitude() < 1e-12);
+        assert!((sample.distance - distance).abs() < 1e-12);
+        assert!(
+            (sample.irradiance.0 * distance * distance - light.intensity.0).magnitude() < 1e-12
+        );
+    }
+    assert!(light.sample(POSITION, &mut rng).is_none());
+
+    let power = power_through_sphere(&light);
+    assert!((light.power() - power).abs() < 1e-9 * power);
+    assert!((light.power() - 4.0 * Float::PI() * light.intensity.luminance()).abs() < 1e-12);
+}
+
+#[test]
+fn spot_light_fades_out_across_its_cone() {
+    let light = spot(30.0, 10.0, Vec::new());
+    let mut rng = MyRng::seed_from_u64(1);
+
+    let irradiance_at = |angle: Float, distance: Float, rng: &mut MyRng| {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        light
+            .sample(POSITION + distance * vec3(sin, -cos, 0.0), rng)
+            .map_or(0.0, |sample| sample.irradiance.luminance())
+    };
+
+    // Full intensity inside the falloff angle, with the inverse square law.
+    for &angle in &[0.0, 10.0, 19.9] {
+        assert!((irradiance_at(angle, 1.0, &mut rng) - 2.0).abs() < 1e-12);
+        assert!((irradiance_at(angle, 4.0, &mut rng) - 2.0 / 16.0).abs() < 1e-12);
+    }
+    // Fading smoothly and monotonically to nothing at the edge.
+    let mut previous = 2.0;
+    for i in 1..100 {
+        let irradiance = irradiance_at(20.0 + i as Float / 10.0, 1.0, &mut rng);
+        assert!(irradiance > 0.0 && irradiance <= previous);
+        previous = irradiance;
+    }
+    for &angle in &[30.0, 45.0, 90.0, 180.0] {
+        assert_eq!(irradiance_at(angle, 1.0, &mut rng), 0.0);
+    }
+}
+
+#[test]
+fn spot_light_power_integrates_its_falloff() {
+    let intensity = 2.0;
+    let cases = [
+        // A sharp cone covers `2π (1 - cos θ)` steradians.
+        (spot(60.0, 0.0, Vec::new()), 2.0 * Float::PI() * 0.5),
+        // The smooth step averages one half over the band where it fades.
+        (spot(60.0, 30.0, Vec::new()), {
+            let (cos_total, cos_start): (Float, Float) = (0.5, (3.0 as Float).sqrt() / 2.0);
+            2.0 * Float::PI() * ((1.0 - cos_start) + (cos_start - cos_total) / 2.0)
+        }),
+        // A constant profile scales the whole cone.
+        (spot(60.0, 0.0, vec![0.5]), Float::PI() * 0.5),
+        // Open to the whole sphere, with a profile falling linearly from the axis to the back,
+        // which averages one half.
+        (spot(180.0, 0.0, vec![1.0, 0.5, 0.0]), 2.0 * Float::PI()),
+    ];
+
+    for (light, solid_angle) in cases.iter() {
+        let expected = solid_angle * intensity;
+        assert!(
+            (light.power() - expected).abs() < 0.01 * expected,
+            "{:?} has power {}, not {}",
+            light,
+            light.power(),
+            expected
+        );
+        let through_sphere = power_through_sphere(light);
+        assert!(
+            (light.power() - through_sphere).abs() < 0.02 * expected,
+            "{:?} has power {}, but {} reaches a sphere around it",
+            light,
+            light.power(),
+            through_sphere
+        );
+    }
+}
+
+#[test]
+fn distant_light_is_the_same_everywhere_and_comes_from_its_disk() {
+    let direction = vec3(0.3, -1.0, 0.2).normalize();
+    let angular_diameter = (10.0 as Float).to_radians();
+    let light = DistantLight {
+        direction,
+        irradiance: Color(vec3(5.0, 4.0, 3.0)),
+        angular_diameter,
+    };
+    let mut rng = MyRng::seed_from_u64(1);
+
+    for _ in 0..1000 {
+        let origin = point3(0.0, 0.0, 0.0) + 100.0 * random_in_unit_sphere(&mut rng);
+        let sample = light.sample(origin, &mut rng).unwrap();
+        assert_eq!(sample.irradiance.0, light.irradiance.0);
+        assert_eq!(sample.distance, Float::INFINITY);
+        assert!((sample.wi.magnitude() - 1.0).abs() < 1e-12);
+        assert!(sample.wi.dot(-direction) >= (angular_diameter / 2.0).cos() - 1e-12);
+    }
+}
@@ -0,0 +1,218 @@
+//! The integrators are different estimators of the same image, so they must agree on average.
+
+use rand::SeedableRng;
+use raytracing_1w::{
+    bvh::SplitMethod,
+    render::{Integrator, MisHeuristic},
+    scene::load_scene,
+    Float, MyRng, Renderer, Scene,
+};
+
+mod common;
+
+use common::write_files;
+
+/// The Cornell box with two white boxes, a larger and dimmer light and a camera that does not
+/// see the light. This keeps the variance low enough to compare estimates from about a thousand
+/// paths per pixel, even when only BSDFs are sampled.
+const CORNELL_BOX: &str = r#"
+[camera]
+look_from = [278.0, 278.0, -800.0]
+look_at = [278.0, 150.0, 0.0]
+vfov = 30.0
+
+[materials.red]
+type = "lambertian"
+albedo = [0.65, 0.05, 0.05]
+
+[materials.white]
+type = "lambertian"
+albedo = [0.73, 0.73, 0.73]
+
+[materials.green]
+type = "lambertian"
+albedo = [0.12, 0.45, 0.15]
+
+[materials.light]
+type = "diffuse_light"
+emit = [2.0, 2.0, 2.0]
+
+[[objects]]
+type = "yz_rect"
+y0 = 0.0
+y1 = 555.0
+z0 = 0.0
+z1 = 555.0
+k = 555.0
+material = "green"
+
+[[objects]]
+type = "yz_rect"
+y0 = 0.0
+y1 = 555.0
+z0 = 0.0
+z1 = 555.0
+k = 0.0
+material = "red"
+
+[[objects]]
+type = "xz_rect"
+x0 = 113.0
+x1 = 443.0
+z0 = 127.0
+z1 = 432.0
+k = 554.0
+material = "light"
+flip_face = true
+
+[[objects]]
+type = "xz_rect"
+x0 = 0.0
+x1 = 555.0
+z0 = 0.0
+z1 = 555.0
+k = 0.0
+material = "white"
+
+[[objects]]
+type = "xz_rect"
+x0 = 0.0
+x1 = 555.0
+z0 = 0.0
+z1 = 555.0
+k = 555.0
+material = "white"
+
+[[objects]]
+type = "xy_rect"
+x0 = 0.0
+x1 = 555.0
+y0 = 0.0
+y1 = 555.0
+k = 555.0
+material = "white"
+
+[[objects]]
+type = "box"
+min = [0.0, 0.0, 0.0]
+max = [165.0, 330.0, 165.0]
+material = "white"
+transform = [{ rotate_y = 15.0 }, { translate = [265.0, 0.0, 295.0] }]
+
+[[objects]]
+type = "box"
+min = [0.0, 0.0, 0.0]
+max = [165.0, 165.0, 165.0]
+material = "white"
+transform = [{ rotate_y = -18.0 }, { translate = [130.0, 0.0, 65.0] }]
+"#;
+
+/// Loads [`CORNELL_BOX`] from a directory named after the test.
+fn cornell_box(test: &str) -> Scene {
+    let dir = write_files(test, &[("cornell_box.toml", CORNELL_BOX)]);
+    let mut rng = MyRng::seed_from_u64(0);
+    load_scene(dir.join("cornell_box.toml"), SplitMethod::Median, &mut rng).unwrap()
+}
+
+/// Mean radiance of a small render, per channel.
+fn mean(scene: &Scene, integrator: Integrator, roulette_depth: usize) -> [Float; 3] {
+    let renderer = Renderer {
+        image_width: 8,
+        image_height: 8,
+        samples_per_pixel: 1024,
+        max_depth: 8,
+        seed: 1,
+        integrator,
+        roulette_depth,
+    };
+    let image = renderer.render(scene);
+    let mut sum = [0.0; 3];
+    for pixel in &image.pixels {
+        for (sum, c) in sum.iter_mut().zip(pixel.to_rgb32f().iter()) {
+            *sum += *c as Float;
+        }
+    }
+    sum.map(|sum| sum / image.pixels.len() as Float)
+}
+
+/// Checks that every channel of `a` is within `tolerance`, relative, of `b`.
+fn assert_close(a: [Float; 3], b: [Float; 3], tolerance: Float) {
+    for i in 0..3 {
+        assert!(
+            (a[i] - b[i]).abs() <= tolerance * b[i],
+            "{:?} is not within {} of {:?}",
+            a,
+            tolerance,
+            b
+        );
+    }
+}
+
+#[test]
+fn path_tracing_matches_recursive_mis() {
+    let scene = cornell_box("path_tracing_matches_recursive_mis");
+    let mis = mean(&scene, Integrator::Mis(MisHeuristic::Power), usize::MAX);
+
+    for roulette_depth in [0, 3, usize::MAX].iter() {
+        let path = mean(
+            &scene,
+            Integrator::Path(MisHeuristic::Power),
+            *roulette_depth,
+        );
+        assert_close(path, mis, 0.03);
+    }
+}
+
+#[test]
+fn bsdf_sampling_alone_matches_mis() {
+    let scene = cornell_box("bsdf_sampling_alone_matches_mis");
+    let mis = mean(&scene, Integrator::Mis(MisHeuristic::Power), usize::MAX);
+
+    // Without lights, the renderer falls back to `path_trace` without light sampling for
+    // `Path` and to `ray_color_without_light_objects` for the recursive integrators.
+    let mut no_lights = cornell_box("bsdf_sampling_alone_matches_mis");
+    no_lights.lights = None;
+    for (integrator, roulette_depth) in [
+        (Integrator::Path(MisHeuristic::Power), 0),
+        (Integrator::Mis(MisHeuristic::Power), usize::MAX),
+    ]
+    .iter()
+    {
+        assert_close(mean(&no_lights, *integrator, *roulette_depth), mis, 0.08);
+    }
+}
+
+#[test]
+fn mis_weights_of_both_strategies_sum_to_one() {
+    let pdfs = [1e-6, 0.01, 0.3, 1.0, 2.5, 40.0, 1e6];
+    for heuristic in [MisHeuristic::Balance, MisHeuristic::Power].iter() {
+        for &light_pdf in &pdfs {
+            for &bsdf_pdf in &pdfs {
+                let sum =
+                    heuristic.weight(light_pdf, bsdf_pdf) + heuristic.weight(bsdf_pdf, light_pdf);
+                assert!(
+                    (sum - 1.0).abs() < 1e-12,
+                    "{:?} weights for {} and {} sum to {}",
+                    heuristic,
+                    light_pdf,
+                    bsdf_pdf,
+                    sum
+                );
+            }
+        }
+        // A strategy that cannot produce the sample takes none of it.
+        assert_eq!(heuristic.weight(0.0, 1.0), 0.0);
+        assert_eq!(heuristic.weight(1.0, 0.0), 1.0);
+    }
+}
+
+#[test]
+fn mis_matches_the_mixture() {
+    let scene = cornell_box("mis_matches_the_mixture");
+    let mixture = mean(&scene, Integrator::Mixture, usize::MAX);
+
+    for heuristic in [MisHeuristic::Balance, MisHeuristic::Power].iter() {
+        let mis = mean(&scene, Integrator::Mis(*heuristic), usize::MAX);
+        assert_close(mis, mixture, 0.03);
+    }
+}
@@ -0,0 +1,287 @@
+//! Finding the lights of a scene.
+
+use std::sync::Arc;
+
+use cgmath::{point3, vec3, InnerSpace, Point3};
+use rand::SeedableRng;
+use raytracing_1w::{
+    aarect::XZRect,
+    bvh::SplitMethod,
+    color::Color,
+    hittable::Hittable,
+    light::{emitted_power, LightList, LightsBuilder},
+    material::{DiffuseLight, Material},
+    math::random_in_unit_sphere,
+    scene::load_scene,
+    sphere::Sphere,
+    texture::{CheckerTexture, SolidColor},
+    Float, MyRng,
+};
+
+mod common;
+
+use common::write_files;
+
+const HEADER: &str = r#"
+[camera]
+look_from = [0.0, 0.0, -10.0]
+look_at = [0.0, 0.0, 0.0]
+vfov = 40.0
+
+[materials.white]
+type = "lambertian"
+albedo = [0.5, 0.5, 0.5]
+
+[materials.light]
+type = "diffuse_light"
+emit = [4.0, 4.0, 4.0]
+"#;
+
+#[test]
+fn emissive_obj_meshes_are_lights() {
+    let obj = "\
+mtllib lamp.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+usemtl shade
+f 1 2 3
+usemtl bulb
+f 1 3 4
+";
+    let mtl = "\
+newmtl shade
+Kd 0.5 0.5 0.5
+newmtl bulb
+Kd 0 0 0
+Ke 10 10 10
+";
+    let scene = format!(
+        "{}\n[[objects]]\ntype = \"obj\"\npath = \"lamp.obj\"\ntransform = [{{ translate = [0.0, 0.0, 2.0] }}]\n",
+        HEADER
+    );
+    let dir = write_files(
+        "emissive_obj_meshes_are_lights",
+        &[("lamp.obj", obj), ("lamp.mtl", mtl), ("scene.toml", &scene)],
+    );
+    let mut rng = MyRng::seed_from_u64(1);
+    let scene = load_scene(dir.join("scene.toml"), SplitMethod::Median, &mut rng).unwrap();
+
+    let lights = scene.lights.expect("the bulb is a light");
+    assert_eq!(lights.len(), 1);
+
+    // Only the bulb's triangle, moved with the mesh, is sampled.
+    let origin = point3(0.0, 0.0, -1.0);
+    let towards_bulb = point3(0.25, 0.75, 2.0) - origin;
+    let towards_shade = point3(0.75, 0.25, 2.0) - origin;
+    assert!(lights.pdf_value(origin, towards_bulb, 0.0, &mut rng) > 0.0);
+    assert_eq!(lights.pdf_value(origin, towards_shade, 0.0, &mut rng), 0.0);
+}
+
+#[test]
+fn instanced_emitters_are_lights() {
+    let scene = format!(
+        r#"{}
+[[geometry.lamp]]
+type = "sphere"
+center = [0.0, 1.0, 0.0]
+radius = 0.5
+material = "light"
+
+[[geometry.lamp]]
+type = "box"
+min = [-0.2, -1.0, -0.2]
+max = [0.2, 0.5, 0.2]
+material = "white"
+
+[[geometry.pair]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = 0.5
+material = "white"
+
+[[geometry.pair]]
+type = "sphere"
+center = [2.0, 0.0, 0.0]
+radius = 0.5
+material = "white"
+
+[[objects]]
+type = "instance"
+geometry = "lamp"
+transform = [{{ translate = [-3.0, 0.0, 0.0] }}]
+
+[[objects]]
+type = "instance"
+geometry = "lamp"
+transform = [{{ translate = [3.0, 0.0, 0.0] }}]
+
+[[objects]]
+type = "instance"
+geometry = "pair"
+material = "light"
+transform = [{{ translate = [0.0, -3.0, 0.0] }}]
+"#,
+        HEADER
+    );
+    let dir = write_files("instanced_emitters_are_lights", &[("scene.toml", &scene)]);
+    let mut rng = MyRng::seed_from_u64(1);
+    let scene = load_scene(dir.join("scene.toml"), SplitMethod::Median, &mut rng).unwrap();
+
+    // One bulb per lamp, and both spheres of the pair through the instance's material.
+    let lights = scene.lights.expect("the instances contain lights");
+    assert_eq!(lights.len(), 4);
+
+    let origin = point3(0.0, 0.0, -5.0);
+    for center in [
+        point3(-3.0, 1.0, 0.0),
+        point3(3.0, 1.0, 0.0),
+        point3(0.0, -3.0, 0.0),
+        point3(2.0, -3.0, 0.0),
+    ] {
+        assert!(lights.pdf_value(origin, center - origin, 0.0, &mut rng) > 0.0);
+    }
+    assert_eq!(
+        lights.pdf_value(origin, vec3(0.0, 1.0, 5.0), 0.0, &mut rng),
+        0.0
+    );
+}
+
+fn emitting(radiance: Float) -> Arc<Box<dyn Material>> {
+    Arc::new(Box::new(DiffuseLight::new(SolidColor {
+        color_value: Color(vec3(radiance, radiance, radiance)),
+    })))
+}
+
+fn sphere(center: Point3<Float>, radius: Float, radiance: Float) -> Box<dyn Hittable> {
+    Box::new(Sphere {
+        center,
+        radius,
+        material: emitting(radiance),
+    })
+}
+
+#[test]
+fn lights_are_picked_in_proportion_to_their_weight() {
+    let lights = LightList::new(vec![
+        (Arc::from(sphere(point3(0.0, 0.0, 0.0), 1.0, 1.0)), 1.0),
+        (Arc::from(sphere(point3(3.0, 0.0, 0.0), 1.0, 1.0)), 3.0),
+        (Arc::from(sphere(point3(6.0, 0.0, 0.0), 1.0, 1.0)), 6.0),
+    ]);
+
+    for (i, expected) in [0.1, 0.3, 0.6].iter().enumerate() {
+        assert!((lights.probability(i) - expected).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn discovered_lights_are_weighed_by_power() {
+    let mut builder = LightsBuilder::default();
+    builder.discover(sphere(point3(0.0, 0.0, 0.0), 1.0, 1.0));
+    builder.discover(sphere(point3(3.0, 0.0, 0.0), 1.0, 3.0));
+    // Not emissive, so not a light.
+    builder.discover(Box::new(Sphere {
+        center: point3(6.0, 0.0, 0.0),
+        radius: 1.0,
+        material: Arc::new(Box::new(())),
+    }));
+    // Added lights weigh as much as the average discovered one.
+    builder.add(sphere(point3(9.0, 0.0, 0.0), 1.0, 0.0));
+    let lights = builder.build().unwrap();
+
+    assert_eq!(lights.len(), 3);
+    for (i, expected) in [1.0 / 6.0, 0.5, 1.0 / 3.0].iter().enumerate() {
+        assert!(
+            (lights.probability(i) - expected).abs() < 1e-12,
+            "light {} is picked with probability {}",
+            i,
+            lights.probability(i)
+        );
+    }
+}
+
+#[test]
+fn light_list_pdf_integrates_to_one() {
+    const SAMPLES: usize = 200_000;
+
+    let mut builder = LightsBuilder::default();
+    builder.discover(sphere(point3(0.0, 0.0, 3.0), 1.5, 1.0));
+    builder.discover(sphere(point3(-2.0, 1.0, -2.0), 1.0, 4.0));
+    builder.discover(Box::new(XZRect {
+        x0: -2.0,
+        x1: 2.0,
+        z0: -2.0,
+        z1: 2.0,
+        k: -1.5,
+        material: emitting(2.0),
+    }));
+    let lights = builder.build().unwrap();
+    let origin = point3(0.5, 0.0, 0.0);
+    let mut rng = MyRng::seed_from_u64(1);
+
+    let sum: Float = (0..SAMPLES)
+        .map(|_| {
+            let v = random_in_unit_sphere(&mut rng).normalize();
+            lights.pdf_value(origin, v, 0.0, &mut rng)
+        })
+        .sum();
+    let integral = sum / SAMPLES as Float * 4.0 * std::f64::consts::PI;
+    assert!((integral - 1.0).abs() < 0.02, "{}", integral);
+
+    // Every sampled direction has a density.
+    for _ in 0..1000 {
+        let v = lights.random(origin, 0.0, &mut rng);
+        assert!(lights.pdf_value(origin, v, 0.0, &mut rng) > 0.0);
+    }
+}
+
+#[test]
+fn power_follows_textures_over_the_surface() {
+    // Black at the origin, where the checker's sines vanish, and white on half the surface.
+    let checker = || -> Arc<Box<dyn Material>> {
+        Arc::new(Box::new(DiffuseLight::new(CheckerTexture {
+            odd: SolidColor {
+                color_value: Color(vec3(2.0, 2.0, 2.0)),
+            },
+            even: SolidColor {
+                color_value: Color(vec3(0.0, 0.0, 0.0)),
+            },
+        })))
+    };
+    let objects: [(Box<dyn Hittable>, Float); 2] = [
+        (
+            Box::new(Sphere {
+                center: point3(0.0, 0.0, 0.0),
+                radius: 1.0,
+                material: checker(),
+            }),
+            4.0 * std::f64::consts::PI,
+        ),
+        (
+            Box::new(XZRect {
+                x0: -1.0,
+                x1: 1.0,
+                z0: -1.0,
+                z1: 1.0,
+                k: 0.3,
+                material: checker(),
+            }),
+            4.0,
+        ),
+    ];
+
+    for (object, area) in objects {
+        let expected = std::f64::consts::PI * area;
+        let power = emitted_power(object.as_ref());
+        assert!(
+            (power - expected).abs() < 0.15 * expected,
+            "power {} is not about {}",
+            power,
+            expected
+        );
+        let mut builder = LightsBuilder::default();
+        builder.discover(object);
+        assert_eq!(builder.build().map(|lights| lights.len()), Some(1));
+    }
+}
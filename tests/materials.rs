@@ -0,0 +1,264 @@
+//! Scattering off single surfaces.
+
+use std::sync::Arc;
+
+use cgmath::{point3, vec3, EuclideanSpace, InnerSpace, Point3, Vector3};
+use rand::SeedableRng;
+use raytracing_1w::{
+    color::Color,
+    hittable::{HitRecord, Hittable},
+    material::{BsdfSample, ComplexIor, Conductor, Dielectric, Material, RoughDielectric},
+    microfacet::Ggx,
+    principled::Principled,
+    ray::Ray,
+    sphere::Sphere,
+    texture::{SolidColor, Texture},
+    Float, MyRng,
+};
+
+/// A unit sphere at the origin made of `material`.
+fn sphere(material: Box<dyn Material>) -> Sphere {
+    Sphere {
+        center: point3(0.0, 0.0, 0.0),
+        radius: 1.0,
+        material: Arc::new(material),
+    }
+}
+
+/// Where the ray from `origin` along `direction` meets `object`.
+fn hit<'a>(
+    object: &'a Sphere,
+    origin: Point3<Float>,
+    direction: Vector3<Float>,
+    rng: &mut MyRng,
+) -> HitRecord<'a> {
+    let ray = Ray {
+        origin,
+        direction,
+        time: 0.0,
+    };
+    object
+        .hit(&ray, 0.001, Float::INFINITY, rng)
+        .expect("the ray meets the sphere")
+}
+
+/// Samples the material where the ray from `origin` along `direction` meets `object`, and
+/// tells whether the sample crosses the surface.
+fn sample(
+    object: &Sphere,
+    origin: Point3<Float>,
+    direction: Vector3<Float>,
+    rng: &mut MyRng,
+) -> Option<(BsdfSample, bool)> {
+    let hit_record = hit(object, origin, direction, rng);
+    let sample = hit_record
+        .material
+        .sample(&hit_record, -direction.normalize(), rng)?;
+    let outward = hit_record.position.to_vec();
+    let transmitted = sample.wi.dot(outward).signum() == direction.dot(outward).signum();
+    Some((sample, transmitted))
+}
+
+/// Rays meeting the unit sphere from outside and from inside, at angles where light can leave.
+fn rays() -> Vec<(Point3<Float>, Vector3<Float>)> {
+    let mut rays = Vec::new();
+    for &offset in &[0.0, 0.3, 0.6] {
+        rays.push((point3(offset, 0.0, -3.0), vec3(0.0, 0.0, 1.0)));
+        rays.push((point3(offset, 0.0, 0.0), vec3(0.0, 0.0, 1.0)));
+    }
+    rays
+}
+
+fn smooth_rough_dielectric() -> Sphere {
+    sphere(Box::new(RoughDielectric {
+        ir: 1.5,
+        roughness: SolidColor {
+            color_value: Color(vec3(0.0, 0.0, 0.0)),
+        },
+    }))
+}
+
+#[test]
+fn smooth_rough_dielectric_transmits_like_dielectric() {
+    let mut rng = MyRng::seed_from_u64(1);
+    let smooth = sphere(Box::new(Dielectric::new(1.5)));
+    let rough = smooth_rough_dielectric();
+
+    for (origin, direction) in rays() {
+        let mut transmitted = |object: &Sphere| {
+            (0..200)
+                .filter_map(|_| sample(object, origin, direction, &mut rng))
+                .find(|(_, transmitted)| *transmitted)
+                .expect("some light is transmitted")
+                .0
+        };
+        let expected = transmitted(&smooth);
+        let actual = transmitted(&rough);
+
+        assert!(actual.is_delta);
+        assert!((actual.wi - expected.wi).magnitude() < 1e-9);
+        assert_eq!(actual.weight.0, expected.weight.0);
+    }
+}
+
+#[test]
+fn smooth_rough_dielectric_transmits_as_much_as_dielectric() {
+    const SAMPLES: usize = 100_000;
+
+    let mut rng = MyRng::seed_from_u64(2);
+    // At normal incidence the Schlick approximation `Dielectric` uses is exact.
+    for (origin, direction) in [
+        (point3(0.0, 0.0, -3.0), vec3(0.0, 0.0, 1.0)),
+        (point3(0.0, 0.0, 0.0), vec3(0.0, 0.0, 1.0)),
+    ] {
+        let mut throughput = |object: &Sphere| {
+            let sum: Float = (0..SAMPLES)
+                .filter_map(|_| sample(object, origin, direction, &mut rng))
+                .filter(|(_, transmitted)| *transmitted)
+                .map(|(sample, _)| sample.weight.0.x)
+                .sum();
+            sum / SAMPLES as Float
+        };
+        let expected = throughput(&sphere(Box::new(Dielectric::new(1.5))));
+        let actual = throughput(&smooth_rough_dielectric());
+
+        assert!((expected - 0.96).abs() < 0.005, "{}", expected);
+        assert!(
+            (actual - expected).abs() < 0.005,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+}
+
+/// Integrates `f` over all directions with the midpoint rule, on a grid that is uniform in the
+/// cosine of the polar angle and in the azimuth, so every cell spans the same solid angle. The
+/// poles, where cells are coarsest, lie along the y axis, away from the lobes of the test rays.
+fn integrate_over_sphere(f: impl Fn(Vector3<Float>) -> Float) -> Float {
+    const Y_STEPS: usize = 400;
+    const PHI_STEPS: usize = 800;
+
+    let mut sum = 0.0;
+    for i in 0..Y_STEPS {
+        let y = -1.0 + 2.0 * (i as Float + 0.5) / Y_STEPS as Float;
+        let r = (1.0 - y * y).sqrt();
+        for j in 0..PHI_STEPS {
+            let phi = 2.0 * std::f64::consts::PI * (j as Float + 0.5) / PHI_STEPS as Float;
+            sum += f(vec3(r * phi.cos(), y, r * phi.sin()));
+        }
+    }
+    sum * 4.0 * std::f64::consts::PI / (Y_STEPS * PHI_STEPS) as Float
+}
+
+/// Checks, at a few angles of incidence on a sphere of `material`, that every sample's density
+/// and weight agree with `pdf` and `eval`, and that `pdf` integrates to the share of samples
+/// that succeed.
+fn assert_sampling_matches_pdf(name: &str, material: Box<dyn Material>) {
+    const SAMPLES: usize = 100_000;
+
+    let mut rng = MyRng::seed_from_u64(3);
+    let object = sphere(material);
+    for &offset in &[0.0, 0.5, 0.9] {
+        let direction = vec3(0.0, 0.0, 1.0);
+        let hit_record = hit(&object, point3(offset, 0.3, -3.0), direction, &mut rng);
+        let material = hit_record.material;
+        let wo = -direction;
+
+        let mut sampled = 0;
+        for _ in 0..SAMPLES {
+            let sample = match material.sample(&hit_record, wo, &mut rng) {
+                Some(sample) if !sample.is_delta => sample,
+                _ => continue,
+            };
+            sampled += 1;
+            let pdf = material.pdf(&hit_record, wo, sample.wi);
+            assert!(
+                sample.pdf > 0.0 && (sample.pdf - pdf).abs() <= 1e-6 * pdf,
+                "{}: sampled with density {} but pdf is {}",
+                name,
+                sample.pdf,
+                pdf
+            );
+            let eval = material.eval(&hit_record, wo, sample.wi).0 / pdf;
+            for i in 0..3 {
+                assert!(
+                    (sample.weight.0[i] - eval[i]).abs() <= 1e-6 * eval[i].max(1e-3),
+                    "{}: weight {:?} but eval / pdf is {:?}",
+                    name,
+                    sample.weight,
+                    eval
+                );
+            }
+        }
+
+        let integral = integrate_over_sphere(|wi| material.pdf(&hit_record, wo, wi));
+        let expected = sampled as Float / SAMPLES as Float;
+        assert!(
+            (integral - expected).abs() < 0.01,
+            "{} at offset {}: pdf integrates to {}, but {} of the samples succeed",
+            name,
+            offset,
+            integral,
+            expected
+        );
+    }
+}
+
+#[test]
+fn ggx_conductor_samples_match_its_pdf() {
+    let materials: [(&str, Ggx); 3] = [
+        ("isotropic", Ggx::isotropic(0.4)),
+        ("anisotropic", Ggx::anisotropic(0.5, 0.8)),
+        ("rough", Ggx::isotropic(1.0)),
+    ];
+    for (name, distribution) in materials.iter() {
+        assert_sampling_matches_pdf(
+            name,
+            Box::new(Conductor {
+                ior: ComplexIor::GOLD,
+                distribution: *distribution,
+            }),
+        );
+    }
+}
+
+fn grey(value: Float) -> Arc<dyn Texture> {
+    Arc::new(SolidColor {
+        color_value: Color(vec3(value, value, value)),
+    })
+}
+
+#[test]
+fn principled_samples_match_its_pdf() {
+    let base_color: Arc<dyn Texture> = Arc::new(SolidColor {
+        color_value: Color(vec3(0.8, 0.3, 0.1)),
+    });
+    let plastic = Principled::new(base_color.clone());
+    let brushed_metal = Principled {
+        metallic: grey(1.0),
+        roughness: grey(0.4),
+        anisotropy: grey(0.8),
+        ..Principled::new(base_color.clone())
+    };
+    let coated_cloth = Principled {
+        roughness: grey(0.7),
+        sheen: grey(1.0),
+        clearcoat: grey(1.0),
+        ..Principled::new(base_color.clone())
+    };
+    let frosted_glass = Principled {
+        roughness: grey(0.4),
+        transmission: grey(0.9),
+        ..Principled::new(base_color)
+    };
+
+    for (name, material) in [
+        ("plastic", plastic),
+        ("brushed metal", brushed_metal),
+        ("coated cloth", coated_cloth),
+        ("frosted glass", frosted_glass),
+    ] {
+        assert_sampling_matches_pdf(name, Box::new(material));
+    }
+}
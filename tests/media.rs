@@ -0,0 +1,213 @@
+//! Light is absorbed along every segment a path spends inside tinted glass.
+
+use std::sync::Arc;
+
+use cgmath::{point3, vec3, Deg};
+use rand::SeedableRng;
+use raytracing_1w::{
+    color::Color,
+    constant_medium::ConstantMedium,
+    hittable::{Hittable, RotateY, Translate},
+    material::{Dielectric, DiffuseLight, Material},
+    ray::Ray,
+    render::{path_trace, ray_color, ray_color_mis, ray_color_without_light_objects, MisHeuristic},
+    sphere::Sphere,
+    texture::SolidColor,
+    Float, MyRng,
+};
+
+const EMIT: Float = 2.0;
+/// Light left after travelling a unit distance through the glass.
+const TINT: [Float; 3] = [0.5, 0.25, 0.8];
+
+/// Glass with an index of one, so a ray through its center is never reflected.
+fn tinted_glass() -> Arc<Box<dyn Material>> {
+    Arc::new(Box::new(Dielectric::tinted(
+        1.0,
+        Color(vec3(TINT[0], TINT[1], TINT[2])),
+        1.0,
+    )))
+}
+
+fn emissive() -> Arc<Box<dyn Material>> {
+    Arc::new(Box::new(DiffuseLight::new(SolidColor {
+        color_value: Color(vec3(EMIT, EMIT, EMIT)),
+    })))
+}
+
+/// Every integrator's estimate for `ray`.
+fn radiances(
+    world: &[Box<dyn Hittable>],
+    lights: &[Box<dyn Hittable>],
+    ray: &Ray,
+    background: Color,
+) -> Vec<Color> {
+    let mut rng = MyRng::seed_from_u64(1);
+    vec![
+        ray_color(ray, background, world, lights, &[], 10, &mut rng),
+        ray_color_mis(
+            ray,
+            background,
+            world,
+            lights,
+            &[],
+            MisHeuristic::Power,
+            10,
+            &mut rng,
+        ),
+        path_trace(
+            ray,
+            background,
+            world,
+            Some(lights),
+            &[],
+            MisHeuristic::Power,
+            10,
+            10,
+            &mut rng,
+        ),
+        ray_color_without_light_objects(ray, background, world, &[], 10, &mut rng),
+    ]
+}
+
+fn assert_tinted(color: Color, scale: Float, distance: Float) {
+    for (i, tint) in TINT.iter().enumerate() {
+        let expected = scale * tint.powf(distance);
+        assert!(
+            (color.0[i] - expected).abs() < 1e-9,
+            "{:?} is not {} through {}",
+            color,
+            scale,
+            distance
+        );
+    }
+}
+
+#[test]
+fn light_from_an_object_inside_glass_is_absorbed() {
+    let world: Vec<Box<dyn Hittable>> = vec![
+        Box::new(Sphere {
+            center: point3(0.0, 0.0, 0.0),
+            radius: 2.0,
+            material: tinted_glass(),
+        }),
+        Box::new(Sphere {
+            center: point3(0.0, 0.0, 0.0),
+            radius: 0.5,
+            material: emissive(),
+        }),
+    ];
+    let lights: Vec<Box<dyn Hittable>> = vec![Box::new(Sphere {
+        center: point3(0.0, 0.0, 0.0),
+        radius: 0.5,
+        material: Arc::new(Box::new(())),
+    })];
+    let ray = Ray {
+        origin: point3(0.0, 0.0, -10.0),
+        direction: vec3(0.0, 0.0, 1.0),
+        time: 0.0,
+    };
+
+    for color in radiances(&world, &lights, &ray, Color(vec3(0.0, 0.0, 0.0))) {
+        assert_tinted(color, EMIT, 1.5);
+    }
+}
+
+#[test]
+fn transformed_glass_absorbs_light() {
+    let glass = Sphere {
+        center: point3(0.0, 0.0, 0.0),
+        radius: 1.0,
+        material: tinted_glass(),
+    };
+    let world: Vec<Box<dyn Hittable>> = vec![Box::new(Translate {
+        hittable: RotateY::new(glass, 0.0, 1.0, Deg(30.0)),
+        offset: vec3(3.0, 0.0, 0.0),
+    })];
+    // A light far behind the camera, which the ray never meets.
+    let lights: Vec<Box<dyn Hittable>> = vec![Box::new(Sphere {
+        center: point3(0.0, 0.0, -100.0),
+        radius: 1.0,
+        material: Arc::new(Box::new(())),
+    })];
+    let ray = Ray {
+        origin: point3(3.0, 0.0, -10.0),
+        direction: vec3(0.0, 0.0, 1.0),
+        time: 0.0,
+    };
+
+    for color in radiances(&world, &lights, &ray, Color(vec3(1.0, 1.0, 1.0))) {
+        assert_tinted(color, 1.0, 2.0);
+    }
+}
+
+#[test]
+fn scattering_in_smoke_inside_glass_keeps_the_glass_absorbing() {
+    // White smoke filling the middle of the glass, at least 1.5 from its surface.
+    let world: Vec<Box<dyn Hittable>> = vec![
+        Box::new(Sphere {
+            center: point3(0.0, 0.0, 0.0),
+            radius: 2.0,
+            material: tinted_glass(),
+        }),
+        Box::new(ConstantMedium::new(
+            Sphere {
+                center: point3(0.0, 0.0, 0.0),
+                radius: 0.5,
+                material: Arc::new(Box::new(())),
+            },
+            2.0,
+            Box::new(SolidColor {
+                color_value: Color(vec3(1.0, 1.0, 1.0)),
+            }),
+        )),
+    ];
+    let lights: Vec<Box<dyn Hittable>> = vec![Box::new(Sphere {
+        center: point3(0.0, 0.0, -100.0),
+        radius: 1.0,
+        material: Arc::new(Box::new(())),
+    })];
+    let ray = Ray {
+        origin: point3(0.0, 0.0, -10.0),
+        direction: vec3(0.0, 0.0, 1.0),
+        time: 0.0,
+    };
+
+    // Every path that gets out travels at least 1.5 through the glass on the way in and again
+    // on the way out, however often it scatters in the smoke. The integrators that follow only
+    // BSDF samples keep the throughput of a white path at one, so each sample is bounded.
+    let world: &[Box<dyn Hittable>] = &world;
+    let lights: &[Box<dyn Hittable>] = &lights;
+    let mut rng = MyRng::seed_from_u64(1);
+    let background = Color(vec3(1.0, 1.0, 1.0));
+    let mut escaped = 0;
+    for _ in 0..1000 {
+        let colors = [
+            path_trace(
+                &ray,
+                background,
+                world,
+                Some(lights),
+                &[],
+                MisHeuristic::Power,
+                10,
+                10,
+                &mut rng,
+            ),
+            ray_color_without_light_objects(&ray, background, world, &[], 10, &mut rng),
+        ];
+        for color in colors.iter() {
+            for (i, tint) in TINT.iter().enumerate() {
+                assert!(
+                    color.0[i] <= tint.powi(3) + 1e-9,
+                    "{:?} was not absorbed by the glass",
+                    color
+                );
+            }
+            if color.0 != vec3(0.0, 0.0, 0.0) {
+                escaped += 1;
+            }
+        }
+    }
+    assert!(escaped > 1000);
+}
@@ -0,0 +1,186 @@
+//! Sampling triangle meshes as lights.
+
+use std::sync::Arc;
+
+use cgmath::{point3, vec3, InnerSpace, Point3, Vector3};
+use rand::SeedableRng;
+use raytracing_1w::{
+    bvh::SplitMethod,
+    hittable::Hittable,
+    math::random_in_unit_sphere,
+    mesh::{Face, InvalidMesh, Mesh, TriangleMesh},
+    ray::Ray,
+    triangle::{intersect_triangle, triangle_area},
+    Float, MyRng,
+};
+
+/// A unit cube split into a grid of `n` by `n` quads on each side.
+fn grid_cube(n: usize) -> Mesh {
+    let mut mesh = Mesh::default();
+    let axes = [
+        (
+            vec3(1.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+            vec3(0.0, 0.0, 0.0),
+        ),
+        (
+            vec3(0.0, 1.0, 0.0),
+            vec3(1.0, 0.0, 0.0),
+            vec3(0.0, 0.0, 1.0),
+        ),
+        (
+            vec3(0.0, 0.0, 1.0),
+            vec3(1.0, 0.0, 0.0),
+            vec3(0.0, 0.0, 0.0),
+        ),
+        (
+            vec3(1.0, 0.0, 0.0),
+            vec3(0.0, 0.0, 1.0),
+            vec3(0.0, 1.0, 0.0),
+        ),
+        (
+            vec3(0.0, 1.0, 0.0),
+            vec3(0.0, 0.0, 1.0),
+            vec3(0.0, 0.0, 0.0),
+        ),
+        (
+            vec3(0.0, 0.0, 1.0),
+            vec3(0.0, 1.0, 0.0),
+            vec3(1.0, 0.0, 0.0),
+        ),
+    ];
+    for (a, b, offset) in axes {
+        let first = mesh.positions.len();
+        for i in 0..=n {
+            for j in 0..=n {
+                let (s, t) = (i as Float / n as Float, j as Float / n as Float);
+                mesh.positions
+                    .push(point3(0.0, 0.0, 0.0) + offset + a * s + b * t);
+            }
+        }
+        let index = |i: usize, j: usize| first + i * (n + 1) + j;
+        for i in 0..n {
+            for j in 0..n {
+                for positions in [
+                    [index(i, j), index(i + 1, j), index(i + 1, j + 1)],
+                    [index(i, j), index(i + 1, j + 1), index(i, j + 1)],
+                ] {
+                    mesh.faces.push(Face {
+                        positions,
+                        normals: None,
+                        uvs: None,
+                    });
+                }
+            }
+        }
+    }
+    mesh
+}
+
+/// The density of a direction, found by testing every face.
+fn face_scan_pdf(mesh: &Mesh, origin: Point3<Float>, v: Vector3<Float>) -> Float {
+    let vertices = |face: &Face| {
+        let [i0, i1, i2] = face.positions;
+        (mesh.positions[i0], mesh.positions[i1], mesh.positions[i2])
+    };
+    let total_area: Float = mesh
+        .faces
+        .iter()
+        .map(|face| {
+            let (v0, v1, v2) = vertices(face);
+            triangle_area(v0, v1, v2)
+        })
+        .sum();
+    let ray = Ray {
+        origin,
+        direction: v,
+        time: 0.0,
+    };
+    mesh.faces
+        .iter()
+        .filter_map(|face| {
+            let (v0, v1, v2) = vertices(face);
+            let (t, _, _) = intersect_triangle(&ray, v0, v1, v2, 0.001, Float::INFINITY)?;
+            let cosine = v
+                .normalize()
+                .dot((v1 - v0).cross(v2 - v0).normalize())
+                .abs();
+            Some(t * t * v.magnitude2() / (cosine * total_area))
+        })
+        .sum()
+}
+
+#[test]
+fn pdf_matches_a_scan_of_every_face() {
+    let mut rng = MyRng::seed_from_u64(1);
+    let mesh = grid_cube(4);
+    let light = TriangleMesh::new(
+        mesh.clone(),
+        Arc::new(Box::new(())),
+        SplitMethod::Median,
+        &mut rng,
+    )
+    .unwrap();
+    let origin = point3(2.0, 1.5, -1.0);
+
+    for _ in 0..1000 {
+        let v = light.random(origin, 0.0, &mut rng);
+        let pdf = light.pdf_value(origin, v, 0.0, &mut rng);
+        let expected = face_scan_pdf(&mesh, origin, v);
+        assert!(pdf > 0.0);
+        assert!(
+            (pdf - expected).abs() <= 1e-9 * expected,
+            "{} != {}",
+            pdf,
+            expected
+        );
+    }
+}
+
+#[test]
+fn pdf_integrates_to_one() {
+    const SAMPLES: usize = 200_000;
+
+    let mut rng = MyRng::seed_from_u64(2);
+    let light = TriangleMesh::new(
+        grid_cube(3),
+        Arc::new(Box::new(())),
+        SplitMethod::Median,
+        &mut rng,
+    )
+    .unwrap();
+    let origin = point3(1.5, 0.5, 0.2);
+
+    let sum: Float = (0..SAMPLES)
+        .map(|_| {
+            let v = random_in_unit_sphere(&mut rng).normalize();
+            light.pdf_value(origin, v, 0.0, &mut rng)
+        })
+        .sum();
+    let integral = sum / SAMPLES as Float * 4.0 * std::f64::consts::PI;
+    assert!((integral - 1.0).abs() < 0.03, "{}", integral);
+}
+
+#[test]
+fn empty_or_out_of_range_meshes_are_rejected() {
+    let mut rng = MyRng::seed_from_u64(3);
+    let mut new = |mesh: Mesh| {
+        TriangleMesh::new(
+            mesh,
+            Arc::new(Box::new(())),
+            SplitMethod::default(),
+            &mut rng,
+        )
+        .err()
+    };
+
+    assert_eq!(new(Mesh::default()), Some(InvalidMesh::Empty));
+
+    let mut mesh = grid_cube(1);
+    mesh.faces[3].positions[1] = mesh.positions.len();
+    assert_eq!(new(mesh), Some(InvalidMesh::InvalidFace { face: 3 }));
+
+    let mut mesh = grid_cube(1);
+    mesh.faces[5].uvs = Some([0, 0, 0]);
+    assert_eq!(new(mesh), Some(InvalidMesh::InvalidFace { face: 5 }));
+}
@@ -0,0 +1,182 @@
+//! Every segment of a light path must see moving objects at the time of its camera sample.
+
+use std::sync::Arc;
+
+use cgmath::{point3, vec3};
+use rand::SeedableRng;
+use raytracing_1w::{
+    aarect::{XYRect, XZRect},
+    color::Color,
+    hittable::{FlipFace, Hittable},
+    material::{DiffuseLight, Lambertian, Material, Metal},
+    moving_sphere::MovingSphere,
+    ray::Ray,
+    render::{ray_color, ray_color_without_light_objects},
+    texture::SolidColor,
+    Float, MyRng,
+};
+
+const EMIT: Float = 4.0;
+const ALBEDO: Float = 0.5;
+
+fn black() -> Color {
+    Color(vec3(0.0, 0.0, 0.0))
+}
+
+fn emissive() -> Arc<Box<dyn Material>> {
+    Arc::new(Box::new(DiffuseLight::new(SolidColor {
+        color_value: Color(vec3(EMIT, EMIT, EMIT)),
+    })))
+}
+
+/// A light far above the scene, only used for light sampling.
+fn lights() -> Vec<Box<dyn Hittable>> {
+    vec![Box::new(XZRect {
+        x0: -1.0,
+        x1: 1.0,
+        z0: -1.0,
+        z1: 1.0,
+        k: 100.0,
+        material: Arc::new(Box::new(())),
+    })]
+}
+
+/// A mirror in the z = 0 plane and an emissive sphere sweeping along x = -20..20 at z = -4
+/// during the shutter interval [0, 1]. The camera ray hits the mirror at the origin and
+/// reflects towards (2, 0, -4), where the sphere is at time 0.55.
+fn mirror_scene() -> Vec<Box<dyn Hittable>> {
+    let mirror: Arc<Box<dyn Material>> = Arc::new(Box::new(Metal {
+        albedo: Color(vec3(1.0, 1.0, 1.0)),
+        fuzz: 0.0,
+    }));
+
+    vec![
+        Box::new(XYRect {
+            x0: -50.0,
+            x1: 50.0,
+            y0: -50.0,
+            y1: 50.0,
+            k: 0.0,
+            material: mirror,
+        }),
+        Box::new(MovingSphere {
+            center0: point3(-20.0, 0.0, -4.0),
+            center1: point3(20.0, 0.0, -4.0),
+            time0: 0.0,
+            time1: 1.0,
+            radius: 0.5,
+            material: emissive(),
+        }),
+    ]
+}
+
+/// A diffuse floor and an inward-facing emissive sky sphere that rushes past and only surrounds
+/// the floor around time 0.5. Every bounce off the floor then reaches the sky.
+fn diffuse_scene() -> Vec<Box<dyn Hittable>> {
+    let floor: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian {
+        albedo: SolidColor {
+            color_value: Color(vec3(ALBEDO, ALBEDO, ALBEDO)),
+        },
+    }));
+
+    vec![
+        Box::new(XZRect {
+            x0: -1.0,
+            x1: 1.0,
+            z0: -1.0,
+            z1: 1.0,
+            k: 0.0,
+            material: floor,
+        }),
+        Box::new(FlipFace(MovingSphere {
+            center0: point3(-1e6, 0.0, 0.0),
+            center1: point3(1e6, 0.0, 0.0),
+            time0: 0.0,
+            time1: 1.0,
+            radius: 1000.0,
+            material: emissive(),
+        })),
+    ]
+}
+
+#[test]
+fn specular_bounce_sees_moving_object_at_camera_time() {
+    let world = mirror_scene();
+    let lights = lights();
+    let mut rng = MyRng::seed_from_u64(0);
+
+    for time in [0.0, 0.3, 0.55, 0.8, 1.0] {
+        let ray = Ray {
+            origin: point3(-4.0, 0.0, -8.0),
+            direction: vec3(1.0, 0.0, 2.0),
+            time,
+        };
+        let expected = if time == 0.55 { EMIT } else { 0.0 };
+
+        let color = ray_color(
+            &ray,
+            black(),
+            world.as_slice(),
+            lights.as_slice(),
+            &[],
+            4,
+            &mut rng,
+        );
+        assert_eq!(color.0, vec3(expected, expected, expected), "time {}", time);
+
+        let color =
+            ray_color_without_light_objects(&ray, black(), world.as_slice(), &[], 4, &mut rng);
+        assert_eq!(color.0, vec3(expected, expected, expected), "time {}", time);
+    }
+}
+
+#[test]
+fn diffuse_bounce_sees_moving_object_at_camera_time() {
+    let world = diffuse_scene();
+    let lights = lights();
+    let mut rng = MyRng::seed_from_u64(0);
+    let samples = 10_000;
+
+    for time in [0.2, 0.5, 0.8] {
+        let ray = Ray {
+            origin: point3(0.0, 1.0, 0.0),
+            direction: vec3(0.0, -1.0, 0.0),
+            time,
+        };
+        let expected = if time == 0.5 { ALBEDO * EMIT } else { 0.0 };
+
+        // Cosine-weighted bounces towards a uniform sky have no variance.
+        let color =
+            ray_color_without_light_objects(&ray, black(), world.as_slice(), &[], 2, &mut rng);
+        assert!(
+            (color.0.x - expected).abs() < 1e-9,
+            "time {}: {:?}",
+            time,
+            color
+        );
+
+        // Mixing in light sampling does, so average.
+        let mean = (0..samples)
+            .map(|_| {
+                ray_color(
+                    &ray,
+                    black(),
+                    world.as_slice(),
+                    lights.as_slice(),
+                    &[],
+                    2,
+                    &mut rng,
+                )
+                .0
+                .x
+            })
+            .sum::<Float>()
+            / samples as Float;
+        assert!(
+            (mean - expected).abs() < 0.05 * ALBEDO * EMIT,
+            "time {}: {}",
+            time,
+            mean
+        );
+    }
+}
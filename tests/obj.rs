@@ -0,0 +1,207 @@
+//! Reading Wavefront OBJ files and their MTL libraries.
+
+use std::path::PathBuf;
+
+use raytracing_1w::obj::{read_obj, ObjError};
+
+mod common;
+
+use common::write_files;
+
+const CUBE: &str = "\
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v 0 0 1
+v 1 0 1
+v 1 1 1
+v 0 1 1
+f 1 4 3 2
+f 5 6 7 8
+f 1 2 6 5
+f 2 3 7 6
+f 3 4 8 7
+f 4 1 5 8
+";
+
+#[test]
+fn closed_mesh_shares_vertices() {
+    let dir = write_files("closed_mesh_shares_vertices", &[("cube.obj", CUBE)]);
+    let meshes = read_obj(dir.join("cube.obj")).unwrap();
+
+    assert_eq!(meshes.len(), 1);
+    let mesh = &meshes[0].0;
+    assert_eq!(mesh.faces.len(), 12);
+    assert_eq!(mesh.positions.len(), 8);
+}
+
+#[test]
+fn meshes_keep_only_their_vertices_in_material_order() {
+    let obj = "\
+mtllib cube.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v 5 5 5
+f 1 2 3
+usemtl red
+f 1 3 4
+usemtl blue
+f 1 2 4
+usemtl red
+f 2 3 4
+";
+    let mtl = "\
+newmtl red
+Kd 1 0 0
+newmtl blue
+Kd 0 0 1
+";
+    let dir = write_files(
+        "meshes_keep_only_their_vertices_in_material_order",
+        &[("cube.obj", obj), ("cube.mtl", mtl)],
+    );
+    let meshes = read_obj(dir.join("cube.obj")).unwrap();
+
+    // The faces before any `usemtl`, then `blue` before `red`.
+    let counts: Vec<_> = meshes
+        .iter()
+        .map(|(mesh, _)| (mesh.faces.len(), mesh.positions.len()))
+        .collect();
+    assert_eq!(counts, [(1, 3), (1, 3), (2, 4)]);
+}
+
+#[test]
+fn polygons_are_fanned_with_negative_indices_uvs_and_normals() {
+    let obj = "\
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+f -4/1/1 -3/2/1 -2/3/1 -1/4/1
+";
+    let dir = write_files(
+        "polygons_are_fanned_with_negative_indices_uvs_and_normals",
+        &[("quad.obj", obj)],
+    );
+    let meshes = read_obj(dir.join("quad.obj")).unwrap();
+
+    assert_eq!(meshes.len(), 1);
+    let mesh = &meshes[0].0;
+    assert_eq!(mesh.positions.len(), 4);
+    assert_eq!(mesh.uvs.len(), 4);
+    assert_eq!(mesh.normals.len(), 1);
+    let faces: Vec<_> = mesh
+        .faces
+        .iter()
+        .map(|face| (face.positions, face.uvs, face.normals))
+        .collect();
+    assert_eq!(
+        faces,
+        [
+            ([0, 1, 2], Some([0, 1, 2]), Some([0, 0, 0])),
+            ([0, 2, 3], Some([0, 2, 3]), Some([0, 0, 0])),
+        ]
+    );
+}
+
+/// A file name and its contents.
+type File<'a> = (&'a str, &'a str);
+
+/// The line `read_obj` blames for the error it returns on the given files.
+fn error_line(test: &str, files: &[File]) -> (PathBuf, usize) {
+    let dir = write_files(test, files);
+    match read_obj(dir.join("bad.obj")).unwrap_err() {
+        ObjError::Parse { path, line, .. }
+        | ObjError::MissingMaterialLibrary { path, line, .. }
+        | ObjError::UnknownMaterial { path, line, .. } => {
+            (path.strip_prefix(&*dir).unwrap().to_path_buf(), line)
+        }
+        error => panic!("unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn errors_point_at_the_offending_line() {
+    let cases: [(&str, &[File], &str, usize); 7] = [
+        (
+            "obj_invalid_number",
+            &[("bad.obj", "v 0 0 0\nv 1 x 0\n")],
+            "bad.obj",
+            2,
+        ),
+        (
+            "obj_index_out_of_range",
+            &[("bad.obj", "v 0 0 0\nv 1 0 0\n# comment\nf 1 2 3\n")],
+            "bad.obj",
+            4,
+        ),
+        (
+            "obj_face_with_two_vertices",
+            &[("bad.obj", "v 0 0 0\nv 1 0 0\nf 1 2\n")],
+            "bad.obj",
+            3,
+        ),
+        (
+            "obj_missing_library",
+            &[("bad.obj", "v 0 0 0\n\nmtllib missing.mtl\n")],
+            "bad.obj",
+            3,
+        ),
+        (
+            "obj_unknown_material",
+            &[
+                ("bad.obj", "mtllib a.mtl\nusemtl red\nusemtl blue\n"),
+                ("a.mtl", "newmtl red\nKd 1 0 0\n"),
+            ],
+            "bad.obj",
+            3,
+        ),
+        (
+            "mtl_invalid_color",
+            &[
+                ("bad.obj", "mtllib a.mtl\n"),
+                ("a.mtl", "newmtl red\nKd 1 0\n"),
+            ],
+            "a.mtl",
+            2,
+        ),
+        (
+            "mtl_property_before_newmtl",
+            &[
+                ("bad.obj", "mtllib a.mtl\n"),
+                ("a.mtl", "# red\nKd 1 0 0\n"),
+            ],
+            "a.mtl",
+            2,
+        ),
+    ];
+
+    for (test, files, file, line) in cases.iter() {
+        assert_eq!(
+            error_line(test, files),
+            (PathBuf::from(file), *line),
+            "{}",
+            test
+        );
+    }
+}
+
+#[test]
+fn file_without_faces_is_an_error() {
+    let dir = write_files(
+        "file_without_faces_is_an_error",
+        &[("bad.obj", "v 0 0 0\n")],
+    );
+    assert!(matches!(
+        read_obj(dir.join("bad.obj")),
+        Err(ObjError::Empty { .. })
+    ));
+}
@@ -0,0 +1,139 @@
+//! Loading scene files and reporting where they are wrong.
+
+use rand::SeedableRng;
+use raytracing_1w::{
+    bvh::SplitMethod,
+    scene::{load_scene, SceneError},
+    MyRng, Scene,
+};
+
+mod common;
+
+use common::write_files;
+
+fn load(test: &str, scene: &str) -> Scene {
+    let dir = write_files(test, &[("scene.toml", scene)]);
+    let mut rng = MyRng::seed_from_u64(1);
+    load_scene(dir.join("scene.toml"), SplitMethod::Median, &mut rng).unwrap()
+}
+
+/// The error loading `scene` fails with.
+fn load_error(test: &str, scene: &str) -> SceneError {
+    let dir = write_files(test, &[("scene.toml", scene)]);
+    let mut rng = MyRng::seed_from_u64(1);
+    match load_scene(dir.join("scene.toml"), SplitMethod::Median, &mut rng) {
+        Err(error) => error,
+        Ok(_) => panic!("{}: the scene loaded", test),
+    }
+}
+
+/// A camera and a material, ending on line 9.
+const HEADER: &str = r#"[camera]
+look_from = [0.0, 0.0, -10.0]
+look_at = [0.0, 0.0, 0.0]
+vfov = 40.0
+
+[materials.white]
+type = "lambertian"
+albedo = [0.5, 0.5, 0.5]
+
+"#;
+
+const SPHERE: &str = r#"[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = "white"
+"#;
+
+/// The line a scene made of `HEADER` and `body` is invalid at.
+fn invalid_line(test: &str, body: &str) -> usize {
+    let scene = format!("{}{}", HEADER, body);
+    match load_error(test, &scene) {
+        SceneError::Invalid { line, .. } => line,
+        error => panic!("{}: unexpected error: {}", test, error),
+    }
+}
+
+#[test]
+fn valid_scene_loads() {
+    let scene = format!("{}{}", HEADER, SPHERE);
+    let scene = load("valid_scene_loads", &scene);
+    assert!(scene.lights.is_none());
+}
+
+#[test]
+fn invalid_values_point_at_their_table() {
+    let cases = [
+        (
+            "scene_invalid_material",
+            "[materials.shiny]\ntype = \"metal\"\nalbedo = [0.5, 0.5, 0.5]\nfuzz = 2.0\n\n",
+            10,
+        ),
+        (
+            "scene_unknown_material",
+            "[[objects]]\ntype = \"sphere\"\ncenter = [0.0, 0.0, 0.0]\nradius = 1.0\nmaterial = \"red\"\n",
+            10,
+        ),
+        (
+            "scene_unknown_geometry",
+            "[[objects]]\ntype = \"sphere\"\ncenter = [0.0, 0.0, 0.0]\nradius = 1.0\nmaterial = \"white\"\n\n[[objects]]\ntype = \"instance\"\ngeometry = \"lamp\"\n",
+            16,
+        ),
+        ("scene_without_objects", "", 1),
+        (
+            "scene_nan_keyframe",
+            &format!("{}keyframes = [\n    {{ time = 0.0 }},\n    {{ time = nan }},\n]\n", SPHERE),
+            17,
+        ),
+        (
+            "scene_infinite_keyframe",
+            &format!("{}keyframes = [{{ time = inf }}]\n", SPHERE),
+            15,
+        ),
+    ];
+
+    for (test, body, line) in cases.iter() {
+        assert_eq!(invalid_line(test, body), *line, "{}", test);
+    }
+
+    let scene = HEADER.replace("vfov = 40.0", "vfov = 200.0") + SPHERE;
+    match load_error("scene_invalid_camera", &scene) {
+        SceneError::Invalid { line, .. } => assert_eq!(line, 1),
+        error => panic!("unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn syntax_errors_and_unknown_keys_report_their_line() {
+    for (test, body, line) in [
+        ("scene_syntax_error", "[[objects]\n", 10),
+        ("scene_unknown_key", "[[objects]]\ntype = \"sphere\"\nradius = 1.0\ncenter = [0.0, 0.0, 0.0]\nmaterial = \"white\"\ncolour = 1.0\n", 10),
+    ] {
+        let scene = format!("{}{}", HEADER, body);
+        match load_error(test, &scene) {
+            error @ SceneError::Parse { .. } => {
+                let message = error.to_string();
+                assert!(
+                    message.contains(&format!("line {}", line)),
+                    "{}: {}",
+                    test,
+                    message
+                );
+            }
+            error => panic!("{}: unexpected error: {}", test, error),
+        }
+    }
+}
+
+#[test]
+fn mesh_errors_point_at_the_object() {
+    let scene = format!(
+        "{}{}\n[[objects]]\ntype = \"obj\"\npath = \"missing.obj\"\n",
+        HEADER, SPHERE
+    );
+    match load_error("mesh_errors_point_at_the_object", &scene) {
+        SceneError::Mesh { line, .. } => assert_eq!(line, 16),
+        error => panic!("unexpected error: {}", error),
+    }
+}
@@ -0,0 +1,73 @@
+//! The shading frame of anisotropic materials follows the surface.
+
+use std::sync::Arc;
+
+use cgmath::{point3, vec3, Deg, EuclideanSpace, InnerSpace, Vector3};
+use rand::SeedableRng;
+use raytracing_1w::{
+    hittable::{Hittable, RotateY},
+    microfacet::ShadingFrame,
+    ray::Ray,
+    sphere::Sphere,
+    Float, MyRng,
+};
+
+fn unit_sphere() -> Sphere {
+    Sphere {
+        center: point3(0.0, 0.0, 0.0),
+        radius: 1.0,
+        material: Arc::new(Box::new(())),
+    }
+}
+
+/// The first tangent of the shading frame where a ray from outside meets `object` at `normal`.
+fn frame_tangent(object: &dyn Hittable, normal: Vector3<Float>) -> Vector3<Float> {
+    let mut rng = MyRng::seed_from_u64(1);
+    let ray = Ray {
+        origin: point3(0.0, 0.0, 0.0) + 3.0 * normal,
+        direction: -normal,
+        time: 0.0,
+    };
+    let hit_record = object
+        .hit(&ray, 0.001, Float::INFINITY, &mut rng)
+        .expect("the ray meets the sphere");
+    assert!((hit_record.position.to_vec() - normal).magnitude() < 1e-9);
+    ShadingFrame::from_hit(&hit_record).to_world(vec3(1.0, 0.0, 0.0))
+}
+
+/// Walks around the equator, and from it towards a pole, checking that neighbouring hits have
+/// nearly the same tangent.
+fn assert_tangent_is_continuous(object: &dyn Hittable) {
+    const STEPS: usize = 720;
+
+    let paths: [fn(Float) -> Vector3<Float>; 2] = [
+        |a| vec3(a.cos(), 0.0, a.sin()),
+        |a| vec3(a.cos(), 0.9 * a.sin(), 0.1).normalize(),
+    ];
+    for path in paths.iter() {
+        let mut previous = frame_tangent(object, path(0.0));
+        for i in 1..=STEPS {
+            let normal = path(i as Float / STEPS as Float * std::f64::consts::PI);
+            let tangent = frame_tangent(object, normal);
+            assert!(tangent.dot(normal).abs() < 1e-9);
+            assert!(
+                tangent.dot(previous) > 0.99,
+                "the tangent jumps from {:?} to {:?} at {:?}",
+                previous,
+                tangent,
+                normal
+            );
+            previous = tangent;
+        }
+    }
+}
+
+#[test]
+fn sphere_tangent_is_continuous() {
+    assert_tangent_is_continuous(&unit_sphere());
+}
+
+#[test]
+fn rotated_sphere_tangent_is_continuous() {
+    assert_tangent_is_continuous(&RotateY::new(unit_sphere(), 0.0, 1.0, Deg(40.0)));
+}
@@ -0,0 +1,67 @@
+//! Colors survive the trip to spectra and back.
+
+use cgmath::vec3;
+use raytracing_1w::{color::Color, spectrum::SampledWavelengths, Float};
+
+/// The RGB of `color` upsampled and converted back, averaged over stratified wavelengths.
+fn round_trip(color: Color, terminate_secondary: bool) -> Color {
+    const SAMPLES: usize = 20_000;
+
+    let mut sum = vec3(0.0, 0.0, 0.0);
+    for i in 0..SAMPLES {
+        let mut wavelengths =
+            SampledWavelengths::sample_visible((i as Float + 0.5) / SAMPLES as Float);
+        if terminate_secondary {
+            wavelengths.terminate_secondary();
+        }
+        sum += wavelengths.to_rgb(wavelengths.upsample(color)).0;
+    }
+    Color(sum / SAMPLES as Float)
+}
+
+const COLORS: [[Float; 3]; 12] = [
+    [1.0, 1.0, 1.0],
+    [0.5, 0.5, 0.5],
+    [0.0, 0.0, 0.0],
+    [1.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0],
+    [0.0, 0.0, 1.0],
+    [0.0, 1.0, 1.0],
+    [1.0, 0.0, 1.0],
+    [1.0, 1.0, 0.0],
+    [0.8, 0.3, 0.1],
+    [0.2, 0.5, 0.9],
+    [4.0, 2.0, 3.0],
+];
+
+#[test]
+fn rgb_round_trips_through_spectra() {
+    for terminate_secondary in [false, true].iter() {
+        for rgb in COLORS.iter() {
+            let color = Color(vec3(rgb[0], rgb[1], rgb[2]));
+            let back = round_trip(color, *terminate_secondary);
+            let scale = rgb.iter().cloned().fold(1.0, Float::max);
+            for i in 0..3 {
+                assert!(
+                    (back.0[i] - color.0[i]).abs() < 0.01 * scale,
+                    "{:?} comes back as {:?}",
+                    color,
+                    back
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn upsampled_spectra_are_not_negative() {
+    for rgb in COLORS.iter() {
+        for i in 0..1000 {
+            let wavelengths = SampledWavelengths::sample_visible(i as Float / 1000.0);
+            let spectrum = wavelengths.upsample(Color(vec3(rgb[0], rgb[1], rgb[2])));
+            for j in 0..4 {
+                assert!(spectrum[j] >= 0.0, "{:?} at {:?}", rgb, wavelengths.lambda);
+            }
+        }
+    }
+}
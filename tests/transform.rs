@@ -0,0 +1,177 @@
+//! Affine transforms of points, hits and light sampling.
+
+use std::sync::Arc;
+
+use cgmath::{
+    point3, vec3, Deg, ElementWise, EuclideanSpace, InnerSpace, Point3, SquareMatrix, Vector3,
+};
+use rand::{Rng, SeedableRng};
+use raytracing_1w::{
+    hittable::Hittable,
+    math::random_in_unit_sphere,
+    ray::Ray,
+    sphere::Sphere,
+    transform::{Affine, Transform},
+    Float, MyRng,
+};
+
+fn unit_sphere() -> Sphere {
+    Sphere {
+        center: point3(0.0, 0.0, 0.0),
+        radius: 1.0,
+        material: Arc::new(Box::new(())),
+    }
+}
+
+fn assert_near(a: Point3<Float>, b: Point3<Float>) {
+    assert!((a - b).magnitude() < 1e-12, "{:?} != {:?}", a, b);
+}
+
+#[test]
+fn steps_apply_in_order_and_invert() {
+    let transform = Affine::identity()
+        .scale(vec3(2.0, 1.0, 1.0))
+        .rotate_z(Deg(90.0))
+        .translate(vec3(1.0, 0.0, 0.0));
+
+    // Scaled to (2, 0, 0), turned onto the y axis, then moved along x.
+    assert_near(
+        transform.transform_point(point3(1.0, 0.0, 0.0)),
+        point3(1.0, 2.0, 0.0),
+    );
+    assert_near(
+        transform.inverse().transform_point(point3(1.0, 2.0, 0.0)),
+        point3(1.0, 0.0, 0.0),
+    );
+    assert!(transform
+        .then(transform.inverse())
+        .matrix()
+        .eq(&SquareMatrix::identity()));
+
+    let composed = Affine::identity()
+        .rotate(vec3(1.0, 1.0, 0.0), Deg(30.0))
+        .translate(vec3(0.0, 0.0, 2.0))
+        .then(transform);
+    let mut rng = MyRng::seed_from_u64(1);
+    for _ in 0..100 {
+        let p = Point3::from_vec(10.0 * random_in_unit_sphere(&mut rng));
+        assert_near(
+            composed
+                .inverse()
+                .transform_point(composed.transform_point(p)),
+            p,
+        );
+    }
+}
+
+/// An ellipsoid with semi-axes `AXES` around `CENTER`, as a transformed unit sphere.
+const AXES: [Float; 3] = [2.0, 0.5, 1.0];
+const CENTER: [Float; 3] = [1.0, -1.0, 3.0];
+
+fn ellipsoid() -> Transform<Sphere> {
+    let transform = Affine::identity()
+        .scale(vec3(AXES[0], AXES[1], AXES[2]))
+        .translate(vec3(CENTER[0], CENTER[1], CENTER[2]));
+    Transform::new(unit_sphere(), transform, 0.0, 1.0)
+}
+
+#[test]
+fn scaled_sphere_hits_lie_on_the_ellipsoid_with_its_normals() {
+    let ellipsoid = ellipsoid();
+    let center = point3(CENTER[0], CENTER[1], CENTER[2]);
+    let axes = vec3(AXES[0], AXES[1], AXES[2]);
+    let aabb = ellipsoid.bounding_box(0.0, 1.0).unwrap();
+    let mut rng = MyRng::seed_from_u64(2);
+
+    let mut hits = 0;
+    for _ in 0..2000 {
+        let origin = center + 6.0 * random_in_unit_sphere(&mut rng);
+        let target = center + random_in_unit_sphere(&mut rng).mul_element_wise(axes);
+        let ray = Ray {
+            origin,
+            direction: (target - origin) * rng.gen_range(0.5..2.0),
+            time: 0.0,
+        };
+        let hit_record = match ellipsoid.hit(&ray, 0.001, Float::INFINITY, &mut rng) {
+            Some(hit_record) => hit_record,
+            None => continue,
+        };
+        hits += 1;
+
+        let local = (hit_record.position - center).div_element_wise(axes);
+        assert!((local.magnitude() - 1.0).abs() < 1e-9);
+        assert_near(hit_record.position, ray.at(hit_record.t));
+        for c in 0..3 {
+            assert!(aabb.minimum[c] <= hit_record.position[c]);
+            assert!(hit_record.position[c] <= aabb.maximum[c]);
+        }
+
+        // The gradient of the implicit surface, turned to face the ray.
+        let gradient = local.div_element_wise(axes).normalize();
+        let outside = ray.direction.dot(gradient) < 0.0;
+        assert_eq!(hit_record.front_face, outside);
+        let expected = if outside { gradient } else { -gradient };
+        assert!((hit_record.normal - expected).magnitude() < 1e-9);
+    }
+    assert!(hits > 1000);
+}
+
+#[test]
+fn transformed_light_pdf_integrates_to_one() {
+    const SAMPLES: usize = 200_000;
+
+    let rotation = Affine::identity().rotate(vec3(1.0, 2.0, 3.0), Deg(40.0));
+    let light = Transform::new(ellipsoid(), rotation, 0.0, 1.0);
+    // Just past the end of the shortest axis, where the light fills much of the view.
+    let origin = rotation.transform_point(point3(CENTER[0], CENTER[1] + 1.0, CENTER[2]));
+    let mut rng = MyRng::seed_from_u64(3);
+
+    let sum: Float = (0..SAMPLES)
+        .map(|_| {
+            let v = random_in_unit_sphere(&mut rng).normalize();
+            light.pdf_value(origin, v, 0.0, &mut rng)
+        })
+        .sum();
+    let integral = sum / SAMPLES as Float * 4.0 * std::f64::consts::PI;
+    assert!((integral - 1.0).abs() < 0.02, "{}", integral);
+
+    // Sampled directions hit the light and have a density.
+    for _ in 0..1000 {
+        let v = light.random(origin, 0.0, &mut rng);
+        let ray = Ray {
+            origin,
+            direction: v,
+            time: 0.0,
+        };
+        assert!(light.hit(&ray, 0.001, Float::INFINITY, &mut rng).is_some());
+        assert!(light.pdf_value(origin, v, 0.0, &mut rng) > 0.0);
+    }
+}
+
+#[test]
+fn uniformly_scaled_sphere_has_the_pdf_of_the_bigger_sphere() {
+    let transform = Affine::identity()
+        .uniform_scale(2.5)
+        .rotate_y(Deg(70.0))
+        .translate(vec3(0.0, 1.0, 4.0));
+    let light = Transform::new(unit_sphere(), transform, 0.0, 1.0);
+    let sphere = Sphere {
+        center: point3(0.0, 1.0, 4.0),
+        radius: 2.5,
+        material: Arc::new(Box::new(())),
+    };
+    let origin = point3(1.0, -2.0, -3.0);
+    let mut rng = MyRng::seed_from_u64(4);
+
+    for _ in 0..1000 {
+        let v: Vector3<Float> = sphere.random(origin, 0.0, &mut rng);
+        let expected = sphere.pdf_value(origin, v, 0.0, &mut rng);
+        let pdf = light.pdf_value(origin, v, 0.0, &mut rng);
+        assert!(
+            (pdf - expected).abs() < 1e-9 * expected,
+            "{} != {}",
+            pdf,
+            expected
+        );
+    }
+}